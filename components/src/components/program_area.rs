@@ -5,6 +5,8 @@ use yew::prelude::*;
 pub struct ProgramAreaProps {
     pub on_assemble: Callback<String>,
     pub on_step: Callback<()>,
+    pub on_step_over: Callback<()>,
+    pub on_step_out: Callback<()>,
     pub on_run: Callback<()>,
     pub on_reset: Callback<()>,
     pub assembly_output: Option<Html>,
@@ -51,6 +53,20 @@ pub fn program_area(props: &ProgramAreaProps) -> Html {
         })
     };
 
+    let on_step_over_click = {
+        let on_step_over = props.on_step_over.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_step_over.emit(());
+        })
+    };
+
+    let on_step_out_click = {
+        let on_step_out = props.on_step_out.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_step_out.emit(());
+        })
+    };
+
     let on_run_click = {
         let on_run = props.on_run.clone();
         Callback::from(move |_: MouseEvent| {
@@ -81,6 +97,8 @@ pub fn program_area(props: &ProgramAreaProps) -> Html {
             <div class="controls">
                 <button id="assembleBtn" onclick={on_assemble_click}>{"Assemble"}</button>
                 <button id="stepBtn" onclick={on_step_click} disabled={!props.step_enabled}>{"Step"}</button>
+                <button id="stepOverBtn" onclick={on_step_over_click} disabled={!props.step_enabled}>{"Step Over"}</button>
+                <button id="stepOutBtn" onclick={on_step_out_click} disabled={!props.step_enabled}>{"Step Out"}</button>
                 <button id="runBtn" onclick={on_run_click} disabled={!props.run_enabled}>{"Run"}</button>
                 <button id="resetBtn" onclick={on_reset_click}>{"Reset"}</button>
             </div>
@@ -1,29 +1,33 @@
-use crate::assembler::assemble;
-use crate::cpu::{execute_instruction, Cpu, Instruction};
-use components::{
-    Header, LegendItem, MemoryViewer, Modal, ProgramArea, Register, RegisterPanel, Sidebar,
-    SidebarButton,
-};
+mod assembly;
+mod challenges;
+mod examples;
+mod registers;
+mod stepping;
+
+use crate::assembler::{Diagnostic, SourceMap, Symbol, assemble};
+use crate::challenge;
+use crate::cpu::{CallStack, Cpu};
+use components::{Header, MemoryViewer, Modal, ProgramArea, Sidebar, SidebarButton};
+use stepping::use_stepper;
 use yew::prelude::*;
 
 #[function_component(App)]
 pub fn app() -> Html {
     // CPU state
     let cpu = use_state(|| Cpu::new());
-    let program_size = use_state(|| 0usize);
-    let assembly_lines = use_state(|| Vec::<String>::new());
+    // The last successful assembly, for checking challenges
+    let program = use_state(challenge::Solution::default);
+    // Assembly output lines paired with the main-file line they came from
+    let assembly_lines = use_state(Vec::<(usize, String)>::new);
+    let source_map = use_state(SourceMap::default);
+    let symbols = use_state(Vec::<Symbol>::new);
+    let call_stack = use_state(CallStack::default);
     let error_message = use_state(|| None::<String>);
-    let last_registers = use_state(|| vec![0u16; 16]);
-    let last_d = use_state(|| 0u8);
-    let last_p = use_state(|| 0u8);
-    let last_x = use_state(|| 0u8);
+    let diagnostics = use_state(Vec::<(Diagnostic, String)>::new);
+    let stepper = use_stepper(&cpu, &call_stack, &error_message);
 
     // Editor code state
-    let editor_code = use_state(|| {
-        String::from(
-            "; Add two numbers\nLDI 0x05    ; D = 5\nPHI R1      ; R1.high = 5\nLDI 0x0A    ; D = 10\nPHI R2      ; R2.high = 10\nGHI R1      ; D = R1.high\nSTR R3      ; Memory[R3] = D\nGHI R2      ; D = R2.high\nADD         ; D = D + Memory[R3]\nIDL         ; Stop execution (Idle)",
-        )
-    });
+    let editor_code = use_state(|| examples::EXAMPLES[0].1.to_string());
 
     // Modal states
     let tutorial_open = use_state(|| false);
@@ -37,294 +41,164 @@ pub fn app() -> Html {
     let current_challenge = use_state(|| None::<usize>);
     let challenge_result = use_state(|| None::<String>);
 
-    // Example programs
-    let examples = vec![
-        (
-            "Example 1: Simple Addition",
-            "; Add two numbers\nLDI 0x05    ; D = 5\nPHI R1      ; R1.high = 5\nLDI 0x0A    ; D = 10\nPHI R2      ; R2.high = 10\nGHI R1      ; D = R1.high\nSTR R3      ; Memory[R3] = D\nGHI R2      ; D = R2.high\nADD         ; D = D + Memory[R3]\nIDL         ; Stop execution (Idle)",
-        ),
-        (
-            "Example 2: Register Loading",
-            "; Load immediate values\nLDI 0xFF    ; D = 255\nPLO R5      ; R5.low = 255\nLDI 0x12    ; D = 18\nPHI R5      ; R5.high = 18\nGLO R5      ; D = R5.low (255)\nIDL         ; Stop execution",
-        ),
-        (
-            "Example 3: Loop (Count to 10)",
-            "; Count from 0 to 10 in R5 low byte\nLDI 0x00    ; D = 0\nPLO R5      ; R5.low = 0 (counter in LOW byte)\nLOOP:\nINC R5      ; R5++\nGLO R5      ; D = R5.low (not high byte!)\nXRI 0x0A    ; D = D XOR 10\nBNZ LOOP    ; Branch if not zero (loop while D != 0)\nIDL         ; Stop when R5.low = 10",
-        ),
-        (
-            "Example 4: Conditional Branch",
-            "; Branch based on comparison\nLDI 0x42    ; D = 66\nPHI R4      ; R4.high = 66\nLDI 0x42    ; D = 66\nXRI 0x42    ; D = D XOR 66 (result = 0)\nBZ EQUAL    ; Branch if zero (they're equal)\nLDI 0xFF    ; Not equal path\nIDL\nEQUAL:\nLDI 0x00    ; Equal path (D = 0)\nIDL",
-        ),
-    ];
-
-    // Challenge definitions with detailed validation
-    let challenges = use_memo((), |_| {
-        vec![
-            (
-                "Challenge 1: Load a Value",
-                "Load the value 42 (0x2A) into register R1's high byte and halt.",
-                |cpu: &Cpu| -> Result<String, String> {
-                    if !cpu.halted {
-                        return Err(
-                            "❌ CPU not halted. Make sure your program ends with IDL.".to_string()
-                        );
-                    }
-                    let r1_high = cpu.registers[1] >> 8;
-                    if r1_high != 0x2A {
-                        return Err(format!(
-                            "❌ R1.high = 0x{:02X} (expected 0x2A). Use LDI 0x2A followed by PHI R1.",
-                            r1_high
-                        ));
-                    }
-                    Ok(
-                        "✅ Challenge completed! You successfully loaded 42 into R1's high byte!"
-                            .to_string(),
-                    )
-                } as fn(&Cpu) -> Result<String, String>,
-            ),
-            (
-                "Challenge 2: Simple Addition",
-                "Add 5 + 7 and store the result (12) in R2's high byte, then halt.",
-                |cpu: &Cpu| -> Result<String, String> {
-                    if !cpu.halted {
-                        return Err(
-                            "❌ CPU not halted. Make sure your program ends with IDL.".to_string()
-                        );
-                    }
-                    let r2_high = cpu.registers[2] >> 8;
-                    if r2_high != 12 {
-                        return Err(format!(
-                            "❌ R2.high = {} (expected 12). Remember: 5 + 7 = 12.",
-                            r2_high
-                        ));
-                    }
-                    Ok(
-                        "✅ Challenge completed! You correctly added 5 + 7 and stored 12!"
-                            .to_string(),
-                    )
-                } as fn(&Cpu) -> Result<String, String>,
-            ),
-            (
-                "Challenge 3: Memory Write",
-                "Store the value 0xFF at the memory location pointed to by R3 (initially 0x0010), then halt.",
-                |cpu: &Cpu| -> Result<String, String> {
-                    if !cpu.halted {
-                        return Err(
-                            "❌ CPU not halted. Make sure your program ends with IDL.".to_string()
-                        );
-                    }
-                    let mem_val = cpu.read_byte(0x0010).unwrap_or(0);
-                    if mem_val != 0xFF {
-                        return Err(format!(
-                            "❌ Memory[0x0010] = 0x{:02X} (expected 0xFF). Use LDI 0xFF, then STR R3 to write to memory.",
-                            mem_val
-                        ));
-                    }
-                    Ok(
-                        "✅ Challenge completed! You successfully wrote 0xFF to memory!"
-                            .to_string(),
-                    )
-                } as fn(&Cpu) -> Result<String, String>,
-            ),
-            (
-                "Challenge 4: Count to Five",
-                "Use a loop to count from 0 to 5, storing the final value (5) in R4's low byte, then halt.\n\nSuccess criteria:\n- CPU halted (program ends with IDL)\n- R4.low = 5 (count reached exactly 5)",
-                |cpu: &Cpu| -> Result<String, String> {
-                    if !cpu.halted {
-                        return Err(
-                            "❌ CPU not halted. Make sure your loop exits and ends with IDL."
-                                .to_string(),
-                        );
-                    }
-                    let r4_low = cpu.registers[4] & 0xFF;
-                    if r4_low < 5 {
-                        return Err(format!(
-                            "❌ R4.low = {} (expected 5). Your loop stopped too early. Check your loop condition.",
-                            r4_low
-                        ));
-                    } else if r4_low > 5 {
-                        return Err(format!(
-                            "❌ R4.low = {} (expected 5). Your loop counted too high. Check your exit condition (should stop when R4.low = 5).",
-                            r4_low
-                        ));
-                    }
-                    Ok("✅ Challenge completed! Your loop correctly counted to 5!".to_string())
-                } as fn(&Cpu) -> Result<String, String>,
-            ),
-        ]
-    });
+    // Challenges are defined in src/challenge/builtin.toml
+    let challenges = use_memo((), |_| challenge::builtin());
 
     // Event handlers
     let handle_assemble = {
         let cpu = cpu.clone();
-        let program_size = program_size.clone();
+        let program = program.clone();
+        let challenges = challenges.clone();
+        let current_challenge = current_challenge.clone();
         let assembly_lines = assembly_lines.clone();
+        let source_map = source_map.clone();
+        let symbols = symbols.clone();
+        let call_stack = call_stack.clone();
         let error_message = error_message.clone();
+        let diagnostics = diagnostics.clone();
 
         Callback::from(move |code: String| {
             error_message.set(None);
 
             match assemble(&code) {
                 Ok(output) => {
-                    let mut new_cpu = Cpu::new();
-                    if let Err(e) = new_cpu.load_program(&output.machine_code, 0) {
-                        error_message.set(Some(format!("Failed to load program: {}", e)));
-                        return;
-                    }
-
-                    new_cpu.p = 0;
-                    new_cpu.registers[0] = 0;
-                    new_cpu.halted = false;
+                    // A challenge sets up registers, memory and input ports
+                    let loaded = match (*current_challenge).and_then(|i| challenges.get(i)) {
+                        Some(challenge) => challenge.initial_cpu(&output.machine_code),
+                        None => {
+                            let mut new_cpu = Cpu::new();
+                            new_cpu
+                                .load_program(&output.machine_code, 0)
+                                .map(|_| new_cpu)
+                        }
+                    };
+                    let new_cpu = match loaded {
+                        Ok(new_cpu) => new_cpu,
+                        Err(e) => {
+                            error_message.set(Some(format!("Failed to load program: {}", e)));
+                            return;
+                        }
+                    };
 
-                    program_size.set(output.machine_code.len());
+                    program.set(challenge::Solution::assembled(&output));
                     cpu.set(new_cpu);
 
-                    // Store disassembly lines for highlighting
-                    assembly_lines.set(output.disassembly);
+                    assembly_lines.set(assembly::main_lines(&output));
+                    source_map.set(output.source_map);
+                    symbols.set(output.symbols);
+                    call_stack.set(CallStack::default());
+                    diagnostics.set(assembly::annotate(&code, &output.diagnostics));
                 }
                 Err(e) => {
-                    error_message.set(Some(format!("Assembly error: {}", e)));
+                    let count = e.diagnostics().iter().filter(|d| d.is_error()).count();
+                    if count > 0 {
+                        error_message.set(Some(format!(
+                            "Assembly failed with {} error(s) - see the assembly output",
+                            count
+                        )));
+                    } else {
+                        error_message.set(Some(format!("Assembly error: {}", e)));
+                    }
+                    assembly_lines.set(Vec::new());
+                    diagnostics.set(assembly::annotate(&code, e.diagnostics()));
                 }
             }
         })
     };
 
-    let handle_step = {
+    let handle_reset = {
         let cpu = cpu.clone();
+        let call_stack = call_stack.clone();
+        let program = program.clone();
+        let assembly_lines = assembly_lines.clone();
         let error_message = error_message.clone();
-        let last_registers = last_registers.clone();
-        let last_d = last_d.clone();
-        let last_p = last_p.clone();
-        let last_x = last_x.clone();
+        let challenge_result = challenge_result.clone();
+        let diagnostics = diagnostics.clone();
 
         Callback::from(move |_| {
+            cpu.set(Cpu::new());
+            call_stack.set(CallStack::default());
+            program.set(challenge::Solution::default());
+            assembly_lines.set(Vec::new());
+            diagnostics.set(Vec::new());
             error_message.set(None);
-
-            let mut new_cpu = (*cpu).clone();
-
-            if new_cpu.halted {
-                error_message.set(Some("CPU is halted".to_string()));
-                return;
-            }
-
-            // Save old state for change tracking
-            last_registers.set(new_cpu.registers.to_vec());
-            last_d.set(new_cpu.d);
-            last_p.set(new_cpu.p);
-            last_x.set(new_cpu.x);
-
-            let pc = new_cpu.get_pc();
-
-            // Decode instruction
-            let instr_bytes: Vec<u8> = (0..3)
-                .filter_map(|i| new_cpu.read_byte(pc.wrapping_add(i)).ok())
-                .collect();
-
-            if instr_bytes.is_empty() {
-                error_message.set(Some("Failed to read instruction".to_string()));
-                return;
-            }
-
-            let instruction = match Instruction::decode(&instr_bytes) {
-                Some(instr) => instr,
-                None => {
-                    error_message.set(Some("Failed to decode instruction".to_string()));
-                    return;
-                }
-            };
-
-            // Advance PC
-            let instruction_length = instruction.opcode.length() as u16;
-            new_cpu.set_pc(pc.wrapping_add(instruction_length));
-
-            // Execute
-            if let Err(e) = execute_instruction(&mut new_cpu, &instruction) {
-                error_message.set(Some(format!("Execution error: {}", e)));
-                return;
-            }
-
-            cpu.set(new_cpu);
+            challenge_result.set(None);
         })
     };
 
-    let handle_run = {
-        let cpu = cpu.clone();
-        let error_message = error_message.clone();
+    // Challenge check handler: run the assembled program through every test case
+    let handle_check_challenge = {
+        let program = program.clone();
+        let current_challenge = current_challenge.clone();
+        let challenge_result = challenge_result.clone();
+        let challenges = challenges.clone();
 
         Callback::from(move |_| {
-            error_message.set(None);
-
-            let mut new_cpu = (*cpu).clone();
-            let start_cycles = new_cpu.cycles;
-            let max_cycles = 10000u64;
-
-            while !new_cpu.halted && (new_cpu.cycles - start_cycles) < max_cycles {
-                let pc = new_cpu.get_pc();
-                let instr_bytes: Vec<u8> = (0..3)
-                    .filter_map(|i| new_cpu.read_byte(pc.wrapping_add(i)).ok())
-                    .collect();
-
-                if instr_bytes.is_empty() {
-                    break;
-                }
-
-                if let Some(instruction) = Instruction::decode(&instr_bytes) {
-                    let instruction_length = instruction.opcode.length() as u16;
-                    new_cpu.set_pc(pc.wrapping_add(instruction_length));
-
-                    if execute_instruction(&mut new_cpu, &instruction).is_err() {
-                        break;
-                    }
-                } else {
-                    break;
-                }
+            if let Some(challenge) = (*current_challenge).and_then(|i| challenges.get(i)) {
+                challenge_result.set(Some(challenges::check(challenge, &program)));
             }
+        })
+    };
 
-            cpu.set(new_cpu);
+    // Dismiss challenge result
+    let dismiss_result = {
+        let challenge_result = challenge_result.clone();
+        Callback::from(move |_| {
+            challenge_result.set(None);
         })
     };
 
-    let handle_reset = {
+    // Load an example into the editor, from a clean CPU
+    let select_example = {
+        let editor_code = editor_code.clone();
+        let examples_open = examples_open.clone();
         let cpu = cpu.clone();
-        let program_size = program_size.clone();
+        let call_stack = call_stack.clone();
         let assembly_lines = assembly_lines.clone();
+        let diagnostics = diagnostics.clone();
         let error_message = error_message.clone();
-        let challenge_result = challenge_result.clone();
 
-        Callback::from(move |_| {
+        Callback::from(move |idx: usize| {
+            // Reset CPU and clear assembly output
             cpu.set(Cpu::new());
-            program_size.set(0);
+            call_stack.set(CallStack::default());
             assembly_lines.set(Vec::new());
+            diagnostics.set(Vec::new());
             error_message.set(None);
-            challenge_result.set(None);
+
+            // Load new code
+            editor_code.set(examples::EXAMPLES[idx].1.to_string());
+            examples_open.set(false);
         })
     };
 
-    // Challenge check handler
-    let handle_check_challenge = {
+    // Start a challenge from a clean CPU and its title and description
+    let select_challenge = {
+        let editor_code = editor_code.clone();
+        let challenges_open = challenges_open.clone();
+        let challenges = challenges.clone();
         let cpu = cpu.clone();
+        let program = program.clone();
+        let call_stack = call_stack.clone();
+        let assembly_lines = assembly_lines.clone();
+        let diagnostics = diagnostics.clone();
+        let error_message = error_message.clone();
         let current_challenge = current_challenge.clone();
         let challenge_result = challenge_result.clone();
-        let challenges_clone = challenges.clone();
 
-        Callback::from(move |_| {
-            if let Some(idx) = *current_challenge {
-                if idx < challenges_clone.len() {
-                    let (_title, _description, validator) = challenges_clone[idx];
-                    match validator(&cpu) {
-                        Ok(success_msg) => challenge_result.set(Some(success_msg)),
-                        Err(error_msg) => challenge_result.set(Some(error_msg)),
-                    }
-                }
-            }
-        })
-    };
-
-    // Dismiss challenge result
-    let dismiss_result = {
-        let challenge_result = challenge_result.clone();
-        Callback::from(move |_| {
+        Callback::from(move |idx: usize| {
+            // Reset CPU and clear assembly output
+            cpu.set(Cpu::new());
+            program.set(challenge::Solution::default());
+            call_stack.set(CallStack::default());
+            assembly_lines.set(Vec::new());
+            diagnostics.set(Vec::new());
+            error_message.set(None);
             challenge_result.set(None);
+
+            current_challenge.set(Some(idx));
+            editor_code.set(challenges::starting_code(&challenges[idx]));
+            challenges_open.set(false);
         })
     };
 
@@ -403,64 +277,6 @@ pub fn app() -> Html {
         },
     ];
 
-    // Build register list
-    let registers: Vec<Register> = (0..16)
-        .map(|i| {
-            let changed = cpu.registers[i] != (*last_registers)[i];
-            Register {
-                name: format!("R{:X}", i),
-                value: format!("0x{:04X}", cpu.registers[i]),
-                changed,
-            }
-        })
-        .collect();
-
-    // Build legend items
-    let legend_items = vec![
-        LegendItem {
-            label: "D = Accumulator".to_string(),
-            value: format!("0x{:02X}", cpu.d),
-            changed: cpu.d != *last_d,
-        },
-        LegendItem {
-            label: "P = PC Selector".to_string(),
-            value: format!("R{:X}", cpu.p),
-            changed: cpu.p != *last_p,
-        },
-        LegendItem {
-            label: "X = Index Selector".to_string(),
-            value: format!("R{:X}", cpu.x),
-            changed: cpu.x != *last_x,
-        },
-        LegendItem {
-            label: "DF = Data Flag".to_string(),
-            value: if cpu.df {
-                "1".to_string()
-            } else {
-                "0".to_string()
-            },
-            changed: false,
-        },
-        LegendItem {
-            label: "Q = Output".to_string(),
-            value: if cpu.q {
-                "1".to_string()
-            } else {
-                "0".to_string()
-            },
-            changed: false,
-        },
-        LegendItem {
-            label: "IE = Interrupts".to_string(),
-            value: if cpu.ie {
-                "ON".to_string()
-            } else {
-                "OFF".to_string()
-            },
-            changed: false,
-        },
-    ];
-
     // Get memory
     let memory: Vec<u8> = (0..128)
         .map(|addr| cpu.read_byte(addr).unwrap_or(0))
@@ -475,37 +291,17 @@ pub fn app() -> Html {
             <div class="main-content">
                 <ProgramArea
                     on_assemble={handle_assemble}
-                    on_step={handle_step}
-                    on_run={handle_run}
+                    on_step={stepper.step()}
+                    on_step_over={stepper.step_over()}
+                    on_step_out={stepper.step_out()}
+                    on_run={stepper.run()}
                     on_reset={handle_reset}
-                    assembly_output={if assembly_lines.is_empty() {
-                        None
-                    } else {
-                        let pc = cpu.get_pc();
-                        Some(html! {
-                            <div>
-                                {for assembly_lines.iter().map(|line| {
-                                    // Parse address from line (format: "0000: F8 05 | LDI 0x05")
-                                    let addr_str = line.split(':').next().unwrap_or("");
-                                    let is_current = if let Ok(addr) = u16::from_str_radix(addr_str, 16) {
-                                        addr == pc
-                                    } else {
-                                        false
-                                    };
-
-                                    let class = if is_current {
-                                        "assembly-line current"
-                                    } else {
-                                        "assembly-line"
-                                    };
-
-                                    html! {
-                                        <div class={class}>{line}</div>
-                                    }
-                                })}
-                            </div>
-                        })
-                    }}
+                    assembly_output={assembly::assembly_output(
+                        &diagnostics,
+                        &assembly_lines,
+                        &source_map,
+                        cpu.get_pc(),
+                    )}
                     initial_code={Some((*editor_code).clone())}
                     step_enabled={!cpu.halted}
                     run_enabled={!cpu.halted}
@@ -513,49 +309,7 @@ pub fn app() -> Html {
 
                 <div class="right-panels">
                     // Registers Panel
-                    <div class="registers-panel">
-                        <div class="panel-title">{"Registers & Flags"}</div>
-                        <div class="registers-container">
-                            <RegisterPanel
-                                registers={registers}
-                                legend_items={legend_items}
-                            />
-                        </div>
-
-                        // Flags
-                        <div class="flags">
-                            <div class="flag">
-                                <div class={if cpu.df { "flag-indicator set" } else { "flag-indicator" }}></div>
-                                <span>{"DF (Data Flag)"}</span>
-                            </div>
-                            <div class="flag">
-                                <div class={if cpu.q { "flag-indicator set" } else { "flag-indicator" }}></div>
-                                <span>{"Q (Output)"}</span>
-                            </div>
-                            <div class="flag">
-                                <div class={if cpu.ie { "flag-indicator set" } else { "flag-indicator" }}></div>
-                                <span>{"IE (Interrupt)"}</span>
-                            </div>
-                        </div>
-
-                        // CPU Status
-                        <div class="cpu-status">
-                            <div class="status-item">
-                                <span class="status-label">{"Cycles:"}</span>
-                                <span class="status-value">{cpu.cycles}</span>
-                            </div>
-                            <div class="status-item">
-                                <span class="status-label">{"Instructions:"}</span>
-                                <span class="status-value">{cpu.instructions_executed}</span>
-                            </div>
-                            <div class="status-item">
-                                <span class="status-label">{"Status:"}</span>
-                                <span class="status-value">
-                                    {if cpu.halted { "HALTED" } else { "RUNNING" }}
-                                </span>
-                            </div>
-                        </div>
-                    </div>
+                    {registers::registers_panel(&stepper, &symbols)}
 
                     // Memory Viewer
                     <MemoryViewer
@@ -568,38 +322,13 @@ pub fn app() -> Html {
                 </div>
             </div>
 
-            // Challenge status banner
-            if current_challenge.is_some() {
-                <div class="challenge-banner">
-                    <div class="challenge-info">
-                        <strong>{"Challenge Mode Active"}</strong>
-                        {if let Some(idx) = *current_challenge {
-                            format!(" - {}", challenges[idx].0)
-                        } else {
-                            String::new()
-                        }}
-                    </div>
-                    <button class="check-solution-btn" onclick={handle_check_challenge}>
-                        {"Check Solution"}
-                    </button>
-                </div>
-            }
-
-            // Challenge result banner
-            if let Some(result) = (*challenge_result).clone() {
-                <div class={if result.contains("✅") {
-                    "success-banner"
-                } else {
-                    "error-banner"
-                }}>
-                    <div class="banner-content">
-                        {result}
-                    </div>
-                    <button class="dismiss-btn" onclick={dismiss_result.clone()}>
-                        {"×"}
-                    </button>
-                </div>
-            }
+            // Challenge status and result banners
+            {challenges::challenge_banners(
+                (*current_challenge).and_then(|i| challenges.get(i)),
+                (*challenge_result).clone(),
+                handle_check_challenge,
+                dismiss_result,
+            )}
 
             // Error banner
             if let Some(err) = (*error_message).clone() {
@@ -656,34 +385,7 @@ pub fn app() -> Html {
                 active={*examples_open}
                 on_close={close_examples.clone()}
             >
-                <div class="examples-list">
-                    {for examples.iter().enumerate().map(|(idx, (title, code))| {
-                        let editor_code = editor_code.clone();
-                        let examples_open = examples_open.clone();
-                        let cpu = cpu.clone();
-                        let assembly_lines = assembly_lines.clone();
-                        let error_message = error_message.clone();
-                        let code = code.to_string();
-
-                        let load_example = Callback::from(move |_: MouseEvent| {
-                            // Reset CPU and clear assembly output
-                            cpu.set(Cpu::new());
-                            assembly_lines.set(Vec::new());
-                            error_message.set(None);
-
-                            // Load new code
-                            editor_code.set(code.clone());
-                            examples_open.set(false);
-                        });
-
-                        html! {
-                            <div class="example-item" key={idx} onclick={load_example}>
-                                <h4>{title}</h4>
-                                <p>{"Click to load this example"}</p>
-                            </div>
-                        }
-                    })}
-                </div>
+                {examples::example_list(&select_example)}
             </Modal>
 
             // Challenges Modal
@@ -693,41 +395,7 @@ pub fn app() -> Html {
                 active={*challenges_open}
                 on_close={close_challenges.clone()}
             >
-                <div class="challenges-list">
-                    {for challenges.iter().enumerate().map(|(idx, (title, description, _validator))| {
-                        let editor_code = editor_code.clone();
-                        let challenges_open = challenges_open.clone();
-                        let cpu = cpu.clone();
-                        let assembly_lines = assembly_lines.clone();
-                        let error_message = error_message.clone();
-                        let current_challenge = current_challenge.clone();
-                        let challenge_result = challenge_result.clone();
-                        let title_str = title.to_string();
-                        let desc_str = description.to_string();
-
-                        let load_challenge = Callback::from(move |_: MouseEvent| {
-                            // Reset CPU and clear assembly output
-                            cpu.set(Cpu::new());
-                            assembly_lines.set(Vec::new());
-                            error_message.set(None);
-                            challenge_result.set(None);
-
-                            // Set current challenge
-                            current_challenge.set(Some(idx));
-
-                            // Load empty code (user writes solution)
-                            editor_code.set(format!("; {}\n; {}\n\n", title_str, desc_str));
-                            challenges_open.set(false);
-                        });
-
-                        html! {
-                            <div class="challenge-item" key={idx} onclick={load_challenge}>
-                                <h4>{title}</h4>
-                                <p>{description}</p>
-                            </div>
-                        }
-                    })}
-                </div>
+                {challenges::challenge_list(&challenges, &select_challenge)}
             </Modal>
 
             // ISA Reference Modal
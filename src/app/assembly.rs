@@ -0,0 +1,83 @@
+use crate::assembler::{AssembleOptions, AssemblyOutput, Diagnostic, SourceMap};
+use yew::prelude::*;
+
+/// Name the editor's source is assembled under
+fn main_file() -> String {
+    AssembleOptions::default().file_name
+}
+
+/// Each diagnostic with its caret-annotated rendering, quoting the editor's
+/// `code` for those in the main file
+pub(super) fn annotate(code: &str, diagnostics: &[Diagnostic]) -> Vec<(Diagnostic, String)> {
+    let main_file = main_file();
+    let source_lines: Vec<&str> = code.lines().collect();
+    diagnostics
+        .iter()
+        .map(|d| {
+            let line = match d.file == main_file {
+                true => source_lines.get(d.line.wrapping_sub(1)).copied(),
+                false => None,
+            };
+            (d.clone(), d.render(line.unwrap_or("")))
+        })
+        .collect()
+}
+
+/// The main file's disassembly, each entry with the line it came from;
+/// the SCRT runtime and included files are left out
+pub(super) fn main_lines(output: &AssemblyOutput) -> Vec<(usize, String)> {
+    // Every line that emitted bytes has one disassembly entry
+    output
+        .lines
+        .iter()
+        .filter(|l| !l.bytes.is_empty())
+        .zip(&output.disassembly)
+        .filter(|(l, _)| l.file == 0)
+        .map(|(l, text)| (l.line, text.clone()))
+        .collect()
+}
+
+/// The assembly output pane: diagnostics, then the disassembly with the
+/// statement the PC is in highlighted, even mid-expansion
+pub(super) fn assembly_output(
+    diagnostics: &[(Diagnostic, String)],
+    lines: &[(usize, String)],
+    source_map: &SourceMap,
+    pc: u16,
+) -> Option<Html> {
+    if lines.is_empty() && diagnostics.is_empty() {
+        return None;
+    }
+    let main_file = main_file();
+    let current_line = source_map
+        .location(pc)
+        .filter(|l| l.file == main_file)
+        .map(|l| l.line);
+    Some(html! {
+        <div>
+            {for diagnostics.iter().map(|(d, rendered)| {
+                let class = if d.is_error() {
+                    "diagnostic error"
+                } else {
+                    "diagnostic warning"
+                };
+                html! {
+                    <pre class={class} data-line={d.line.to_string()}>{rendered}</pre>
+                }
+            })}
+            {for lines.iter().map(|(number, line)| {
+                let is_current = current_line == Some(*number);
+
+                let class = if is_current {
+                    "assembly-line current"
+                } else {
+                    "assembly-line"
+                };
+
+                html! {
+                    <div class={class}>{line}</div>
+                }
+            })}
+        </div>
+    })
+}
@@ -0,0 +1,107 @@
+use crate::challenge::{self, Challenge, Solution};
+use yew::prelude::*;
+
+/// The editor's starting text: the title and description as comments, one
+/// per line
+pub(super) fn starting_code(challenge: &Challenge) -> String {
+    let header = std::iter::once(challenge.title.as_str())
+        .chain(challenge.description.lines())
+        .map(|line| format!("; {}", line).trim_end().to_string() + "\n")
+        .collect::<String>();
+    format!("{}\n", header)
+}
+
+/// What to tell the player about `solution`: the check's summary, and the
+/// score of an accepted solution, kept as a personal best in localStorage
+pub(super) fn check(challenge: &Challenge, solution: &Solution) -> String {
+    if solution.image.is_empty() {
+        return "❌ Assemble your program first.".to_string();
+    }
+    let report = challenge.check(solution);
+    let mut message = report.summary.clone();
+    if let Some(score) = challenge::Score::of(&report) {
+        let reference = challenge.reference_score();
+        match challenge::record(&mut challenge::BrowserStorage, &challenge.id, score) {
+            Ok((board, new)) => {
+                let lines = challenge::describe(&score, reference.as_ref(), &board, new);
+                message.push_str(&format!("\n\n{}", lines));
+            }
+            Err(e) => message.push_str(&format!("\n\nScore not saved: {}", e)),
+        }
+    }
+    message
+}
+
+/// The Challenges modal's list, each entry with its test counts, size limit
+/// and the player's best
+pub(super) fn challenge_list(challenges: &[Challenge], on_select: &Callback<usize>) -> Html {
+    html! {
+        <div class="challenges-list">
+            {for challenges.iter().enumerate().map(|(idx, challenge)| {
+                let load_challenge = on_select.reform(move |_: MouseEvent| idx);
+
+                let visible = challenge.tests.iter().filter(|t| !t.hidden).count();
+                let hidden = challenge.tests.len() - visible;
+                let mut limits = format!("{} test(s)", visible);
+                if hidden > 0 {
+                    limits.push_str(&format!(" + {} hidden", hidden));
+                }
+                if let Some(max) = challenge.max_bytes {
+                    limits.push_str(&format!(" | at most {} bytes", max));
+                }
+                let board = challenge::leaderboard(&challenge::BrowserStorage, &challenge.id);
+                if let Some(best) = board.smallest {
+                    limits.push_str(&format!(" | your best: {}", best));
+                }
+
+                html! {
+                    <div class="challenge-item" key={idx} onclick={load_challenge}>
+                        <h4>{&challenge.title}</h4>
+                        <p>{&challenge.description}</p>
+                        <p class="challenge-limits">{limits}</p>
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
+
+/// The active challenge's banner with its Check Solution button, and the
+/// result of the last check
+pub(super) fn challenge_banners(
+    active: Option<&Challenge>,
+    result: Option<String>,
+    on_check: Callback<MouseEvent>,
+    on_dismiss: Callback<MouseEvent>,
+) -> Html {
+    html! {
+        <>
+            if let Some(challenge) = active {
+                <div class="challenge-banner">
+                    <div class="challenge-info">
+                        <strong>{"Challenge Mode Active"}</strong>
+                        {format!(" - {}", challenge.title)}
+                    </div>
+                    <button class="check-solution-btn" onclick={on_check}>
+                        {"Check Solution"}
+                    </button>
+                </div>
+            }
+
+            if let Some(result) = result {
+                <div class={if result.contains("✅") {
+                    "success-banner"
+                } else {
+                    "error-banner"
+                }}>
+                    <div class="banner-content">
+                        {result}
+                    </div>
+                    <button class="dismiss-btn" onclick={on_dismiss}>
+                        {"×"}
+                    </button>
+                </div>
+            }
+        </>
+    }
+}
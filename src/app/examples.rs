@@ -0,0 +1,38 @@
+use yew::prelude::*;
+
+/// Example programs: a title and the source loaded into the editor
+pub(super) const EXAMPLES: &[(&str, &str)] = &[
+    (
+        "Example 1: Simple Addition",
+        "; Add two numbers\nLDI 0x05    ; D = 5\nPHI R1      ; R1.high = 5\nLDI 0x0A    ; D = 10\nPHI R2      ; R2.high = 10\nGHI R1      ; D = R1.high\nSTR R3      ; Memory[R3] = D\nGHI R2      ; D = R2.high\nADD         ; D = D + Memory[R3]\nIDL         ; Stop execution (Idle)",
+    ),
+    (
+        "Example 2: Register Loading",
+        "; Load immediate values\nLDI 0xFF    ; D = 255\nPLO R5      ; R5.low = 255\nLDI 0x12    ; D = 18\nPHI R5      ; R5.high = 18\nGLO R5      ; D = R5.low (255)\nIDL         ; Stop execution",
+    ),
+    (
+        "Example 3: Loop (Count to 10)",
+        "; Count from 0 to 10 in R5 low byte\nLDI 0x00    ; D = 0\nPLO R5      ; R5.low = 0 (counter in LOW byte)\nLOOP:\nINC R5      ; R5++\nGLO R5      ; D = R5.low (not high byte!)\nXRI 0x0A    ; D = D XOR 10\nBNZ LOOP    ; Branch if not zero (loop while D != 0)\nIDL         ; Stop when R5.low = 10",
+    ),
+    (
+        "Example 4: Conditional Branch",
+        "; Branch based on comparison\nLDI 0x42    ; D = 66\nPHI R4      ; R4.high = 66\nLDI 0x42    ; D = 66\nXRI 0x42    ; D = D XOR 66 (result = 0)\nBZ EQUAL    ; Branch if zero (they're equal)\nLDI 0xFF    ; Not equal path\nIDL\nEQUAL:\nLDI 0x00    ; Equal path (D = 0)\nIDL",
+    ),
+];
+
+/// The Examples modal's list
+pub(super) fn example_list(on_select: &Callback<usize>) -> Html {
+    html! {
+        <div class="examples-list">
+            {for EXAMPLES.iter().enumerate().map(|(idx, (title, _))| {
+                let load_example = on_select.reform(move |_: MouseEvent| idx);
+                html! {
+                    <div class="example-item" key={idx} onclick={load_example}>
+                        <h4>{title}</h4>
+                        <p>{"Click to load this example"}</p>
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
@@ -0,0 +1,134 @@
+use super::stepping::Stepper;
+use crate::assembler::Symbol;
+use components::{LegendItem, Register, RegisterPanel};
+use yew::prelude::*;
+
+/// The Registers & Flags panel: registers and D, P and X highlighted when
+/// the last step changed them, the flags, the cycle counts and the call
+/// stack
+pub(super) fn registers_panel(stepper: &Stepper, symbols: &[Symbol]) -> Html {
+    let cpu = &*stepper.cpu;
+    let call_stack = &*stepper.call_stack;
+
+    // Build register list
+    let registers: Vec<Register> = (0..16)
+        .map(|i| {
+            let changed = cpu.registers[i] != (*stepper.last_registers)[i];
+            Register {
+                name: format!("R{:X}", i),
+                value: format!("0x{:04X}", cpu.registers[i]),
+                changed,
+            }
+        })
+        .collect();
+
+    // Build legend items
+    let legend_items = vec![
+        LegendItem {
+            label: "D = Accumulator".to_string(),
+            value: format!("0x{:02X}", cpu.d),
+            changed: cpu.d != *stepper.last_d,
+        },
+        LegendItem {
+            label: "P = PC Selector".to_string(),
+            value: format!("R{:X}", cpu.p),
+            changed: cpu.p != *stepper.last_p,
+        },
+        LegendItem {
+            label: "X = Index Selector".to_string(),
+            value: format!("R{:X}", cpu.x),
+            changed: cpu.x != *stepper.last_x,
+        },
+        LegendItem {
+            label: "DF = Data Flag".to_string(),
+            value: if cpu.df {
+                "1".to_string()
+            } else {
+                "0".to_string()
+            },
+            changed: false,
+        },
+        LegendItem {
+            label: "Q = Output".to_string(),
+            value: if cpu.q {
+                "1".to_string()
+            } else {
+                "0".to_string()
+            },
+            changed: false,
+        },
+        LegendItem {
+            label: "IE = Interrupts".to_string(),
+            value: if cpu.ie {
+                "ON".to_string()
+            } else {
+                "OFF".to_string()
+            },
+            changed: false,
+        },
+    ];
+
+    html! {
+        <div class="registers-panel">
+            <div class="panel-title">{"Registers & Flags"}</div>
+            <div class="registers-container">
+                <RegisterPanel
+                    registers={registers}
+                    legend_items={legend_items}
+                />
+            </div>
+
+            // Flags
+            <div class="flags">
+                <div class="flag">
+                    <div class={if cpu.df { "flag-indicator set" } else { "flag-indicator" }}></div>
+                    <span>{"DF (Data Flag)"}</span>
+                </div>
+                <div class="flag">
+                    <div class={if cpu.q { "flag-indicator set" } else { "flag-indicator" }}></div>
+                    <span>{"Q (Output)"}</span>
+                </div>
+                <div class="flag">
+                    <div class={if cpu.ie { "flag-indicator set" } else { "flag-indicator" }}></div>
+                    <span>{"IE (Interrupt)"}</span>
+                </div>
+            </div>
+
+            // CPU Status
+            <div class="cpu-status">
+                <div class="status-item">
+                    <span class="status-label">{"Cycles:"}</span>
+                    <span class="status-value">{cpu.cycles}</span>
+                </div>
+                <div class="status-item">
+                    <span class="status-label">{"Instructions:"}</span>
+                    <span class="status-value">{cpu.instructions_executed}</span>
+                </div>
+                <div class="status-item">
+                    <span class="status-label">{"Status:"}</span>
+                    <span class="status-value">
+                        {if cpu.halted { "HALTED" } else { "RUNNING" }}
+                    </span>
+                </div>
+            </div>
+
+            // SCRT and SEP-style calls, innermost first
+            if !call_stack.frames().is_empty() {
+                <div class="call-stack">
+                    <div class="legend-title">{"Call Stack"}</div>
+                    {for call_stack.frames().iter().rev().map(|frame| {
+                        let name = symbols
+                            .iter()
+                            .find(|s| s.value == frame.target)
+                            .map_or(format!("{:04X}", frame.target), |s| s.name.clone());
+                        html! {
+                            <div class="call-frame">
+                                {format!("{} (from {:04X})", name, frame.call_site)}
+                            </div>
+                        }
+                    })}
+                </div>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,125 @@
+use crate::cpu::{CallStack, Cpu, CpuError, StepStop};
+use yew::prelude::*;
+
+/// Machine cycles Run, Step Over and Step Out give up after
+const MAX_CYCLES: u64 = 10000;
+
+/// What the Step, Step Over, Step Out and Run buttons change: the CPU, its
+/// call stack, the error banner and the values the registers panel
+/// compares with to highlight changes
+#[derive(Clone)]
+pub(super) struct Stepper {
+    pub cpu: UseStateHandle<Cpu>,
+    pub call_stack: UseStateHandle<CallStack>,
+    pub error_message: UseStateHandle<Option<String>>,
+    pub last_registers: UseStateHandle<Vec<u16>>,
+    pub last_d: UseStateHandle<u8>,
+    pub last_p: UseStateHandle<u8>,
+    pub last_x: UseStateHandle<u8>,
+}
+
+/// A [`Stepper`] for `cpu` and its call stack, starting with nothing
+/// highlighted as changed
+#[hook]
+pub(super) fn use_stepper(
+    cpu: &UseStateHandle<Cpu>,
+    call_stack: &UseStateHandle<CallStack>,
+    error_message: &UseStateHandle<Option<String>>,
+) -> Stepper {
+    Stepper {
+        cpu: cpu.clone(),
+        call_stack: call_stack.clone(),
+        error_message: error_message.clone(),
+        last_registers: use_state(|| vec![0u16; 16]),
+        last_d: use_state(|| 0u8),
+        last_p: use_state(|| 0u8),
+        last_x: use_state(|| 0u8),
+    }
+}
+
+impl Stepper {
+    /// Execute one instruction
+    pub fn step(&self) -> Callback<()> {
+        self.callback(|cpu, stack| {
+            let result = stack.step(cpu);
+            result.err().map(|e| format!("Execution error: {}", e))
+        })
+    }
+
+    /// Execute one instruction, running any subroutine it calls to the end
+    pub fn step_over(&self) -> Callback<()> {
+        self.callback(|cpu, stack| stopped(stack.step_over(cpu, MAX_CYCLES)))
+    }
+
+    /// Run until the current subroutine returns
+    pub fn step_out(&self) -> Callback<()> {
+        self.callback(|cpu, stack| stopped(stack.step_out(cpu, MAX_CYCLES)))
+    }
+
+    /// Run until halted or out of cycles, without highlighting changes
+    pub fn run(&self) -> Callback<()> {
+        let cpu = self.cpu.clone();
+        let call_stack = self.call_stack.clone();
+        let error_message = self.error_message.clone();
+
+        Callback::from(move |_| {
+            error_message.set(None);
+
+            let mut new_cpu = (*cpu).clone();
+            let end = new_cpu.cycles + MAX_CYCLES;
+
+            let mut stack = (*call_stack).clone();
+            while !new_cpu.halted && new_cpu.cycles < end {
+                if stack.step(&mut new_cpu).is_err() {
+                    break;
+                }
+            }
+
+            call_stack.set(stack);
+            cpu.set(new_cpu);
+        })
+    }
+
+    /// A callback running `step` on a copy of the CPU and call stack, after
+    /// saving the registers it starts from; `step` returns any message for
+    /// the error banner
+    fn callback(&self, step: fn(&mut Cpu, &mut CallStack) -> Option<String>) -> Callback<()> {
+        let this = self.clone();
+        Callback::from(move |_| {
+            this.error_message.set(None);
+
+            let mut new_cpu = (*this.cpu).clone();
+
+            if new_cpu.halted {
+                this.error_message.set(Some("CPU is halted".to_string()));
+                return;
+            }
+
+            // Save old state for change tracking
+            this.last_registers.set(new_cpu.registers.to_vec());
+            this.last_d.set(new_cpu.d);
+            this.last_p.set(new_cpu.p);
+            this.last_x.set(new_cpu.x);
+
+            let mut stack = (*this.call_stack).clone();
+            if let Some(message) = step(&mut new_cpu, &mut stack) {
+                this.error_message.set(Some(message));
+            }
+
+            this.call_stack.set(stack);
+            this.cpu.set(new_cpu);
+        })
+    }
+}
+
+/// The error banner's message for how a step over or step out ended
+fn stopped(result: Result<StepStop, CpuError>) -> Option<String> {
+    match result {
+        Ok(StepStop::CycleLimit) => Some(format!(
+            "Subroutine still running after {} cycles",
+            MAX_CYCLES
+        )),
+        Ok(_) => None,
+        Err(e) => Some(format!("Execution error: {}", e)),
+    }
+}
@@ -0,0 +1,223 @@
+use super::AssemblyError;
+use super::encoder::{Encoder, Issue};
+use super::listing::Expansion;
+use super::parser::Token;
+use crate::cpu::isa::{self, OperandKind};
+use crate::linker::RelocationKind;
+
+impl Encoder<'_> {
+    /// Assemble a short branch instruction (opcode + offset)
+    ///
+    /// The branch replaces the low byte of R(P) while it points at the
+    /// operand byte, so the target must be on that byte's page. Small
+    /// numeric targets are taken as an offset within the page. In a
+    /// relocatable object the page of a label is only known after linking.
+    pub(crate) fn assemble_short_branch(
+        &mut self,
+        branch: ShortBranch,
+        operand: &Token,
+    ) -> Result<Vec<u8>, Issue> {
+        let target = self.resolve_field(operand, 1, RelocationKind::ShortBranch)?;
+        let page = branch.address.wrapping_add(1) & 0xFF00;
+        let label = self.labels.contains_key(&operand.text.to_uppercase());
+        let absolute = target > 0xFF || label;
+
+        if absolute && !(label && self.relocatable) && target & 0xFF00 != page {
+            self.off_page = true;
+            let mnemonic = branch.mnemonic.text.to_uppercase();
+            return Err(Issue {
+                suggestion: long_branch_for(&mnemonic).map(str::to_string),
+                ..Issue::error(
+                    branch.mnemonic.span,
+                    AssemblyError::BranchOffPage { target, page },
+                )
+            });
+        }
+
+        // For short branches, use low byte of address
+        Ok(vec![branch.opcode, target as u8])
+    }
+
+    /// Assemble the long replacement for a short branch whose target is on
+    /// another page
+    ///
+    /// Branches with a long equivalent become that instruction. The external
+    /// flag branches (B1-B4, BN1-BN4) have none, so they become the inverted
+    /// short branch over an `LBR` to the target.
+    pub(crate) fn assemble_relaxed_branch(
+        &mut self,
+        mnemonic: &str,
+        operand: &Token,
+        address: u16,
+    ) -> Result<Vec<u8>, Issue> {
+        let target = self.resolve_target(operand)?;
+
+        if let Some(long) = long_branch_for(mnemonic) {
+            let bytes = long_branch_bytes(long_branch_opcode(long), target);
+            self.expansion = vec![Expansion::instruction(
+                address,
+                bytes.clone(),
+                format!("{} {}", long, operand.text),
+            )];
+            return Ok(bytes);
+        }
+
+        let opcode = flag_branch_opcode(mnemonic).ok_or_else(|| {
+            Issue::error(
+                operand.span,
+                AssemblyError::InvalidInstruction(format!("{} cannot be relaxed", mnemonic)),
+            )
+        })?;
+        let skip_to = address.wrapping_add(5);
+        let page = address.wrapping_add(1) & 0xFF00;
+        if skip_to & 0xFF00 != page {
+            self.off_page = true;
+            return Err(Issue::error(
+                operand.span,
+                AssemblyError::BranchOffPage {
+                    target: skip_to,
+                    page,
+                },
+            ));
+        }
+
+        let inverted = match mnemonic.strip_prefix("BN") {
+            Some(flag) => format!("B{}", flag),
+            None => format!("BN{}", &mnemonic[1..]),
+        };
+        let skip = vec![opcode ^ 0x08, skip_to as u8];
+        let long = long_branch_bytes(0xC0, target);
+        self.expansion = vec![
+            Expansion::instruction(
+                address,
+                skip.clone(),
+                format!("{} {:#06X}", inverted, skip_to),
+            ),
+            Expansion::instruction(
+                address.wrapping_add(2),
+                long.clone(),
+                format!("LBR {}", operand.text),
+            ),
+        ];
+        Ok([skip, long].concat())
+    }
+}
+
+/// Length of a short branch once relaxed to its long form, if it can be
+pub(crate) fn relaxed_length(mnemonic: &str) -> Option<u16> {
+    let mnemonic = mnemonic.to_uppercase();
+    if long_branch_for(&mnemonic).is_some() {
+        Some(3)
+    } else if flag_branch_opcode(&mnemonic).is_some() {
+        Some(5)
+    } else {
+        None
+    }
+}
+
+/// Encode a long branch as big-endian: opcode, high byte, then low byte
+pub(crate) fn long_branch_bytes(opcode: u8, address: u16) -> Vec<u8> {
+    vec![opcode, (address >> 8) as u8, address as u8]
+}
+
+/// Long branch equivalent of a short branch
+fn long_branch_for(mnemonic: &str) -> Option<&'static str> {
+    match mnemonic {
+        "BR" => Some("LBR"),
+        "BQ" => Some("LBQ"),
+        "BZ" => Some("LBZ"),
+        "BDF" => Some("LBDF"),
+        "BNQ" => Some("LBNQ"),
+        "BNZ" => Some("LBNZ"),
+        "BNF" => Some("LBNF"),
+        _ => None,
+    }
+}
+
+fn long_branch_opcode(mnemonic: &str) -> u8 {
+    isa::lookup(mnemonic).map_or(0xC0, |info| info.code)
+}
+
+/// Opcode of an external flag branch, which has no long form
+fn flag_branch_opcode(mnemonic: &str) -> Option<u8> {
+    isa::lookup(mnemonic)
+        .filter(|info| info.operand == OperandKind::ShortBranch)
+        .filter(|_| long_branch_for(mnemonic).is_none())
+        .map(|info| info.code)
+}
+
+/// A short branch being encoded and where it sits
+pub(crate) struct ShortBranch<'t> {
+    pub opcode: u8,
+    pub mnemonic: &'t Token,
+    pub address: u16,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::{AssembleOptions, Span, assemble, assemble_with};
+
+    #[test]
+    fn test_short_branch_off_page() {
+        let source = "BR FAR\nORG 0x100\nFAR: IDL";
+        let err = assemble(source).unwrap_err();
+        let diagnostic = &err.diagnostics()[0];
+
+        assert_eq!(diagnostic.line, 1);
+        assert_eq!(diagnostic.span, Span::new(0, 2));
+        assert_eq!(diagnostic.suggestion.as_deref(), Some("LBR"));
+        assert!(diagnostic.message.contains("0x0100"));
+    }
+
+    #[test]
+    fn test_short_branch_page_is_operand_page() {
+        // Opcode at 0xFE, operand at 0xFF: still page 0, target on page 1
+        assert!(assemble("ORG 0xFE\nBR NEXT\nNEXT: IDL").is_err());
+
+        // Opcode at 0xFF, operand at 0x100: page 1, same as the target
+        let result = assemble("ORG 0xFF\nBR NEXT\nNEXT: IDL").unwrap();
+        assert_eq!(result.machine_code[0xFF..], [0x30, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_relax_branches() {
+        let options = AssembleOptions {
+            relax_branches: true,
+            ..Default::default()
+        };
+
+        let result = assemble_with("BR FAR\nB1 FAR\nORG 0x100\nFAR: IDL", &options).unwrap();
+        assert_eq!(
+            result.machine_code[..8],
+            [0xC0, 0x01, 0x00, 0x3C, 0x08, 0xC0, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_relaxation_cascades() {
+        let options = AssembleOptions {
+            relax_branches: true,
+            ..Default::default()
+        };
+
+        // Widening BZ pushes NEAR onto page 1, which then forces BR long too
+        let source = "ORG 0xFB\nBZ FAR\nBR NEAR\nNEAR: IDL\nORG 0x200\nFAR: IDL";
+        let result = assemble_with(source, &options).unwrap();
+        assert_eq!(
+            result.machine_code[0xFB..0x102],
+            [0xC2, 0x02, 0x00, 0xC0, 0x01, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_relaxed_flag_branch_skip_off_page() {
+        let options = AssembleOptions {
+            relax_branches: true,
+            ..Default::default()
+        };
+
+        // B1 becomes BN1 over LBR, whose skip target 0x101 is off page 0
+        let err = assemble_with("ORG 0x00FC\nB1 FAR\nORG 0x200\nFAR: IDL", &options).unwrap_err();
+        assert!(err.diagnostics()[0].message.contains("0x0101"), "{}", err);
+    }
+}
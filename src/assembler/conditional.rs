@@ -0,0 +1,470 @@
+//! Conditional assembly
+//!
+//! `IF expr`, `IFDEF name` and `IFNDEF name` open a block closed by `ENDIF`,
+//! with an optional `ELSE`. Lines in a block whose condition is false are
+//! dropped before layout, including any `INCLUDE` they contain. Blocks nest
+//! but may not span files.
+//!
+//! Conditions use symbols from [`AssembleOptions::defines`](super::AssembleOptions)
+//! and from `DEFINE NAME[=VALUE]` lines (the value defaults to 1). An
+//! expression is built from numbers, names, `DEFINED(name)`, the comparisons
+//! `== != < <= > >=`, `!`, `&&`, `||` and parentheses. A defined name stands
+//! for its value; any other name stands for itself, so with `TARGET=VIP`
+//! the condition `TARGET == VIP` holds. An undefined name on its own is
+//! false.
+
+use super::AssemblyError;
+use super::diagnostic::Span;
+use super::encoder::parse_number;
+use super::parser::ParsedLine;
+use std::collections::HashMap;
+
+/// Split `NAME=VALUE` (as given to `-D` or `DEFINE`) into its parts
+///
+/// Without `=` the value is `1`. Names are case-insensitive.
+pub fn parse_define(text: &str) -> (String, String) {
+    match text.split_once('=') {
+        Some((name, value)) => (name.trim().to_uppercase(), value.trim().to_string()),
+        None => (text.trim().to_uppercase(), "1".to_string()),
+    }
+}
+
+/// An open IF block
+#[derive(Debug)]
+struct Block {
+    /// Whether the enclosing code is being assembled
+    outer: bool,
+    /// Whether the branch currently being read is assembled
+    active: bool,
+    /// Whether ELSE has been seen
+    in_else: bool,
+    /// Where the block was opened, for unterminated-block errors
+    opened: (usize, usize, Span),
+}
+
+/// Tracks open conditional blocks and the symbols they test
+#[derive(Debug, Default)]
+pub(super) struct Conditions {
+    defines: HashMap<String, String>,
+    blocks: Vec<Block>,
+}
+
+/// A problem with a conditional directive, located within its line
+pub(super) type ConditionError = (Span, AssemblyError);
+
+impl Conditions {
+    pub fn new(defines: &HashMap<String, String>) -> Self {
+        Self {
+            defines: defines
+                .iter()
+                .map(|(name, value)| (name.to_uppercase(), value.clone()))
+                .collect(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Whether lines at this point are assembled
+    pub fn active(&self) -> bool {
+        self.blocks.last().is_none_or(|b| b.active)
+    }
+
+    /// Number of blocks open
+    pub fn depth(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Handle `line` if it is a conditional directive or DEFINE
+    ///
+    /// Returns whether it was one. Errors still leave the line handled.
+    pub fn directive(&mut self, line: &ParsedLine) -> Result<bool, ConditionError> {
+        let Some(mnemonic) = &line.mnemonic else {
+            return Ok(false);
+        };
+        let span = line.statement_span();
+        let operand = line
+            .operands
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        let outer = self.active();
+
+        match mnemonic.text.to_uppercase().as_str() {
+            "IF" | "IFDEF" | "IFNDEF" => {
+                let directive = mnemonic.text.to_uppercase();
+                // Conditions inside skipped code are not evaluated
+                let result = if !outer {
+                    Ok(false)
+                } else if operand.is_empty() {
+                    Err(AssemblyError::InvalidCondition(format!(
+                        "{} requires a condition",
+                        directive
+                    )))
+                } else {
+                    match directive.as_str() {
+                        "IFDEF" => Ok(self.is_defined(&operand)),
+                        "IFNDEF" => Ok(!self.is_defined(&operand)),
+                        _ => self.evaluate(&operand),
+                    }
+                };
+                let condition = *result.as_ref().unwrap_or(&false);
+                self.blocks.push(Block {
+                    outer,
+                    active: outer && condition,
+                    in_else: false,
+                    opened: (line.file, line.number, span),
+                });
+                result.map(|_| true).map_err(|e| (span, e))
+            }
+            "ELSE" => {
+                let block = self.blocks.last_mut().ok_or_else(|| {
+                    (
+                        span,
+                        AssemblyError::UnmatchedConditional("ELSE".to_string()),
+                    )
+                })?;
+                if block.in_else {
+                    return Err((
+                        span,
+                        AssemblyError::InvalidCondition("ELSE after ELSE".to_string()),
+                    ));
+                }
+                block.in_else = true;
+                block.active = block.outer && !block.active;
+                Ok(true)
+            }
+            "ENDIF" => {
+                self.blocks.pop().ok_or_else(|| {
+                    (
+                        span,
+                        AssemblyError::UnmatchedConditional("ENDIF".to_string()),
+                    )
+                })?;
+                Ok(true)
+            }
+            "DEFINE" => {
+                if outer {
+                    let (name, value) = parse_define(&operand);
+                    if name.is_empty() {
+                        return Err((
+                            span,
+                            AssemblyError::InvalidOperand("DEFINE requires a name".to_string()),
+                        ));
+                    }
+                    self.defines.insert(name, value);
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Close every block opened since `depth`, returning where each was opened
+    pub fn unterminated(&mut self, depth: usize) -> Vec<(usize, usize, Span)> {
+        self.blocks.drain(depth..).map(|b| b.opened).collect()
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.defines.contains_key(&name.trim().to_uppercase())
+    }
+
+    /// Evaluate a condition expression
+    pub fn evaluate(&self, text: &str) -> Result<bool, AssemblyError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Expr {
+            tokens: &tokens,
+            pos: 0,
+            conditions: self,
+        };
+        let value = parser.or()?;
+        match tokens.get(parser.pos) {
+            None => Ok(value.truth()),
+            Some(extra) => Err(AssemblyError::InvalidCondition(format!(
+                "unexpected `{}`",
+                extra
+            ))),
+        }
+    }
+}
+
+/// A value in a condition
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(u16),
+    /// A defined symbol's non-numeric value
+    Text(String),
+    /// A name that is not defined
+    Undefined(String),
+}
+
+impl Value {
+    fn truth(&self) -> bool {
+        match self {
+            Value::Number(n) => *n != 0,
+            Value::Text(text) => !text.is_empty(),
+            Value::Undefined(_) => false,
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        match self {
+            Value::Number(_) => None,
+            Value::Text(text) | Value::Undefined(text) => Some(text),
+        }
+    }
+
+    fn from_text(text: &str) -> Value {
+        if text.starts_with(|c: char| c.is_ascii_digit() || c == '$')
+            && let Ok(n) = parse_number(text)
+        {
+            return Value::Number(n);
+        }
+        Value::Text(text.to_uppercase())
+    }
+}
+
+const OPERATORS: &[&str] = &["==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")"];
+
+fn tokenize(text: &str) -> Result<Vec<String>, AssemblyError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(op.to_string());
+            rest = &rest[op.len()..];
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return Err(AssemblyError::InvalidCondition(format!(
+                    "unexpected `{}`",
+                    &rest[..rest.chars().next().map_or(0, char::len_utf8)]
+                )));
+            }
+            tokens.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive-descent parser that evaluates as it goes
+struct Expr<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    conditions: &'a Conditions,
+}
+
+impl Expr<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<&str, AssemblyError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| AssemblyError::InvalidCondition("unexpected end".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), AssemblyError> {
+        match self.next()? {
+            t if t == token => Ok(()),
+            t => Err(AssemblyError::InvalidCondition(format!(
+                "expected `{}`, found `{}`",
+                token, t
+            ))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Value, AssemblyError> {
+        let mut value = self.and()?;
+        while self.peek() == Some("||") {
+            self.pos += 1;
+            let right = self.and()?;
+            value = Value::Number((value.truth() || right.truth()) as u16);
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<Value, AssemblyError> {
+        let mut value = self.comparison()?;
+        while self.peek() == Some("&&") {
+            self.pos += 1;
+            let right = self.comparison()?;
+            value = Value::Number((value.truth() && right.truth()) as u16);
+        }
+        Ok(value)
+    }
+
+    fn comparison(&mut self) -> Result<Value, AssemblyError> {
+        let left = self.unary()?;
+        let Some(op) = self
+            .peek()
+            .filter(|op| ["==", "!=", "<", "<=", ">", ">="].contains(op))
+            .map(str::to_string)
+        else {
+            return Ok(left);
+        };
+        self.pos += 1;
+        let right = self.unary()?;
+
+        let result = match (&left, &right) {
+            (Value::Number(a), Value::Number(b)) => match op.as_str() {
+                "==" => a == b,
+                "!=" => a != b,
+                "<" => a < b,
+                "<=" => a <= b,
+                ">" => a > b,
+                _ => a >= b,
+            },
+            _ => {
+                let equal = left.text().is_some() && left.text() == right.text();
+                match op.as_str() {
+                    "==" => equal,
+                    "!=" => !equal,
+                    _ => {
+                        return Err(AssemblyError::InvalidCondition(format!(
+                            "`{}` needs numbers",
+                            op
+                        )));
+                    }
+                }
+            }
+        };
+        Ok(Value::Number(result as u16))
+    }
+
+    fn unary(&mut self) -> Result<Value, AssemblyError> {
+        if self.peek() == Some("!") {
+            self.pos += 1;
+            let value = self.unary()?;
+            return Ok(Value::Number(!value.truth() as u16));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Value, AssemblyError> {
+        let token = self.next()?.to_string();
+        if token == "(" {
+            let value = self.or()?;
+            self.expect(")")?;
+            return Ok(value);
+        }
+        if token.eq_ignore_ascii_case("DEFINED") {
+            self.expect("(")?;
+            let name = self.next()?.to_string();
+            self.expect(")")?;
+            return Ok(Value::Number(self.conditions.is_defined(&name) as u16));
+        }
+        if OPERATORS.contains(&token.as_str()) {
+            return Err(AssemblyError::InvalidCondition(format!(
+                "unexpected `{}`",
+                token
+            )));
+        }
+
+        let name = token.to_uppercase();
+        Ok(match self.conditions.defines.get(&name) {
+            Some(value) => Value::from_text(value),
+            None => match Value::from_text(&token) {
+                Value::Text(_) => Value::Undefined(name),
+                number => number,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{AssembleOptions, assemble, assemble_with};
+
+    fn target(name: &str) -> AssembleOptions {
+        AssembleOptions {
+            defines: HashMap::from([parse_define(&format!("TARGET={}", name))]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let conditions = Conditions::new(&HashMap::from([
+            parse_define("TARGET=vip"),
+            parse_define("PORT=0x02"),
+            parse_define("DEBUG"),
+        ]));
+        let check = |text| conditions.evaluate(text).unwrap();
+
+        assert!(check("TARGET == VIP"));
+        assert!(!check("TARGET == ELF"));
+        assert!(check("PORT > 1 && PORT <= 0x02"));
+        assert!(check("DEBUG && !RELEASE"));
+        assert!(check("defined(DEBUG) || (PORT == 9)"));
+        assert!(!check("RELEASE"));
+        assert!(conditions.evaluate("TARGET < 2").is_err());
+        assert!(conditions.evaluate("(PORT").is_err());
+    }
+
+    #[test]
+    fn test_target_selection() {
+        let source = r#"
+        IF TARGET == VIP
+        ORG 0x0200
+        ELSE
+        IFDEF FAST
+        NOP
+        ENDIF
+        ENDIF
+        DEFINE DONE
+        IFNDEF DONE
+        UNKNOWN OPCODE
+        ENDIF
+        IDL
+"#;
+        let vip = assemble_with(source, &target("VIP")).unwrap();
+        assert_eq!(vip.lines.len(), 1);
+        assert_eq!(
+            (vip.lines[0].address, vip.lines[0].bytes.as_slice()),
+            (0x0200, &[0x00][..])
+        );
+
+        let mut elf = target("ELF");
+        elf.defines.insert("FAST".to_string(), "1".to_string());
+        let elf = assemble_with(source, &elf).unwrap();
+        assert_eq!(elf.machine_code, vec![0xC4, 0x00]);
+    }
+
+    #[test]
+    fn test_defines_as_symbols() {
+        let options = AssembleOptions {
+            defines: HashMap::from([
+                parse_define("BASE=0x0200"),
+                parse_define("PORT=3"),
+                parse_define("TARGET=vip"),
+            ]),
+            ..Default::default()
+        };
+        let result = assemble_with("ORG BASE\nSEX R2\nOUT PORT\nLDI PORT\nIDL", &options).unwrap();
+        assert_eq!(result.lines[0].address, 0x0200);
+        assert_eq!(result.machine_code[0x200..], [0xE2, 0x63, 0xF8, 0x03, 0x00]);
+
+        // Only numeric values become symbols
+        assert!(assemble_with("LDI TARGET", &options).is_err());
+    }
+
+    #[test]
+    fn test_unbalanced_blocks() {
+        let err = assemble("IF 1\nIDL").unwrap_err();
+        let diagnostic = &err.diagnostics()[0];
+        assert_eq!(diagnostic.line, 1);
+        assert_eq!(diagnostic.message, "IF without ENDIF");
+
+        let err = assemble("IDL\nENDIF").unwrap_err();
+        assert_eq!(err.diagnostics()[0].message, "ENDIF without matching IF");
+    }
+}
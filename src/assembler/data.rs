@@ -0,0 +1,139 @@
+use super::AssemblyError;
+use super::encoder::{Encoder, Issue};
+use super::parser::{ParsedLine, Token};
+use crate::linker::RelocationKind;
+
+/// Data definition directives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataKind {
+    /// DB - bytes, character literals and strings
+    Byte,
+    /// DW - 16-bit big-endian words
+    Word,
+}
+
+impl DataKind {
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        match mnemonic.to_uppercase().as_str() {
+            "DB" | "BYTE" => Some(DataKind::Byte),
+            "DW" | "WORD" => Some(DataKind::Word),
+            _ => None,
+        }
+    }
+}
+
+/// Length in bytes of a DB/DW line, or `None` if the line is not data
+pub(crate) fn data_length(line: &ParsedLine) -> Option<u16> {
+    let kind = DataKind::from_mnemonic(&line.mnemonic.as_ref()?.text)?;
+    let length = line
+        .operands
+        .iter()
+        .map(|item| match (kind, string_literal(&item.text)) {
+            (DataKind::Byte, Some(text)) => text.len() as u16,
+            (DataKind::Byte, None) => 1,
+            (DataKind::Word, _) => 2,
+        })
+        .sum();
+    Some(length)
+}
+
+/// The contents of a "..." string literal
+fn string_literal(text: &str) -> Option<&str> {
+    text.strip_prefix('"')?.strip_suffix('"')
+}
+
+/// The value of a 'c' character literal
+pub(crate) fn char_literal(text: &str) -> Option<u16> {
+    let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = inner.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii() => Some(c as u16),
+        _ => None,
+    }
+}
+
+impl Encoder<'_> {
+    /// Assemble the items of a DB or DW directive
+    pub(crate) fn encode_data(
+        &mut self,
+        kind: DataKind,
+        mnemonic: &Token,
+        items: &[Token],
+    ) -> Result<Vec<u8>, Issue> {
+        if items.is_empty() {
+            return Err(Issue::error(
+                mnemonic.span,
+                AssemblyError::InvalidOperand(format!(
+                    "{} requires at least one value",
+                    mnemonic.text.to_uppercase()
+                )),
+            ));
+        }
+
+        let mut bytes = Vec::new();
+        for item in items {
+            match (kind, string_literal(&item.text)) {
+                (DataKind::Byte, Some(text)) => {
+                    if !text.is_ascii() {
+                        return Err(Issue::error(
+                            item.span,
+                            AssemblyError::InvalidOperand(format!(
+                                "Strings must be ASCII: {}",
+                                item.text
+                            )),
+                        ));
+                    }
+                    bytes.extend(text.bytes());
+                }
+                (DataKind::Byte, None) => {
+                    let offset = bytes.len() as u16;
+                    let value = self.resolve_field(item, offset, RelocationKind::Low)?;
+                    if value > 0xFF {
+                        self.warnings.push(Issue::warning(
+                            item.span,
+                            format!(
+                                "Byte value {:#06X} truncated to {:#04X}",
+                                value, value as u8
+                            ),
+                        ));
+                    }
+                    bytes.push(value as u8);
+                }
+                (DataKind::Word, _) => {
+                    let offset = bytes.len() as u16;
+                    let value = self.resolve_field(item, offset, RelocationKind::Word)?;
+                    bytes.extend([(value >> 8) as u8, value as u8]);
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::Dialect;
+    use crate::assembler::parser::parse_line;
+
+    #[test]
+    fn test_data_length() {
+        assert_eq!(
+            data_length(&parse_line(1, "DB 1, \"ABC\", 'x'", Dialect::Native)),
+            Some(5)
+        );
+        assert_eq!(
+            data_length(&parse_line(1, "DW 0x1234, LABEL", Dialect::Native)),
+            Some(4)
+        );
+        assert_eq!(data_length(&parse_line(1, "LDI 5", Dialect::Native)), None);
+    }
+
+    #[test]
+    fn test_char_literal() {
+        assert_eq!(char_literal("'A'"), Some(0x41));
+        assert_eq!(char_literal("'AB'"), None);
+        assert_eq!(char_literal("A"), None);
+    }
+}
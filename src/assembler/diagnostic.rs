@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Severity of an assembler diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A column range within a single source line
+///
+/// Columns are 0-based character offsets; `end` is exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Number of columns covered (at least 1 so carets are always visible)
+    pub fn width(&self) -> usize {
+        self.end.saturating_sub(self.start).max(1)
+    }
+}
+
+/// A single error or warning produced while assembling
+///
+/// Diagnostics are plain data so they can be rendered as caret-annotated
+/// text for a terminal or serialized for the web editor to underline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    /// 1-based source line number
+    pub line: usize,
+    pub span: Span,
+    pub message: String,
+    /// Suggested replacement for the text covered by `span`
+    pub suggestion: Option<String>,
+    /// ID of the lint that produced a warning, such as `L004`
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(file: &str, line: usize, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            file: file.to_string(),
+            line,
+            span,
+            message: message.into(),
+            suggestion: None,
+            code: None,
+        }
+    }
+
+    pub fn warning(file: &str, line: usize, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(file, line, span, message)
+        }
+    }
+
+    /// Attach a suggested replacement ("did you mean ...?")
+    pub fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+
+    /// Tag with the ID of the lint that produced it
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Severity, with the lint ID if there is one: `warning[L004]`
+    fn label(&self) -> String {
+        match &self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Render this diagnostic with the offending source line and carets
+    ///
+    /// ```text
+    /// error: Invalid instruction: PHY
+    ///  --> input.asm:3:1
+    ///   |
+    /// 3 | PHY R1
+    ///   | ^^^ did you mean `PHI`?
+    /// ```
+    pub fn render(&self, source_line: &str) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let hint = match &self.suggestion {
+            Some(s) => format!(" did you mean `{}`?", s),
+            None => String::new(),
+        };
+
+        format!(
+            "{}: {}\n{} --> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}{}\n",
+            self.label(),
+            self.message,
+            gutter,
+            self.file,
+            self.line,
+            self.span.start + 1,
+            gutter,
+            number,
+            source_line,
+            gutter,
+            " ".repeat(self.span.start),
+            "^".repeat(self.span.width()),
+            hint,
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file,
+            self.line,
+            self.span.start + 1,
+            self.label(),
+            self.message
+        )?;
+        if let Some(s) = &self.suggestion {
+            write!(f, " (did you mean `{}`?)", s)?;
+        }
+        Ok(())
+    }
+}
+
+/// Render every diagnostic against the source it was produced from
+pub fn render_diagnostics(diagnostics: &[Diagnostic], source: &str) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    diagnostics
+        .iter()
+        .map(|d| {
+            let line = lines.get(d.line.wrapping_sub(1)).copied().unwrap_or("");
+            d.render(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Pick the candidate closest to `word` by edit distance, if any is close enough
+pub(crate) fn closest_match<'a>(
+    word: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let word = word.to_uppercase();
+    let limit = (word.len() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(&word, &c.to_uppercase()), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.to_string())
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("PHI", "PHI"), 0);
+        assert_eq!(edit_distance("PHY", "PHI"), 1);
+        assert_eq!(edit_distance("LDI", "LDXA"), 2);
+    }
+
+    #[test]
+    fn test_closest_match() {
+        let mnemonics = ["PHI", "PLO", "GHI"];
+        assert_eq!(closest_match("phy", mnemonics), Some("PHI".to_string()));
+        assert_eq!(closest_match("XYZZY", mnemonics), None);
+    }
+
+    #[test]
+    fn test_render_caret() {
+        let diag = Diagnostic::error("test.asm", 3, Span::new(4, 7), "Invalid instruction: PHY")
+            .with_suggestion(Some("PHI".to_string()));
+        let text = diag.render("    PHY R1");
+
+        assert!(text.starts_with("error: Invalid instruction: PHY\n"));
+        assert!(text.contains(" --> test.asm:3:5\n"));
+        assert!(text.contains("3 |     PHY R1\n"));
+        assert!(text.contains("  |     ^^^ did you mean `PHI`?\n"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Source syntax of other 1802 assemblers
+//!
+//! In the A18 dialect (also accepted by asmx and used in the RCA manuals and
+//! Elf/OS sources) each line is rewritten into native syntax as it is
+//! parsed. Tokens keep the spans of what was written, so diagnostics still
+//! point at the original text.
+//!
+//! | A18                        | Native                  |
+//! |----------------------------|-------------------------|
+//! | `LABEL` in column 1        | `LABEL:`                |
+//! | `.. comment`               | `; comment`             |
+//! | `100` (decimal)            | `0x64`                  |
+//! | `#FF`, `X'FF'`, `0FFH`     | `0xFF`                  |
+//! | `B'1010'`, `D'10'`         | `0xA`                   |
+//! | `A.1(label)`, `A.0(label)` | `HIGH(label)`, `LOW(label)` |
+//! | `GHI R5.1`, `PLO R5.0`     | `GHI R5`, `PLO R5`      |
+//! | `INC 12`                   | `INC RC`                |
+//! | `DB 'TEXT'`                | `DB "TEXT"`             |
+//! | `END`                      | (ignored)               |
+//!
+//! `PAGE`, which moves to the start of the next page, works in either
+//! dialect.
+
+use super::parser::ParsedLine;
+use serde::{Deserialize, Serialize};
+
+/// Source syntax accepted by the assembler
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dialect {
+    /// This assembler's own syntax: `LABEL:`, `;`/`#` comments, `0x` hex
+    #[default]
+    Native,
+    /// A18/asmx syntax
+    A18,
+}
+
+impl Dialect {
+    /// Does a comment start at `chars[i]`?
+    pub(super) fn comment_at(self, chars: &[char], i: usize) -> bool {
+        match self {
+            Dialect::Native => chars[i] == ';' || chars[i] == '#',
+            Dialect::A18 => chars[i] == ';' || (chars[i] == '.' && chars.get(i + 1) == Some(&'.')),
+        }
+    }
+
+    /// Is a word starting in column 1 a label even without a colon?
+    pub(super) fn column_labels(self) -> bool {
+        self == Dialect::A18
+    }
+}
+
+/// Rewrite the operands of an A18 line into native syntax
+pub(super) fn translate_a18(line: &mut ParsedLine) {
+    let Some(mnemonic) = &line.mnemonic else {
+        return;
+    };
+    let name = mnemonic.text.to_uppercase();
+    if name == "END" {
+        line.mnemonic = None;
+        line.operands.clear();
+        return;
+    }
+
+    for (i, operand) in line.operands.iter_mut().enumerate() {
+        let text = operand.text.trim();
+        if register_operand(&name, i)
+            && let Some(register) = register(&name, text)
+        {
+            operand.text = register;
+            continue;
+        }
+        operand.text = match text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            Some(string) if string.chars().count() > 1 && !text.contains('"') => {
+                format!("\"{}\"", string)
+            }
+            _ => expression(text),
+        };
+    }
+}
+
+/// Does operand `index` of `mnemonic` name a register (or I/O port)?
+pub(super) fn register_operand(mnemonic: &str, index: usize) -> bool {
+    match mnemonic {
+        "LDN" | "INC" | "DEC" | "LDA" | "STR" | "OUT" | "INP" | "GLO" | "GHI" | "PLO" | "PHI"
+        | "SEP" | "SEX" | "LOAD" | "PUSH" | "POP" => index == 0,
+        "MOV" | "ADD16" => index <= 1,
+        _ => false,
+    }
+}
+
+/// A register operand in native form: `Rn.0`/`Rn.1` lose their byte
+/// selector where it matches the instruction, and numbers become `Rn`
+fn register(mnemonic: &str, text: &str) -> Option<String> {
+    let upper = text.to_uppercase();
+    if let Some((register, byte)) = upper.split_once('.') {
+        let high = matches!(mnemonic, "GHI" | "PHI");
+        let low = matches!(mnemonic, "GLO" | "PLO");
+        return match byte {
+            "1" if high => Some(register.to_string()),
+            "0" if low => Some(register.to_string()),
+            // Left as written, to be reported as an invalid register
+            _ => None,
+        };
+    }
+    let value = number(&upper)?;
+    (value < 16).then(|| format!("R{:X}", value))
+}
+
+/// Rewrite the numbers and byte selectors in an expression
+fn expression(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let upper: String = chars[i..].iter().collect::<String>().to_uppercase();
+
+        // X'FF', B'1010' and D'10'
+        if matches!(c.to_ascii_uppercase(), 'X' | 'B' | 'D')
+            && chars.get(i + 1) == Some(&'\'')
+            && (i == 0 || !is_word(chars[i - 1]))
+            && let Some(close) = chars[i + 2..].iter().position(|&q| q == '\'')
+        {
+            let literal: String = chars[i..i + close + 3].iter().collect();
+            match number(&literal) {
+                Some(value) => out.push_str(&format!("0x{:X}", value)),
+                None => out.push_str(&literal),
+            }
+            i += close + 3;
+        } else if (upper.starts_with("A.0(") || upper.starts_with("A.1("))
+            && (i == 0 || !is_word(chars[i - 1]))
+        {
+            out.push_str(if upper.starts_with("A.1") {
+                "HIGH("
+            } else {
+                "LOW("
+            });
+            i += 4;
+        } else if c == '\'' || c == '"' {
+            // Character and string literals pass through unchanged
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&q| q == c)
+                .map_or(chars.len(), |p| i + p + 2);
+            out.extend(&chars[i..end]);
+            i = end;
+        } else if c.is_ascii_digit() || c == '#' || c == '$' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && is_word(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            match number(&word) {
+                Some(value) => out.push_str(&format!("0x{:X}", value)),
+                None => out.push_str(&word),
+            }
+        } else if is_word(c) {
+            // Names pass through whole, so digits in them are not numbers
+            while i < chars.len() && (is_word(chars[i]) || chars[i] == '.') {
+                out.push(chars[i]);
+                i += 1;
+            }
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Value of an A18 number: decimal unless marked as hex or binary
+fn number(text: &str) -> Option<u16> {
+    let upper = text.to_uppercase();
+    let (digits, radix) = if let Some(quoted) = upper
+        .get(1..)
+        .and_then(|t| t.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')))
+    {
+        let radix = match upper.as_bytes()[0] {
+            b'X' => 16,
+            b'B' => 2,
+            _ => 10,
+        };
+        (quoted, radix)
+    } else if let Some(hex) = upper
+        .strip_prefix('#')
+        .or_else(|| upper.strip_prefix('$'))
+        .or_else(|| upper.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(hex) = upper.strip_suffix('H') {
+        (hex, 16)
+    } else {
+        (upper.as_str(), 10)
+    };
+    u16::from_str_radix(digits, radix).ok()
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::parser::parse_line;
+    use crate::assembler::{AssembleOptions, assemble_with};
+
+    fn operands(line: &str) -> Vec<String> {
+        let parsed = parse_line(1, line, Dialect::A18);
+        parsed.operands.into_iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn test_a18_numbers() {
+        assert_eq!(number("100"), Some(100));
+        assert_eq!(number("#FF"), Some(0xFF));
+        assert_eq!(number("X'3f'"), Some(0x3F));
+        assert_eq!(number("0FFH"), Some(0xFF));
+        assert_eq!(number("B'1010'"), Some(10));
+        assert_eq!(number("D'99'"), Some(99));
+        assert_eq!(number("LOOP"), None);
+    }
+
+    #[test]
+    fn test_a18_operands() {
+        assert_eq!(operands("  LDI A.1(TABLE+2)"), vec!["HIGH(TABLE+0x2)"]);
+        assert_eq!(
+            operands("  DB 10, X'0D', 'HI', 'A'"),
+            vec!["0xA", "0xD", "\"HI\"", "'A'"]
+        );
+        assert_eq!(operands("  GHI R5.1 .. high byte"), vec!["R5"]);
+        assert_eq!(operands("  GLO R5.1"), vec!["R5.1"]);
+        assert_eq!(operands("  SEX 12"), vec!["RC"]);
+        assert_eq!(operands("  BR LOOP2"), vec!["LOOP2"]);
+    }
+
+    #[test]
+    fn test_a18_program() {
+        let source = "\
+.. Set Q, count R5 down to zero
+        ORG  #0010
+START   LDI  A.1(DATA)
+        PHI  R5.1
+        LDI  A.0(DATA)
+        PLO  R5.0
+LOOP    SEQ
+        DEC  5          .. decimal register number
+        GLO  R5.0
+        BNZ  LOOP
+        PAGE
+DATA    DB   X'0A', 10, 'OK'
+        END  START
+";
+        let options = AssembleOptions {
+            dialect: Dialect::A18,
+            ..Default::default()
+        };
+        let output = assemble_with(source, &options).unwrap();
+
+        assert_eq!(
+            output.machine_code[0x10..0x1B],
+            [
+                0xF8, 0x01, 0xB5, 0xF8, 0x00, 0xA5, 0x7B, 0x25, 0x85, 0x3A, 0x16
+            ]
+        );
+        assert_eq!(output.machine_code[0x100..], [0x0A, 0x0A, 0x4F, 0x4B]);
+
+        // Native syntax reads bare numbers as hex and has no column labels
+        assert!(assemble_with(source, &AssembleOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use super::AssemblyError;
+use super::branch::{ShortBranch, long_branch_bytes};
+use super::data::{DataKind, char_literal, data_length};
+use super::diagnostic::{Span, closest_match};
+use super::listing::Expansion;
+use super::object::Fixup;
+use super::parser::{ParsedLine, Token};
+use super::pseudo::pseudo_length;
+use crate::cpu::isa::{self, INSTRUCTIONS, InstructionInfo, OperandKind};
+use crate::linker::RelocationKind;
+use std::collections::HashMap;
+
+/// Directives checked for before instructions
+const DIRECTIVES: &[&str] = &["ORG", "INCLUDE", "DB", "DW"];
+
+/// Mnemonics the assembler expands into instructions
+const PSEUDO_OPS: &[&str] = &["CALL", "RETN", "LOAD", "MOV", "ADD16", "PUSH", "POP"];
+
+/// Every mnemonic the assembler accepts (used for "did you mean" hints)
+pub(crate) fn mnemonics() -> impl Iterator<Item = &'static str> {
+    DIRECTIVES
+        .iter()
+        .copied()
+        .chain(INSTRUCTIONS.iter().map(|i| i.mnemonic))
+        .chain(PSEUDO_OPS.iter().copied())
+}
+
+/// A problem found while encoding one line, located within that line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Issue {
+    pub span: Span,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Issue {
+    pub fn error(span: Span, error: AssemblyError) -> Self {
+        Self {
+            span,
+            message: error.to_string(),
+            suggestion: None,
+        }
+    }
+
+    pub fn warning(span: Span, message: String) -> Self {
+        Self {
+            span,
+            message,
+            suggestion: None,
+        }
+    }
+}
+
+/// Encodes parsed lines into machine code, collecting warnings as it goes
+pub(crate) struct Encoder<'a> {
+    pub labels: &'a HashMap<String, u16>,
+    /// Numeric caller defines, usable as operands; labels of the same name
+    /// take precedence
+    pub constants: HashMap<String, u16>,
+    pub warnings: Vec<Issue>,
+    /// Set when the last error was a short branch to another page
+    pub off_page: bool,
+    /// Labels referenced by the line just encoded
+    pub references: Vec<String>,
+    /// Real instructions generated for the line just encoded, when they
+    /// differ from what was written
+    pub expansion: Vec<Expansion>,
+    /// Label fields of the line just encoded
+    pub fixups: Vec<Fixup>,
+    /// Labels are offsets into sections placed later by the linker, so
+    /// short branches to them are checked at link time instead
+    pub relocatable: bool,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(labels: &'a HashMap<String, u16>) -> Self {
+        Self {
+            labels,
+            constants: HashMap::new(),
+            warnings: Vec::new(),
+            off_page: false,
+            references: Vec::new(),
+            expansion: Vec::new(),
+            fixups: Vec::new(),
+            relocatable: false,
+        }
+    }
+
+    /// Assemble the instruction on a single parsed line placed at `address`
+    ///
+    /// `relaxed` selects the long form of a short branch.
+    pub fn encode(
+        &mut self,
+        line: &ParsedLine,
+        address: u16,
+        relaxed: bool,
+    ) -> Result<Vec<u8>, Issue> {
+        self.off_page = false;
+        let Some(mnemonic_token) = &line.mnemonic else {
+            return Ok(vec![]);
+        };
+        let mnemonic = mnemonic_token.text.to_uppercase();
+
+        if let Some(kind) = DataKind::from_mnemonic(&mnemonic) {
+            return self.encode_data(kind, mnemonic_token, &line.operands);
+        }
+        if let Some(result) = self.encode_pseudo(&mnemonic, line, address) {
+            return result;
+        }
+
+        let info = isa::lookup(&mnemonic);
+        let scrt = matches!(mnemonic.as_str(), "CALL" | "RETN");
+        if info.is_none() && !scrt {
+            return Err(unknown_mnemonic(mnemonic_token));
+        }
+
+        // Anything past the operands an instruction takes is ignored
+        let expected = match info {
+            Some(info) => usize::from(info.operand != OperandKind::Implied),
+            None => usize::from(mnemonic == "CALL"),
+        };
+        for extra in line.operands.iter().skip(expected) {
+            self.warnings.push(Issue::warning(
+                extra.span,
+                format!("Extra operand ignored: {}", extra.text),
+            ));
+        }
+
+        let operand = || {
+            line.operands.first().ok_or_else(|| {
+                Issue::error(
+                    mnemonic_token.span,
+                    AssemblyError::InvalidOperand(format!("{} requires an operand", mnemonic)),
+                )
+            })
+        };
+
+        if relaxed {
+            return self.assemble_relaxed_branch(&mnemonic, operand()?, address);
+        }
+
+        // SCRT subroutine linkage
+        let Some(info) = info else {
+            return match mnemonic.as_str() {
+                "CALL" => self.assemble_call(operand()?, address),
+                _ => Ok(self.assemble_return(address)),
+            };
+        };
+
+        match info.operand {
+            OperandKind::Implied => Ok(vec![info.code]),
+            OperandKind::Register | OperandKind::RegisterNonZero | OperandKind::Port => {
+                assemble_register_op(info, &self.constant_token(operand()?))
+            }
+            OperandKind::Immediate => self.assemble_immediate(info.code, operand()?),
+            OperandKind::ShortBranch => {
+                let branch = ShortBranch {
+                    opcode: info.code,
+                    mnemonic: mnemonic_token,
+                    address,
+                };
+                self.assemble_short_branch(branch, operand()?)
+            }
+            OperandKind::LongBranch => self.assemble_long_branch(info.code, operand()?),
+        }
+    }
+
+    /// Assemble an immediate instruction (opcode + immediate byte)
+    ///
+    /// `HIGH(x)` and `LOW(x)` select a byte of an address or label.
+    fn assemble_immediate(&mut self, opcode: u8, operand: &Token) -> Result<Vec<u8>, Issue> {
+        if let Some((high, inner)) = byte_select(&operand.text) {
+            let inner = Token {
+                text: inner.to_string(),
+                span: operand.span,
+            };
+            let kind = if high {
+                RelocationKind::High
+            } else {
+                RelocationKind::Low
+            };
+            let [hi, lo] = self.resolve_field(&inner, 1, kind)?.to_be_bytes();
+            return Ok(vec![opcode, if high { hi } else { lo }]);
+        }
+
+        let value = match self.constants.get(&operand.text.to_uppercase()) {
+            Some(&value) => value,
+            None => parse_number(&operand.text).map_err(|e| Issue::error(operand.span, e))?,
+        };
+        if value > 0xFF {
+            self.warnings.push(Issue::warning(
+                operand.span,
+                format!(
+                    "Immediate value {:#06X} truncated to {:#04X}",
+                    value, value as u8
+                ),
+            ));
+        }
+        Ok(vec![opcode, value as u8])
+    }
+
+    /// Assemble a long branch instruction (opcode + 16-bit address)
+    fn assemble_long_branch(&mut self, opcode: u8, operand: &Token) -> Result<Vec<u8>, Issue> {
+        let address = self.resolve_field(operand, 1, RelocationKind::Word)?;
+        Ok(long_branch_bytes(opcode, address))
+    }
+
+    /// `operand` with a caller define replaced by its value in hex, for
+    /// operands that name a register or port
+    fn constant_token(&self, operand: &Token) -> Token {
+        match self.constants.get(&operand.text.to_uppercase()) {
+            Some(value) => Token {
+                text: format!("{:X}", value),
+                span: operand.span,
+            },
+            None => operand.clone(),
+        }
+    }
+
+    /// Resolve a branch target: a label, a caller define, or failing that a
+    /// direct address
+    pub(crate) fn resolve_target(&mut self, operand: &Token) -> Result<u16, Issue> {
+        let target = operand.text.to_uppercase();
+        if let Some(&addr) = self.labels.get(&target) {
+            self.references.push(target);
+            return Ok(addr);
+        }
+        if let Some(&value) = self.constants.get(&target) {
+            return Ok(value);
+        }
+        if let Some(value) = char_literal(&operand.text) {
+            return Ok(value);
+        }
+
+        parse_number(&operand.text).map_err(|e| {
+            if looks_like_label(&operand.text) {
+                Issue {
+                    suggestion: closest_match(&target, self.labels.keys().map(|k| k.as_str())),
+                    ..Issue::error(operand.span, AssemblyError::UndefinedLabel(target))
+                }
+            } else {
+                Issue::error(operand.span, e)
+            }
+        })
+    }
+}
+
+/// Length in bytes of whatever a line emits (instruction or data)
+pub(crate) fn statement_length(line: &ParsedLine) -> Option<u16> {
+    data_length(line).or_else(|| instruction_length(&line.mnemonic.as_ref()?.text))
+}
+
+/// Get the length of an instruction without fully assembling it
+pub(crate) fn instruction_length(mnemonic: &str) -> Option<u16> {
+    if let Some(info) = isa::lookup(mnemonic) {
+        return Some(info.length as u16);
+    }
+    match mnemonic.to_uppercase().as_str() {
+        "RETN" => Some(1),
+        "CALL" => Some(3),
+        _ => pseudo_length(mnemonic),
+    }
+}
+
+/// Error for an unrecognised mnemonic, with the closest known one as a hint
+pub(crate) fn unknown_mnemonic(token: &Token) -> Issue {
+    let mnemonic = token.text.to_uppercase();
+    Issue {
+        suggestion: closest_match(&mnemonic, mnemonics()),
+        ..Issue::error(token.span, AssemblyError::InvalidInstruction(mnemonic))
+    }
+}
+
+/// Split `HIGH(x)` or `LOW(x)` into which byte is wanted and `x`
+fn byte_select(text: &str) -> Option<(bool, &str)> {
+    let open = text.find('(')?;
+    let inner = text[open + 1..].strip_suffix(')')?.trim();
+    match text[..open].trim().to_uppercase().as_str() {
+        "HIGH" => Some((true, inner)),
+        "LOW" => Some((false, inner)),
+        _ => None,
+    }
+}
+
+/// Identifiers start with a letter or underscore and are not plain numbers
+fn looks_like_label(s: &str) -> bool {
+    s.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// Assemble a register or port instruction (opcode | N)
+fn assemble_register_op(info: &InstructionInfo, operand: &Token) -> Result<Vec<u8>, Issue> {
+    let error = |e| Issue::error(operand.span, e);
+    let n = parse_register(&operand.text).map_err(error)?;
+    match info.operand {
+        OperandKind::Port if !(1..=7).contains(&n) => Err(error(AssemblyError::InvalidOperand(
+            format!("{} port must be 1-7, got: {}", info.mnemonic, operand.text),
+        ))),
+        OperandKind::RegisterNonZero if n == 0 => Err(error(AssemblyError::InvalidRegister(
+            format!("{} cannot use R0 (its opcode is IDL)", info.mnemonic),
+        ))),
+        _ => Ok(vec![info.code | n]),
+    }
+}
+
+/// Parse a register (R0-RF or 0-F or RA-RF)
+pub(crate) fn parse_register(s: &str) -> Result<u8, AssemblyError> {
+    let s = s.trim().to_uppercase();
+
+    // Remove 'R' prefix if present
+    let num_str = if let Some(stripped) = s.strip_prefix('R') {
+        stripped
+    } else {
+        &s
+    };
+
+    // Parse hex digit (0-F)
+    if num_str.len() == 1
+        && let Some(digit) = num_str.chars().next()
+        && let Some(value) = digit.to_digit(16)
+        && value <= 15
+    {
+        return Ok(value as u8);
+    }
+
+    Err(AssemblyError::InvalidRegister(format!(
+        "Register must be R0-RF or 0-F, got: {}",
+        s
+    )))
+}
+
+/// Parse a number (hex with 0x prefix, or decimal)
+pub(crate) fn parse_number(s: &str) -> Result<u16, AssemblyError> {
+    let s = s.trim();
+
+    // Remove any comma separators
+    let s = s.replace(',', "");
+
+    // Hex with 0x prefix
+    if s.starts_with("0x") || s.starts_with("0X") {
+        u16::from_str_radix(&s[2..], 16)
+            .map_err(|_| AssemblyError::InvalidOperand(format!("Invalid hex number: {}", s)))
+    }
+    // Hex with $ prefix (alternate notation)
+    else if let Some(stripped) = s.strip_prefix('$') {
+        u16::from_str_radix(stripped, 16)
+            .map_err(|_| AssemblyError::InvalidOperand(format!("Invalid hex number: {}", s)))
+    }
+    // Pure hex (no prefix) - try hex first
+    else if s.chars().all(|c| c.is_ascii_hexdigit()) {
+        // Try hex first
+        if let Ok(val) = u16::from_str_radix(&s, 16) {
+            Ok(val)
+        } else {
+            // Fall back to decimal
+            s.parse::<u16>()
+                .map_err(|_| AssemblyError::InvalidOperand(format!("Invalid number: {}", s)))
+        }
+    }
+    // Decimal
+    else {
+        s.parse::<u16>()
+            .map_err(|_| AssemblyError::InvalidOperand(format!("Invalid number: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_register() {
+        assert_eq!(parse_register("R0").unwrap(), 0);
+        assert_eq!(parse_register("R5").unwrap(), 5);
+        assert_eq!(parse_register("RA").unwrap(), 10);
+        assert_eq!(parse_register("RF").unwrap(), 15);
+        assert_eq!(parse_register("0").unwrap(), 0);
+        assert_eq!(parse_register("F").unwrap(), 15);
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("0x42").unwrap(), 0x42);
+        assert_eq!(parse_number("$FF").unwrap(), 0xFF);
+        assert_eq!(parse_number("FF").unwrap(), 0xFF);
+        assert_eq!(parse_number("100").unwrap(), 0x100); // Interpreted as hex
+        assert_eq!(parse_number("0x1234").unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn test_byte_select() {
+        assert_eq!(byte_select("HIGH(START)"), Some((true, "START")));
+        assert_eq!(byte_select("low( 0x1234 )"), Some((false, "0x1234")));
+        assert_eq!(byte_select("0x12"), None);
+    }
+
+    #[test]
+    fn test_mnemonic_table_complete() {
+        for mnemonic in mnemonics().skip(DIRECTIVES.len()) {
+            assert!(instruction_length(mnemonic).is_some(), "{}", mnemonic);
+        }
+    }
+}
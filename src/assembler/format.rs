@@ -0,0 +1,241 @@
+//! Canonical source layout
+//!
+//! Every line is parsed and written out again in fixed columns: labels at
+//! the left margin with a colon, mnemonics in column 9, operands in column
+//! 17 and trailing comments in column 33 (later when the code is longer).
+//! Mnemonics, labels and registers are upper case, numbers are written
+//! `0x` hex, operands are separated by `", "` and comments start with `;`.
+//! Blank lines, comment text and string and character literals are kept
+//! as written. Formatting a formatted file changes nothing.
+
+use super::diagnostic::{Diagnostic, Span};
+use super::dialect::{Dialect, register_operand};
+use super::encoder::parse_register;
+use super::parser::{ParsedLine, parse_line};
+
+const MNEMONIC_COLUMN: usize = 8;
+const OPERAND_COLUMN: usize = 16;
+const COMMENT_COLUMN: usize = 32;
+
+/// Directives whose operands are passed through untouched, since their
+/// values are compared as text or name files
+const VERBATIM: &[&str] = &["IF", "IFDEF", "IFNDEF", "DEFINE", "INCLUDE"];
+
+/// `source` in canonical layout, ending with a newline
+pub fn format_source(source: &str) -> String {
+    let mut out = String::new();
+    for (i, line) in source.lines().enumerate() {
+        out.push_str(&format_line(&parse_line(i + 1, line, Dialect::Native)));
+        out.push('\n');
+    }
+    out
+}
+
+/// A warning for each line of `source` that is not in canonical layout,
+/// suggesting the formatted line; empty when the file is formatted
+pub fn check_format(source: &str, file_name: &str) -> Vec<Diagnostic> {
+    let formatted = format_source(source);
+    let mut diagnostics: Vec<Diagnostic> = source
+        .lines()
+        .zip(formatted.lines())
+        .enumerate()
+        .filter(|(_, (line, expected))| line != expected)
+        .map(|(i, (line, expected))| {
+            let span = Span::new(0, line.chars().count());
+            let mut diagnostic =
+                Diagnostic::warning(file_name, i + 1, span, "Line is not formatted");
+            diagnostic.suggestion = Some(expected.to_string());
+            diagnostic
+        })
+        .collect();
+    if diagnostics.is_empty() && source != formatted {
+        let line = source.lines().count().max(1);
+        let message = "File does not end with a single newline";
+        diagnostics.push(Diagnostic::warning(
+            file_name,
+            line,
+            Span::new(0, 0),
+            message,
+        ));
+    }
+    diagnostics
+}
+
+fn format_line(line: &ParsedLine) -> String {
+    let mut out = String::new();
+    if let Some(label) = &line.label {
+        out.push_str(&label.text.to_uppercase());
+        out.push(':');
+    }
+    if let Some(mnemonic) = &line.mnemonic {
+        let name = mnemonic.text.to_uppercase();
+        pad(&mut out, MNEMONIC_COLUMN);
+        out.push_str(&name);
+        if !line.operands.is_empty() {
+            pad(&mut out, OPERAND_COLUMN);
+            let operands: Vec<String> = line
+                .operands
+                .iter()
+                .enumerate()
+                .map(|(i, o)| operand(&name, i, &o.text))
+                .collect();
+            out.push_str(&operands.join(", "));
+        }
+    }
+    if let Some(comment) = &line.comment {
+        if !out.is_empty() {
+            pad(&mut out, COMMENT_COLUMN);
+        } else if comment.span.start > 0 {
+            pad(&mut out, MNEMONIC_COLUMN);
+        }
+        out.push_str(&comment_text(&comment.text));
+    }
+    out.trim_end().to_string()
+}
+
+/// Pad `out` with spaces to `column`, or by one space if it is already
+/// there
+fn pad(out: &mut String, column: usize) {
+    let width = out.chars().count();
+    let spaces = if width < column { column - width } else { 1 };
+    out.extend(std::iter::repeat_n(' ', spaces));
+}
+
+/// A comment starting with `;`, with a space before its first word
+fn comment_text(text: &str) -> String {
+    let mut comment = match text.strip_prefix('#') {
+        Some(body) => format!(";{}", body),
+        None => text.to_string(),
+    };
+    let rest = comment.trim_start_matches([';', '#']);
+    if rest.starts_with(|c: char| c.is_alphanumeric()) {
+        let at = comment.len() - rest.len();
+        comment.insert(at, ' ');
+    }
+    comment
+}
+
+/// Operand `index` of `mnemonic` in canonical form
+fn operand(mnemonic: &str, index: usize, text: &str) -> String {
+    if VERBATIM.contains(&mnemonic) {
+        return text.to_string();
+    }
+    if register_operand(mnemonic, index)
+        && let Ok(register) = parse_register(text)
+    {
+        return match mnemonic {
+            "OUT" | "INP" => format!("{:X}", register),
+            _ => format!("R{:X}", register),
+        };
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' || c == '"' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&q| q == c)
+                .map_or(chars.len(), |p| i + p + 2);
+            out.extend(&chars[i..end]);
+            i = end;
+        } else if is_word(c) {
+            let start = i;
+            while i < chars.len() && is_word(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            out.push_str(&number(&word).unwrap_or_else(|| word.to_uppercase()));
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// A hex number in `0x` form, keeping its digits; `None` for names and
+/// anything too large to read as hex
+fn number(word: &str) -> Option<String> {
+    let digits = word
+        .strip_prefix("0x")
+        .or_else(|| word.strip_prefix("0X"))
+        .or_else(|| word.strip_prefix('$'))
+        .or_else(|| {
+            word.starts_with(|c: char| c.is_ascii_digit())
+                .then_some(word)
+        })?;
+    u16::from_str_radix(digits, 16).ok()?;
+    Some(format!("0x{}", digits.to_uppercase()))
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    const MESSY: &str = "start:\tldi $ff   ;load\n  plo r3\nloop: dec 3 # count down\n\n  ; inner comment\n\tbnz loop\nmsg:  db 'a', \"Hi; there\",10\nlabel_that_is_long: glo r3\n#top\n";
+
+    #[test]
+    fn test_format_layout() {
+        let formatted = format_source(MESSY);
+        assert_eq!(
+            formatted,
+            "START:  LDI     0xFF            ; load
+        PLO     R3
+LOOP:   DEC     R3              ; count down
+
+        ; inner comment
+        BNZ     LOOP
+MSG:    DB      'a', \"Hi; there\", 0x10
+LABEL_THAT_IS_LONG: GLO R3
+; top
+"
+        );
+        assert_eq!(
+            assemble(MESSY).unwrap().machine_code,
+            assemble(&formatted).unwrap().machine_code
+        );
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let once = format_source(MESSY);
+        assert_eq!(format_source(&once), once);
+        assert!(check_format(&once, "a.asm").is_empty());
+
+        let sources = [
+            "IFDEF debug\nDEFINE level=2\n INCLUDE \"std/print.asm\"\nENDIF",
+            "OUT 1\nLOAD r5, high(table)+$10\nMOV rA, b\nTABLE: DW 1234",
+        ];
+        for source in sources {
+            let once = format_source(source);
+            assert_eq!(format_source(&once), once, "{}", source);
+        }
+        assert!(format_source(sources[0]).starts_with("        IFDEF   debug\n"));
+        assert!(format_source(sources[1]).contains("MOV     RA, RB\n"));
+    }
+
+    #[test]
+    fn test_check_format() {
+        let diagnostics = check_format("LDI 1\n        IDL\n", "prog.asm");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 1);
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("        LDI     0x1")
+        );
+
+        let diagnostics = check_format("        IDL", "prog.asm");
+        assert_eq!(
+            diagnostics[0].message,
+            "File does not end with a single newline"
+        );
+    }
+}
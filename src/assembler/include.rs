@@ -0,0 +1,413 @@
+use super::AssemblyError;
+use super::conditional::Conditions;
+use super::diagnostic::{Diagnostic, Span};
+use super::dialect::Dialect;
+use super::parser::{ParsedLine, parse_file};
+use super::stdlib::{STDLIB_PREFIX, stdlib_source};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where `INCLUDE`d files are read from
+///
+/// Names are relative to the main file's directory: an `INCLUDE` in
+/// `lib/math.asm` of `"util.asm"` asks for `lib/util.asm`. Names starting
+/// with `std/` never reach the provider; they come from the bundled
+/// standard library.
+pub trait SourceProvider {
+    /// Contents of the file called `name`
+    fn read(&self, name: &str) -> io::Result<String>;
+}
+
+/// Files held in memory, for the browser and tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFiles {
+    files: HashMap<String, String>,
+}
+
+impl MemoryFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the file `name`
+    pub fn insert(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.files.insert(name.into(), source.into());
+    }
+}
+
+impl SourceProvider for MemoryFiles {
+    fn read(&self, name: &str) -> io::Result<String> {
+        self.files
+            .get(name)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    }
+}
+
+/// Files on disk, with names resolved against a root directory
+///
+/// Files outside the root, by an absolute path, `..` or a symbolic link,
+/// cannot be read, so a submission being graded can only include files
+/// that sit beside it.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    root: PathBuf,
+}
+
+impl FileSystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl SourceProvider for FileSystem {
+    fn read(&self, name: &str) -> io::Result<String> {
+        let root = match self.root.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => self.root.as_path(),
+        };
+        let root = root.canonicalize()?;
+        let path = root.join(name).canonicalize()?;
+        if !path.starts_with(&root) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "outside the include directory",
+            ));
+        }
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Read `name` from the standard library or else from `provider`
+pub(super) fn read_source(provider: &dyn SourceProvider, name: &str) -> io::Result<String> {
+    if name.starts_with(STDLIB_PREFIX) {
+        return stdlib_source(name).map(str::to_string).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no such standard library file")
+        });
+    }
+    provider.read(name)
+}
+
+/// Render diagnostics against the files they name, read through `provider`
+pub fn render_file_diagnostics(
+    diagnostics: &[Diagnostic],
+    provider: &dyn SourceProvider,
+) -> String {
+    let mut sources: HashMap<&str, Vec<String>> = HashMap::new();
+    diagnostics
+        .iter()
+        .map(|d| {
+            let lines = sources.entry(d.file.as_str()).or_insert_with(|| {
+                read_source(provider, &d.file)
+                    .map(|s| s.lines().map(str::to_string).collect())
+                    .unwrap_or_default()
+            });
+            let line = lines.get(d.line.wrapping_sub(1)).map_or("", |l| l.as_str());
+            d.render(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace every `INCLUDE "name"` with the lines of that file, recursively,
+/// and drop lines in conditional blocks that are not assembled
+///
+/// Each new file is appended to `files` and its lines carry that index.
+/// Included files are read in the same `dialect` as the main source.
+/// Directive lines stay (without their mnemonic) so a label on one still
+/// names the code that follows.
+pub(super) fn preprocess(
+    lines: Vec<ParsedLine>,
+    files: &mut Vec<String>,
+    provider: &dyn SourceProvider,
+    defines: &HashMap<String, String>,
+    dialect: Dialect,
+) -> Result<Vec<ParsedLine>, AssemblyError> {
+    let mut expander = Expander {
+        files,
+        provider,
+        dialect,
+        conditions: Conditions::new(defines),
+        stack: Vec::new(),
+        output: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    expander.stack.push(0);
+    expander.expand(lines);
+
+    if expander.diagnostics.is_empty() {
+        Ok(expander.output)
+    } else {
+        Err(AssemblyError::Diagnostics(expander.diagnostics))
+    }
+}
+
+struct Expander<'a> {
+    files: &'a mut Vec<String>,
+    provider: &'a dyn SourceProvider,
+    dialect: Dialect,
+    conditions: Conditions,
+    /// Indices of the files currently being included, outermost first
+    stack: Vec<usize>,
+    output: Vec<ParsedLine>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, lines: Vec<ParsedLine>) {
+        let depth = self.conditions.depth();
+        for mut line in lines {
+            let directive = self.conditions.directive(&line);
+            if let Err((span, error)) = &directive {
+                self.error((line.file, line.number), *span, error.clone());
+            }
+            if directive.unwrap_or(true) {
+                line.mnemonic = None;
+                line.operands.clear();
+                self.output.push(line);
+                continue;
+            }
+            if !self.conditions.active() {
+                continue;
+            }
+
+            if !line
+                .mnemonic
+                .as_ref()
+                .is_some_and(|m| m.text.eq_ignore_ascii_case("INCLUDE"))
+            {
+                self.output.push(line);
+                continue;
+            }
+
+            let span = line.statement_span();
+            let name = line
+                .operands
+                .first()
+                .map(|t| t.text.trim_matches('"').to_string());
+            line.mnemonic = None;
+            line.operands.clear();
+            let including = (line.file, line.number);
+            self.output.push(line);
+
+            match name {
+                Some(name) if !name.is_empty() => self.include(&name, including, span),
+                _ => self.error(
+                    including,
+                    span,
+                    AssemblyError::InvalidOperand("INCLUDE requires a file name".to_string()),
+                ),
+            }
+        }
+
+        for (file, number, span) in self.conditions.unterminated(depth) {
+            self.error((file, number), span, AssemblyError::UnterminatedConditional);
+        }
+    }
+
+    fn include(&mut self, name: &str, (file, number): (usize, usize), span: Span) {
+        let name = &relative_to(&self.files[file], name);
+        let index = match self.files.iter().position(|f| f == name) {
+            Some(index) => index,
+            None => {
+                self.files.push(name.to_string());
+                self.files.len() - 1
+            }
+        };
+
+        if self.stack.contains(&index) {
+            let mut chain: Vec<&str> = self.stack.iter().map(|&i| self.files[i].as_str()).collect();
+            chain.push(name);
+            let error = AssemblyError::IncludeCycle(chain.join(" -> "));
+            self.error((file, number), span, error);
+            return;
+        }
+
+        match read_source(self.provider, name) {
+            Ok(source) => {
+                self.stack.push(index);
+                self.expand(parse_file(index, &source, self.dialect));
+                self.stack.pop();
+            }
+            Err(e) => {
+                let error = AssemblyError::Include {
+                    name: name.to_string(),
+                    reason: e.to_string(),
+                };
+                self.error((file, number), span, error);
+            }
+        }
+    }
+
+    fn error(&mut self, (file, number): (usize, usize), span: Span, error: AssemblyError) {
+        self.diagnostics.push(Diagnostic::error(
+            &self.files[file],
+            number,
+            span,
+            error.to_string(),
+        ));
+    }
+}
+
+/// The name of the file `name` in an `INCLUDE` in `includer`: relative to
+/// the includer's directory, with `.` and `..` folded in
+fn relative_to(includer: &str, name: &str) -> String {
+    if name.starts_with(STDLIB_PREFIX) || Path::new(name).is_absolute() {
+        return name.to_string();
+    }
+    let mut parts: Vec<&str> = includer.split('/').collect();
+    parts.pop();
+    for part in name.split('/') {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|p| *p != "..") => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileSystem, SourceProvider, relative_to, render_file_diagnostics};
+    use crate::assembler::{AssembleOptions, MemoryFiles, assemble_file, render_listing};
+
+    fn project() -> MemoryFiles {
+        let mut files = MemoryFiles::new();
+        files.insert("main.asm", "START: LDI VALUE\nINCLUDE \"defs.asm\"\nIDL");
+        files.insert("defs.asm", "VALUE_ADDR: DB 0\nINCLUDE \"more.asm\"");
+        files.insert("more.asm", "  PLO R7");
+        files
+    }
+
+    #[test]
+    fn test_nested_includes() {
+        let mut files = project();
+        files.insert("main.asm", "START: LDI 0x05\nINCLUDE \"defs.asm\"\nIDL");
+        let output = assemble_file("main.asm", &files, &AssembleOptions::default()).unwrap();
+
+        assert_eq!(output.machine_code, vec![0xF8, 0x05, 0x00, 0xA7, 0x00]);
+        let location = output.source_map.location(0x0003).unwrap();
+        assert_eq!((location.file.as_str(), location.line), ("more.asm", 1));
+
+        let source = "START: LDI 0x05\nINCLUDE \"defs.asm\"\nIDL";
+        let listing = render_listing(source, &output);
+        assert!(listing.contains("   2                     INCLUDE \"defs.asm\"\n"));
+        assert!(listing.contains("   1+ 0003  A7             PLO R7\n"));
+    }
+
+    #[test]
+    fn test_listing_names_included_files() {
+        let mut files = MemoryFiles::new();
+        let source = "INCLUDE \"defs.asm\"\nSTART: DW FIVE\nBR LOOP";
+        files.insert("main.asm", source);
+        files.insert("defs.asm", "; constants\n\nFIVE: DB 5\nLOOP: BR START");
+        let output = assemble_file("main.asm", &files, &AssembleOptions::default()).unwrap();
+        let listing = render_listing(source, &output);
+        let lines: Vec<&str> = listing.lines().map(str::trim_end).collect();
+
+        // The included file is listed whole, comment and blank line too
+        assert_eq!(
+            lines[1..8],
+            [
+                "   1                     INCLUDE \"defs.asm\"",
+                "   1+ 0000               ; constants",
+                "   2+ 0000",
+                "   3+ 0000  05           FIVE: DB 5",
+                "   4+ 0001  30 03        LOOP: BR START",
+                "   2  0003  00 00        START: DW FIVE",
+                "   3  0005  30 01        BR LOOP",
+            ]
+        );
+        assert!(listing.contains("  FIVE    0000  defined defs.asm:3; used 2\n"));
+        assert!(listing.contains("  LOOP    0001  defined defs.asm:4; used 3\n"));
+        assert!(listing.contains("  START   0003  defined 2; used defs.asm:4\n"));
+    }
+
+    fn project_with_phy() -> MemoryFiles {
+        let mut files = project();
+        files.insert("more.asm", "  PHY R7");
+        files
+    }
+
+    #[test]
+    fn test_diagnostics_name_included_file() {
+        let files = project_with_phy();
+        let err = assemble_file("main.asm", &files, &AssembleOptions::default()).unwrap_err();
+        let names: Vec<&str> = err.diagnostics().iter().map(|d| d.file.as_str()).collect();
+
+        // VALUE is undefined in main.asm, VALUE_ADDR unused in defs.asm and
+        // PHY unknown in more.asm: in include order, although all are on
+        // line 1 and PHY is furthest left
+        assert_eq!(names, ["main.asm", "defs.asm", "more.asm"]);
+
+        let text = render_file_diagnostics(err.diagnostics(), &files);
+        assert!(text.contains(" --> more.asm:1:3\n"));
+        assert!(text.contains("1 |   PHY R7\n"));
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let mut files = project();
+        files.insert("more.asm", "INCLUDE \"defs.asm\"");
+        let err = assemble_file("main.asm", &files, &AssembleOptions::default()).unwrap_err();
+        let diagnostic = &err.diagnostics()[0];
+
+        assert_eq!(diagnostic.file, "more.asm");
+        assert_eq!(
+            diagnostic.message,
+            "Include cycle: main.asm -> defs.asm -> more.asm -> defs.asm"
+        );
+    }
+
+    #[test]
+    fn test_missing_include() {
+        let mut files = project();
+        files.insert("main.asm", "\nINCLUDE \"nowhere.asm\"");
+        let err = assemble_file("main.asm", &files, &AssembleOptions::default()).unwrap_err();
+        let diagnostic = &err.diagnostics()[0];
+
+        assert_eq!((diagnostic.file.as_str(), diagnostic.line), ("main.asm", 2));
+        assert!(diagnostic.message.starts_with("Cannot include nowhere.asm"));
+    }
+
+    #[test]
+    fn test_includes_relative_to_includer() {
+        let mut files = MemoryFiles::new();
+        files.insert("main.asm", "INCLUDE \"lib/a.asm\"\nIDL");
+        files.insert("lib/a.asm", "INCLUDE \"b.asm\"\nINCLUDE \"./../c.asm\"");
+        files.insert("lib/b.asm", "LDI 1");
+        files.insert("c.asm", "LDI 2");
+        let output = assemble_file("main.asm", &files, &AssembleOptions::default()).unwrap();
+        assert_eq!(output.machine_code, vec![0xF8, 0x01, 0xF8, 0x02, 0x00]);
+
+        assert_eq!(relative_to("a/b/c.asm", "../d.asm"), "a/d.asm");
+        assert_eq!(relative_to("main.asm", "../up.asm"), "../up.asm");
+        assert_eq!(relative_to("lib/a.asm", "std/print.asm"), "std/print.asm");
+    }
+
+    #[test]
+    fn test_file_system_stays_in_root() {
+        let dir = std::env::temp_dir().join(format!("rca1802-include-{}", std::process::id()));
+        let root = dir.join("root");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("in.asm"), "IDL").unwrap();
+        std::fs::write(dir.join("out.asm"), "IDL").unwrap();
+
+        let files = FileSystem::new(&root);
+        let reads = [
+            "in.asm",
+            "../out.asm",
+            dir.join("out.asm").to_str().unwrap(),
+        ]
+        .map(|name| files.read(name).map_err(|e| e.kind()));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reads[0], Ok("IDL".to_string()));
+        assert_eq!(reads[1], Err(std::io::ErrorKind::PermissionDenied));
+        assert_eq!(reads[2], Err(std::io::ErrorKind::PermissionDenied));
+    }
+}
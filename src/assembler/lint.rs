@@ -0,0 +1,329 @@
+//! Static checks for common 1802 pitfalls
+//!
+//! Once a program assembles cleanly its instructions and data are checked
+//! in address order. Each finding is a warning tagged with the lint's ID,
+//! e.g. `warning[L004]`. A lint is silenced for one line by a comment
+//! naming it by ID or name, `; allow: L004` or `; allow: unreachable`, or
+//! for the whole program through [`AssembleOptions::allowed_lints`].
+//!
+//! [`AssembleOptions::allowed_lints`]: super::AssembleOptions::allowed_lints
+
+use super::diagnostic::Span;
+use super::listing::Expansion;
+use super::parser::ParsedLine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A static check and the warnings it produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Lint {
+    /// `SEX R0` before any `SEP`, so X and P both select R0
+    SexR0WhileP0,
+    /// `STXD` or `LDXA` before any `SEX` has chosen a stack register
+    StackBeforeSex,
+    /// Code that runs on into data or the end of the program
+    FallsOffEnd,
+    /// Unlabelled code straight after `BR` or `LBR`
+    Unreachable,
+    /// A branch or `CALL` into data or the middle of an instruction
+    BranchMisaligned,
+    /// A short branch whose operand byte starts a new page
+    BranchPageCrossing,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::SexR0WhileP0,
+        Lint::StackBeforeSex,
+        Lint::FallsOffEnd,
+        Lint::Unreachable,
+        Lint::BranchMisaligned,
+        Lint::BranchPageCrossing,
+    ];
+
+    /// Short ID shown in warnings
+    pub fn id(self) -> &'static str {
+        match self {
+            Lint::SexR0WhileP0 => "L001",
+            Lint::StackBeforeSex => "L002",
+            Lint::FallsOffEnd => "L003",
+            Lint::Unreachable => "L004",
+            Lint::BranchMisaligned => "L005",
+            Lint::BranchPageCrossing => "L006",
+        }
+    }
+
+    /// Readable name, accepted wherever the ID is
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::SexR0WhileP0 => "sex-r0-while-p0",
+            Lint::StackBeforeSex => "stack-before-sex",
+            Lint::FallsOffEnd => "falls-off-end",
+            Lint::Unreachable => "unreachable",
+            Lint::BranchMisaligned => "branch-misaligned",
+            Lint::BranchPageCrossing => "branch-page-crossing",
+        }
+    }
+
+    /// The lint with this ID or name, ignoring case
+    pub fn from_name(text: &str) -> Option<Lint> {
+        let text = text.trim();
+        Lint::ALL
+            .into_iter()
+            .find(|l| l.id().eq_ignore_ascii_case(text) || l.name().eq_ignore_ascii_case(text))
+    }
+}
+
+/// Lints a line's `; allow: ...` comment silences
+pub(super) fn allowed(line: &ParsedLine) -> Vec<Lint> {
+    let Some(comment) = &line.comment else {
+        return Vec::new();
+    };
+    let text = comment.text.to_lowercase();
+    let Some(start) = text.find("allow:") else {
+        return Vec::new();
+    };
+    text[start + "allow:".len()..]
+        .split([',', ' '])
+        .filter_map(Lint::from_name)
+        .collect()
+}
+
+/// One instruction or run of data, and the line it came from
+#[derive(Debug, Clone)]
+pub(super) struct Unit {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub data: bool,
+    pub file: usize,
+    pub line: usize,
+    pub span: Span,
+}
+
+impl Unit {
+    fn end(&self) -> u32 {
+        self.address as u32 + self.bytes.len() as u32
+    }
+
+    fn contains(&self, address: u16) -> bool {
+        (self.address as u32..self.end()).contains(&(address as u32))
+    }
+
+    /// Where a branch, long branch or CALL goes
+    fn branch_target(&self) -> Option<u16> {
+        match self.bytes[..] {
+            [0x38, ..] => None,
+            [0x30..=0x3F, low] if !self.data => {
+                Some((self.address.wrapping_add(1) & 0xFF00) | low as u16)
+            }
+            [0xC0..=0xC3 | 0xC9..=0xCB | 0xD4, high, low] if !self.data => {
+                Some(u16::from_be_bytes([high, low]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Does execution never continue to the next address?
+    fn stops(&self) -> bool {
+        match self.bytes[..] {
+            [0x00] | [0x30, _] | [0xC0, _, _] | [0x70] | [0x71] => true,
+            // SEP (but not a 3-byte SCRT CALL, which returns)
+            [0xD0..=0xDF] => true,
+            _ => false,
+        }
+    }
+}
+
+/// Instructions and data emitted by one line at `address`
+///
+/// Inline data in an expansion (the address after a CALL) is part of the
+/// instruction before it.
+pub(super) fn line_units(
+    line: &ParsedLine,
+    address: u16,
+    bytes: &[u8],
+    expansion: &[Expansion],
+    data: bool,
+) -> Vec<Unit> {
+    let unit = |address, bytes: &[u8]| Unit {
+        address,
+        bytes: bytes.to_vec(),
+        data,
+        file: line.file,
+        line: line.number,
+        span: line.statement_span(),
+    };
+    if expansion.is_empty() {
+        return if bytes.is_empty() {
+            Vec::new()
+        } else {
+            vec![unit(address, bytes)]
+        };
+    }
+
+    let mut units: Vec<Unit> = Vec::new();
+    for step in expansion {
+        match units.last_mut() {
+            Some(last) if step.cycles == 0 => last.bytes.extend(&step.bytes),
+            _ => units.push(unit(step.address, &step.bytes)),
+        }
+    }
+    units
+}
+
+/// Run every lint over `units`, which must be sorted by address
+///
+/// `labelled` holds every label's address; `checked` says whether findings
+/// in a file are reported. Returns the index of the unit each finding is
+/// on, the lint and its message.
+pub(super) fn check(
+    units: &[Unit],
+    labelled: &HashSet<u16>,
+    checked: impl Fn(usize) -> bool,
+) -> Vec<(usize, Lint, String)> {
+    let mut findings = Vec::new();
+    let mut sep_seen = false;
+    let mut sex_seen = false;
+
+    for (i, unit) in units.iter().enumerate() {
+        let next = units.get(i + 1).filter(|n| n.address as u32 == unit.end());
+        if let Some(next) = next
+            && !unit.data
+            && matches!(unit.bytes[0], 0x30 | 0xC0)
+            && !next.data
+            && !labelled.contains(&next.address)
+        {
+            let branch = if unit.bytes[0] == 0x30 { "BR" } else { "LBR" };
+            let message = format!("Unreachable code after {} at {:#06X}", branch, next.address);
+            if checked(next.file) {
+                findings.push((i + 1, Lint::Unreachable, message));
+            }
+        }
+
+        let mut report = |lint, message: String| {
+            if checked(unit.file) {
+                findings.push((i, lint, message));
+            }
+        };
+        if unit.data {
+            continue;
+        }
+
+        match unit.bytes[0] {
+            0xE0 if !sep_seen => report(
+                Lint::SexR0WhileP0,
+                "SEX R0 while P is 0 makes the program counter the index register".to_string(),
+            ),
+            0x72 | 0x73 if !sex_seen => report(
+                Lint::StackBeforeSex,
+                "Stack access before any SEX: X is still 0".to_string(),
+            ),
+            _ => {}
+        }
+        if unit.bytes.len() == 1 {
+            sep_seen |= (0xD0..=0xDF).contains(&unit.bytes[0]);
+            sex_seen |= (0xE0..=0xEF).contains(&unit.bytes[0]);
+        }
+
+        if !unit.stops() && next.is_none_or(|n| n.data) {
+            let message = match next {
+                Some(_) => "Execution runs on into data",
+                None => "Execution runs off the end of the code (add IDL or a branch)",
+            };
+            report(Lint::FallsOffEnd, message.to_string());
+        }
+        if let Some(target) = unit.branch_target() {
+            let landing = units.iter().find(|u| u.contains(target));
+            match landing {
+                Some(u) if u.data => {
+                    report(
+                        Lint::BranchMisaligned,
+                        format!("Branch into data at {:#06X}", target),
+                    );
+                }
+                Some(u) if u.address != target => report(
+                    Lint::BranchMisaligned,
+                    format!(
+                        "Branch into the middle of an instruction at {:#06X}",
+                        target
+                    ),
+                ),
+                _ => {}
+            }
+        }
+        if (0x30..=0x3F).contains(&unit.bytes[0])
+            && unit.bytes[0] != 0x38
+            && unit.bytes.len() == 2
+            && unit.address & 0xFF == 0xFF
+        {
+            report(
+                Lint::BranchPageCrossing,
+                format!(
+                    "Short branch at {:#06X} has its operand on the next page, so it branches within page {:#06X}",
+                    unit.address,
+                    unit.address.wrapping_add(1)
+                ),
+            );
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{AssembleOptions, assemble, assemble_with};
+
+    fn codes(source: &str) -> Vec<(usize, String)> {
+        assemble(source)
+            .unwrap()
+            .diagnostics
+            .into_iter()
+            .filter_map(|d| Some((d.line, d.code?)))
+            .collect()
+    }
+
+    #[test]
+    fn test_x_and_p_lints() {
+        let found = codes("SEX R0\nSTXD\nIDL");
+        assert_eq!(found, vec![(1, "L001".to_string())]);
+
+        let found = codes("LDXA\nSEX R2\nSTXD\nIDL");
+        assert_eq!(found, vec![(1, "L002".to_string())]);
+
+        assert!(codes("LDI 0x10\nPLO R3\nSEP R3\nSEX R0\nIDL").is_empty());
+    }
+
+    #[test]
+    fn test_flow_lints() {
+        let source = "START: LDI 1\nBR START\nGLO R1\nDATA: DB 1";
+        let found = codes(source);
+        assert_eq!(
+            found,
+            vec![(3, "L004".to_string()), (3, "L003".to_string())]
+        );
+
+        let found = codes("BR TABLE\nTABLE: DB 1, 2");
+        assert_eq!(found, vec![(1, "L005".to_string())]);
+        let found = codes("LBZ 0x0004\nLDI 5\nIDL");
+        assert_eq!(found, vec![(1, "L005".to_string())]);
+
+        let found = codes("ORG 0xFF\nBR NEXT\nNEXT: IDL");
+        assert_eq!(found, vec![(2, "L006".to_string())]);
+    }
+
+    #[test]
+    fn test_suppression() {
+        let source = "START: LDI 1\nBR START\nGLO R1 ; allow: unreachable, L003";
+        assert!(codes(source).is_empty());
+
+        let options = AssembleOptions {
+            allowed_lints: vec![Lint::Unreachable, Lint::FallsOffEnd],
+            ..Default::default()
+        };
+        let output = assemble_with("START: BR START\nGLO R1", &options).unwrap();
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(Lint::from_name("l005"), Some(Lint::BranchMisaligned));
+    }
+}
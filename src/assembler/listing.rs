@@ -0,0 +1,313 @@
+use super::AssemblyOutput;
+use crate::cpu::Opcode;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Bytes shown per listing row before wrapping onto a continuation row
+const BYTES_PER_ROW: usize = 4;
+
+/// What a single source line assembled to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListedLine {
+    /// Index of the file the line came from (0 is the main source)
+    #[serde(default)]
+    pub file: usize,
+    /// 1-based line number within that file
+    pub line: usize,
+    /// The source line as written
+    #[serde(default)]
+    pub text: String,
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    /// Real instructions the line expanded into, if it was not emitted as
+    /// written (relaxed branches, pseudo-instructions)
+    pub expansion: Vec<Expansion>,
+}
+
+/// One real instruction generated by an expanding source line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expansion {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+    /// Machine cycles the instruction takes (0 for inline data)
+    #[serde(default)]
+    pub cycles: u8,
+}
+
+impl Expansion {
+    /// A generated instruction, costed from its opcode
+    pub(crate) fn instruction(address: u16, bytes: Vec<u8>, text: String) -> Self {
+        let cycles = Opcode::from_byte(bytes[0]).map_or(2, |op| op.machine_cycles());
+        Self {
+            address,
+            bytes,
+            text,
+            cycles,
+        }
+    }
+
+    /// Inline data that is never executed, such as the address after a CALL
+    pub(crate) fn data(address: u16, bytes: Vec<u8>, text: String) -> Self {
+        Self {
+            address,
+            bytes,
+            text,
+            cycles: 0,
+        }
+    }
+}
+
+impl ListedLine {
+    /// Machine cycles taken by an expanded line's instructions
+    pub fn cycles(&self) -> u32 {
+        self.expansion.iter().map(|e| e.cycles as u32).sum()
+    }
+}
+
+/// A line of a named source file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileLine {
+    pub file: String,
+    /// 1-based line number within that file
+    pub line: usize,
+}
+
+/// A label with its value and the lines that define and use it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u16,
+    /// File the label is defined in
+    #[serde(default)]
+    pub file: String,
+    /// Line the label is defined on
+    pub defined: usize,
+    /// Lines that refer to the label, in order, without duplicates
+    pub references: Vec<FileLine>,
+}
+
+/// Produce a printable assembler listing
+///
+/// Every source line is shown with its line number, address and emitted
+/// bytes, followed by symbol tables sorted by name and by value and a
+/// cross-reference of where each symbol is defined and used. Files pulled
+/// in from elsewhere (such as the SCRT runtime) are listed whole where they
+/// were included, their lines marked with `+`; the cross-reference names
+/// their lines `file:line`.
+pub fn render_listing(source: &str, output: &AssemblyOutput) -> String {
+    let source_lines: Vec<&str> = source.lines().collect();
+    let mut out = String::new();
+    // Number of main-file lines printed so far
+    let mut printed = 0;
+
+    let _ = writeln!(out, "LINE  ADDR  BYTES        SOURCE");
+    for (i, line) in output.lines.iter().enumerate() {
+        // Unlisted main lines (comments, blanks, ORG) before the next
+        // listed main line are printed first
+        let next = output.lines[i..]
+            .iter()
+            .find(|l| l.file == 0)
+            .map_or(source_lines.len(), |l| l.line - 1);
+        while printed < next {
+            let text = source_lines.get(printed).copied().unwrap_or("");
+            let _ = writeln!(out, "{:>4}{:<21}{}", printed + 1, "", text);
+            printed += 1;
+        }
+
+        if line.file == 0 {
+            let text = source_lines
+                .get(line.line - 1)
+                .copied()
+                .unwrap_or(&line.text);
+            write_line(&mut out, line, "  ", text);
+            printed = printed.max(line.line);
+        } else {
+            write_line(&mut out, line, "+ ", &line.text);
+        }
+    }
+    for (i, text) in source_lines.iter().enumerate().skip(printed) {
+        let _ = writeln!(out, "{:>4}{:<21}{}", i + 1, "", text);
+    }
+
+    if output.symbols.is_empty() {
+        return out;
+    }
+
+    let width = output
+        .symbols
+        .iter()
+        .map(|s| s.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+
+    let _ = writeln!(out, "\nSYMBOLS BY NAME");
+    let mut by_name: Vec<&Symbol> = output.symbols.iter().collect();
+    by_name.sort_by(|a, b| a.name.cmp(&b.name));
+    for symbol in &by_name {
+        let _ = writeln!(out, "  {:<width$}  {:04X}", symbol.name, symbol.value);
+    }
+
+    let _ = writeln!(out, "\nSYMBOLS BY VALUE");
+    let mut by_value = by_name.clone();
+    by_value.sort_by_key(|s| s.value);
+    for symbol in &by_value {
+        let _ = writeln!(out, "  {:04X}  {}", symbol.value, symbol.name);
+    }
+
+    // Main-file lines are bare numbers, as in the listing itself
+    let main = output.files.first();
+    let place = |file: &String, line: usize| match Some(file) == main {
+        true => line.to_string(),
+        false => format!("{}:{}", file, line),
+    };
+    let _ = writeln!(out, "\nCROSS REFERENCE");
+    for symbol in &by_name {
+        let used = if symbol.references.is_empty() {
+            "never used".to_string()
+        } else {
+            let lines: Vec<String> = (symbol.references.iter())
+                .map(|r| place(&r.file, r.line))
+                .collect();
+            format!("used {}", lines.join(", "))
+        };
+        let _ = writeln!(
+            out,
+            "  {:<width$}  {:04X}  defined {}; {}",
+            symbol.name,
+            symbol.value,
+            place(&symbol.file, symbol.defined),
+            used
+        );
+    }
+
+    out
+}
+
+/// One listed line, its continuation rows and any expansion rows
+fn write_line(out: &mut String, line: &ListedLine, marker: &str, text: &str) {
+    // Expanded lines list their bytes against each generated instruction
+    let bytes: &[u8] = if line.expansion.is_empty() {
+        &line.bytes
+    } else {
+        &[]
+    };
+    let mut rows = bytes.chunks(BYTES_PER_ROW);
+    let first = rows.next().unwrap_or(&[]);
+    let _ = writeln!(
+        out,
+        "{:>4}{}{:04X}  {:<12} {}",
+        line.line,
+        marker,
+        line.address,
+        hex_bytes(first),
+        text
+    );
+    for (row, chunk) in rows.enumerate() {
+        let address = line.address as usize + (row + 1) * BYTES_PER_ROW;
+        let _ = writeln!(out, "      {:04X}  {}", address, hex_bytes(chunk));
+    }
+
+    for expansion in &line.expansion {
+        let _ = writeln!(
+            out,
+            "      {:04X}  {:<12} +   {}",
+            expansion.address,
+            hex_bytes(&expansion.bytes),
+            expansion.text
+        );
+    }
+    if !line.expansion.is_empty() {
+        let _ = writeln!(
+            out,
+            "{:<25}; {} bytes, {} cycles",
+            "",
+            line.bytes.len(),
+            line.cycles()
+        );
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{AssembleOptions, assemble, assemble_with};
+
+    #[test]
+    fn test_listing_keeps_comments_and_blank_lines() {
+        let source = "; header\n\nSTART: LDI 0x05 ; five\n       BR START";
+        let output = assemble(source).unwrap();
+        let listing = render_listing(source, &output);
+        let lines: Vec<&str> = listing.lines().collect();
+
+        assert_eq!(lines[1].trim_end(), "   1                     ; header");
+        assert_eq!(lines[2].trim_end(), "   2");
+        assert_eq!(lines[3], "   3  0000  F8 05        START: LDI 0x05 ; five");
+        assert_eq!(lines[4], "   4  0002  30 00               BR START");
+    }
+
+    #[test]
+    fn test_listing_wraps_data() {
+        let source = "MSG: DB \"HELLO!\"";
+        let output = assemble(source).unwrap();
+        let listing = render_listing(source, &output);
+
+        assert!(listing.contains("   1  0000  48 45 4C 4C  MSG: DB \"HELLO!\"\n"));
+        assert!(listing.contains("      0004  4F 21\n"));
+    }
+
+    #[test]
+    fn test_listing_shows_expansions() {
+        let options = AssembleOptions {
+            relax_branches: true,
+            ..Default::default()
+        };
+        let source = "B1 FAR\nORG 0x100\nFAR: IDL";
+        let output = assemble_with(source, &options).unwrap();
+        let listing = render_listing(source, &output);
+
+        assert!(listing.contains("   1  0000               B1 FAR\n"));
+        assert!(listing.contains("      0000  3C 05        +   BN1 0x0005\n"));
+        assert!(listing.contains("      0002  C0 01 00     +   LBR FAR\n"));
+    }
+
+    #[test]
+    fn test_listing_marks_runtime_lines() {
+        let source = "; uses SCRT\nCALL SUB\nIDL\nSUB: RETN";
+        let output = assemble(source).unwrap();
+        let listing = render_listing(source, &output);
+        let lines: Vec<&str> = listing.lines().collect();
+
+        assert_eq!(lines[1].trim_end(), "   1                     ; uses SCRT");
+        assert!(lines[2].starts_with("   1+ 0000               ; SCRT start-up"));
+        assert_eq!(lines[3].trim_end(), "   2+ 0000               SCRT_INIT:");
+        assert!(listing.contains("   2  001A               CALL SUB\n"));
+        assert!(listing.contains("      001A  D4           +   SEP R4\n"));
+        assert!(listing.contains("  41+ 002E  C0 00 1F             LBR SCRT_CALL_EXIT\n"));
+    }
+
+    #[test]
+    fn test_symbol_tables_and_cross_reference() {
+        let source = "ZED: NOP\nALPHA: BR ZED\nDW ZED, ALPHA\nUNUSED: IDL";
+        let output = assemble(source).unwrap();
+        let listing = render_listing(source, &output);
+
+        let by_name = listing.split("SYMBOLS BY NAME\n").nth(1).unwrap();
+        assert!(by_name.starts_with("  ALPHA   0001\n  UNUSED  0007\n  ZED     0000\n"));
+
+        let by_value = listing.split("SYMBOLS BY VALUE\n").nth(1).unwrap();
+        assert!(by_value.starts_with("  0000  ZED\n  0001  ALPHA\n  0007  UNUSED\n"));
+
+        assert!(listing.contains("  ZED     0000  defined 1; used 2, 3\n"));
+        assert!(listing.contains("  UNUSED  0007  defined 4; never used\n"));
+    }
+}
@@ -0,0 +1,471 @@
+//! RCA 1802 (COSMAC) assembler
+//!
+//! Source is parsed line by line, labels are collected in a first pass and
+//! instructions are encoded in a second. Problems do not stop assembly:
+//! every error and warning is collected as a [`Diagnostic`] carrying its
+//! file, line and column span.
+
+mod branch;
+mod conditional;
+mod data;
+mod diagnostic;
+mod dialect;
+mod encoder;
+mod format;
+mod include;
+mod lint;
+mod listing;
+mod object;
+mod parser;
+mod pass;
+mod pseudo;
+mod scope;
+mod scrt;
+mod source_map;
+mod stdlib;
+
+pub use conditional::parse_define;
+pub use diagnostic::{Diagnostic, Severity, Span, render_diagnostics};
+pub use dialect::Dialect;
+pub use format::{check_format, format_source};
+pub use include::{FileSystem, MemoryFiles, SourceProvider, render_file_diagnostics};
+pub use lint::Lint;
+pub use listing::{Expansion, FileLine, ListedLine, Symbol, render_listing};
+pub use object::assemble_object;
+pub use pseudo::is_pseudo;
+pub use scrt::{SCRT_FILE, runtime_source};
+pub use source_map::{MappedRange, SourceLocation, SourceMap};
+pub use stdlib::{STDLIB_PREFIX, stdlib_names, stdlib_source};
+
+use crate::formats::{Segment, merge_segments};
+use crate::linker::ObjectFile;
+use parser::{ParsedLine, parse_file};
+use pass::Pass;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// Assembly errors
+#[derive(Debug, Error, Clone)]
+pub enum AssemblyError {
+    #[error("Invalid instruction: {0}")]
+    InvalidInstruction(String),
+
+    #[error("Invalid register: {0}")]
+    InvalidRegister(String),
+
+    #[error("Invalid operand: {0}")]
+    InvalidOperand(String),
+
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+
+    #[error("Undefined label: {0}")]
+    UndefinedLabel(String),
+
+    #[error("Duplicate label: {name} (first defined on line {line})")]
+    DuplicateLabel { name: String, line: usize },
+
+    #[error("Short branch target {target:#06X} is not on the current page {page:#06X}")]
+    BranchOffPage { target: u16, page: u16 },
+
+    #[error("Cannot include {name}: {reason}")]
+    Include { name: String, reason: String },
+
+    #[error("Include cycle: {0}")]
+    IncludeCycle(String),
+
+    #[error("Invalid condition: {0}")]
+    InvalidCondition(String),
+
+    #[error("{0} without matching IF")]
+    UnmatchedConditional(String),
+
+    #[error("IF without ENDIF")]
+    UnterminatedConditional,
+
+    /// Assembly failed; holds every error and warning that was found
+    #[error("{}", summarize(.0))]
+    Diagnostics(Vec<Diagnostic>),
+}
+
+impl AssemblyError {
+    /// All diagnostics carried by this error (empty for single-item errors)
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        match self {
+            AssemblyError::Diagnostics(diagnostics) => diagnostics,
+            _ => &[],
+        }
+    }
+}
+
+/// One-line-per-error summary used for the `Display` impl
+fn summarize(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(|d| format!("line {}:{}: {}", d.line, d.span.start + 1, d.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Assembled output with machine code and disassembly
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AssemblyOutput {
+    pub machine_code: Vec<u8>,
+    pub disassembly: Vec<String>,
+    /// Warnings produced during a successful assembly
+    pub diagnostics: Vec<Diagnostic>,
+    /// Source file names, the main file first, indexed by
+    /// [`ListedLine::file`]
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// What each labelled or code-bearing main-file line, and every line of
+    /// an included file, assembled to
+    pub lines: Vec<ListedLine>,
+    /// Every label, sorted by name, with where it is defined and used
+    pub symbols: Vec<Symbol>,
+    /// Where every emitted byte came from, and the reverse
+    pub source_map: SourceMap,
+    /// Sections and relocations, from [`assemble_object`] only
+    #[serde(default)]
+    pub object: Option<ObjectFile>,
+}
+
+impl AssemblyOutput {
+    /// The emitted bytes as load segments, leaving out ORG gaps
+    pub fn segments(&self) -> Vec<Segment> {
+        merge_segments(
+            self.lines
+                .iter()
+                .map(|line| Segment::new(line.address, line.bytes.clone()))
+                .collect(),
+        )
+    }
+
+    /// Bytes the program's source emitted, counting included files but
+    /// not ORG gaps or the SCRT runtime
+    pub fn program_bytes(&self) -> usize {
+        (self.source_map.ranges().iter())
+            .filter(|r| r.location.file != SCRT_FILE)
+            .map(|r| r.length as usize)
+            .sum()
+    }
+}
+
+/// Options controlling a single assembly run
+#[derive(Debug, Clone)]
+pub struct AssembleOptions {
+    /// File name reported in diagnostics
+    pub file_name: String,
+
+    /// Rewrite short branches whose target is on another page into their
+    /// long equivalents instead of reporting an error
+    pub relax_branches: bool,
+
+    /// Splice in the SCRT runtime when the program uses CALL or RETN and
+    /// does not define its own `SCRT_CALL`
+    pub scrt: bool,
+
+    /// Initial SCRT stack pointer (R2); the stack grows downwards
+    pub scrt_stack: u16,
+
+    /// Symbols for conditional assembly, as if `DEFINE`d before the first
+    /// line (see [`parse_define`]); those with numeric values can also be
+    /// used as operands, `ORG BASE` or `OUT PORT`, like labels
+    pub defines: HashMap<String, String>,
+
+    /// Source syntax, for assembling code written for other assemblers
+    pub dialect: Dialect,
+
+    /// Lints not to report anywhere in the program
+    pub allowed_lints: Vec<Lint>,
+}
+
+impl Default for AssembleOptions {
+    fn default() -> Self {
+        Self {
+            file_name: "input.asm".to_string(),
+            relax_branches: false,
+            scrt: true,
+            scrt_stack: 0xFFFF,
+            defines: HashMap::new(),
+            dialect: Dialect::Native,
+            allowed_lints: Vec::new(),
+        }
+    }
+}
+
+/// Assemble RCA 1802 (COSMAC) assembly source code
+pub fn assemble(source: &str) -> Result<AssemblyOutput, AssemblyError> {
+    assemble_with(source, &AssembleOptions::default())
+}
+
+/// Assemble source code with explicit options
+///
+/// On failure the error holds every diagnostic found, not just the first.
+///
+/// With `relax_branches` set, every short branch found to target another
+/// page is widened and the whole program is laid out again. Widening only
+/// ever grows code, so this repeats until no new branch needs relaxing.
+pub fn assemble_with(
+    source: &str,
+    options: &AssembleOptions,
+) -> Result<AssemblyOutput, AssemblyError> {
+    assemble_project(source, options, &MemoryFiles::new())
+}
+
+/// Assemble the file `name` read from `provider`, along with everything it
+/// includes
+pub fn assemble_file(
+    name: &str,
+    provider: &dyn SourceProvider,
+    options: &AssembleOptions,
+) -> Result<AssemblyOutput, AssemblyError> {
+    let source = include::read_source(provider, name).map_err(|e| AssemblyError::Include {
+        name: name.to_string(),
+        reason: e.to_string(),
+    })?;
+    let options = AssembleOptions {
+        file_name: name.to_string(),
+        ..options.clone()
+    };
+    assemble_project(&source, &options, provider)
+}
+
+/// Assemble `source`, reading any files it includes from `provider`
+///
+/// Diagnostics, listing rows and source map entries name the file each
+/// line came from.
+pub fn assemble_project(
+    source: &str,
+    options: &AssembleOptions,
+    provider: &dyn SourceProvider,
+) -> Result<AssemblyOutput, AssemblyError> {
+    let (lines, files) = prepare(source, options, provider)?;
+    let mut relaxed = HashSet::new();
+
+    loop {
+        let pass = Pass::run(&lines, &files, options, &relaxed, false);
+        let before = relaxed.len();
+        if options.relax_branches && !pass.off_page.is_empty() {
+            relaxed.extend(pass.off_page.iter().copied());
+            // A relaxed line can still be off its page, e.g. a flag branch
+            // whose skip crosses one; widening again would change nothing
+            if relaxed.len() > before {
+                continue;
+            }
+        }
+        return pass.finish();
+    }
+}
+
+/// Expand includes, conditionals and the SCRT runtime and resolve label
+/// scopes, giving the lines to lay out and the files they came from
+fn prepare(
+    source: &str,
+    options: &AssembleOptions,
+    provider: &dyn SourceProvider,
+) -> Result<(Vec<ParsedLine>, Vec<String>), AssemblyError> {
+    let mut files = vec![options.file_name.clone()];
+    let lines = parse_file(0, source, options.dialect);
+    let mut lines = include::preprocess(
+        lines,
+        &mut files,
+        provider,
+        &options.defines,
+        options.dialect,
+    )?;
+    if options.scrt && scrt::needs_runtime(&lines) {
+        scrt::include_runtime(&mut lines, files.len(), options.scrt_stack);
+        files.push(SCRT_FILE.to_string());
+    }
+    scope::resolve_scopes(&mut lines, &files)?;
+    Ok((lines, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble_simple() {
+        let source = "LDI 0x42\nPHI R5\nIDL";
+        let result = assemble(source).unwrap();
+
+        assert_eq!(result.machine_code, vec![0xF8, 0x42, 0xB5, 0x00]);
+    }
+
+    #[test]
+    fn test_assemble_with_labels() {
+        let source = r#"
+START: LDI 0x10
+       PHI R3
+       BR START
+"#;
+        let result = assemble(source).unwrap();
+
+        // LDI 0x10 = F8 10
+        // PHI R3 = B3
+        // BR START (offset 0) = 30 00
+        assert_eq!(result.machine_code, vec![0xF8, 0x10, 0xB3, 0x30, 0x00]);
+    }
+
+    #[test]
+    fn test_assemble_long_branch() {
+        let source = "LBR 0x1234";
+        let result = assemble(source).unwrap();
+
+        // LBR 0x1234 = C0 12 34
+        assert_eq!(result.machine_code, vec![0xC0, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn test_assemble_register_ops() {
+        let source = r#"
+INC R3
+DEC RA
+GLO R5
+GHI R7
+"#;
+        let result = assemble(source).unwrap();
+
+        // INC R3 = 13
+        // DEC RA = 2A
+        // GLO R5 = 85
+        // GHI R7 = 97
+        assert_eq!(result.machine_code, vec![0x13, 0x2A, 0x85, 0x97]);
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = r#"
+; This is a comment
+LDI 0x42  ; Load immediate
+PHI R5    # Store to R5 high byte
+"#;
+        let result = assemble(source).unwrap();
+
+        assert_eq!(result.machine_code, vec![0xF8, 0x42, 0xB5]);
+    }
+
+    #[test]
+    fn test_invalid_instruction() {
+        let source = "INVALID";
+        let result = assemble(source);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_undefined_label() {
+        let source = "BR UNDEFINED";
+        let result = assemble(source);
+        // Undefined label should cause an error when it can't be parsed as a number
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_reports_all_errors() {
+        let source = "PHY R1\nLDI 0x05\nGLO RZ\nBR NOWHERE";
+        let err = assemble(source).unwrap_err();
+        let diagnostics = err.diagnostics();
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].line, 1);
+        assert_eq!(diagnostics[0].span, Span::new(0, 3));
+        assert_eq!(diagnostics[0].suggestion.as_deref(), Some("PHI"));
+        assert_eq!(diagnostics[1].line, 3);
+        assert_eq!(diagnostics[1].span, Span::new(4, 6));
+        assert_eq!(diagnostics[2].line, 4);
+        assert!(diagnostics[2].message.contains("NOWHERE"));
+    }
+
+    #[test]
+    fn test_warnings_do_not_fail() {
+        let result = assemble("LDI 0x1234\nIDL 5").unwrap();
+
+        assert_eq!(result.machine_code, vec![0xF8, 0x34, 0x00]);
+        assert_eq!(result.diagnostics.len(), 2);
+        assert!(
+            result
+                .diagnostics
+                .iter()
+                .all(|d| d.severity == Severity::Warning)
+        );
+    }
+
+    #[test]
+    fn test_undefined_label_suggestion() {
+        let source = "LOOP: INC R1\nBR LOPP";
+        let err = assemble(source).unwrap_err();
+
+        assert_eq!(err.diagnostics()[0].suggestion.as_deref(), Some("LOOP"));
+        let text = render_diagnostics(err.diagnostics(), source);
+        assert!(text.contains("2 | BR LOPP\n  |    ^^^^ did you mean `LOOP`?"));
+    }
+
+    #[test]
+    fn test_org_pads_image() {
+        let result = assemble("ORG 0x04\nIDL").unwrap();
+
+        assert_eq!(result.machine_code, vec![0, 0, 0, 0, 0x00]);
+        assert_eq!(result.disassembly, vec!["0004: 00       | IDL"]);
+    }
+
+    #[test]
+    fn test_scrt_runtime_included_on_use() {
+        let output = assemble("START: CALL SUB\n       IDL\nSUB:   RETN").unwrap();
+        let value = |name: &str| {
+            output
+                .symbols
+                .iter()
+                .find(|s| s.name == name)
+                .unwrap()
+                .value
+        };
+
+        // Start-up code comes first and hands over to the program as P = 3
+        assert_eq!(&output.machine_code[..2], &[0xF8, 0xFF]);
+        assert_eq!(output.source_map.location(0).unwrap().file, SCRT_FILE);
+        let start = value("START") as usize;
+        assert_eq!(value("SCRT_MAIN") as usize, start);
+        assert_eq!(
+            &output.machine_code[start..start + 5],
+            &[0xD4, 0x00, value("SUB") as u8, 0x00, 0xD5]
+        );
+        assert!(value("SCRT_CALL") > value("SUB"));
+
+        let call = output
+            .lines
+            .iter()
+            .find(|l| l.file == 0 && l.line == 1)
+            .unwrap();
+        assert_eq!(call.expansion.len(), 2);
+        assert_eq!(call.expansion[1].text, "DW SUB");
+
+        let plain = assemble("IDL").unwrap();
+        assert_eq!(plain.machine_code, vec![0x00]);
+    }
+
+    #[test]
+    fn test_segments_skip_org_gaps() {
+        let result = assemble("LDI 1\nORG 0x100\nIDL").unwrap();
+
+        assert_eq!(
+            result.segments(),
+            vec![
+                Segment::new(0x0000, vec![0xF8, 0x01]),
+                Segment::new(0x0100, vec![0x00]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_directives() {
+        let result = assemble("TABLE: DB 1, 'A', \"HI\"\nDW TABLE, 0x1234").unwrap();
+
+        assert_eq!(
+            result.machine_code,
+            vec![0x01, 0x41, 0x48, 0x49, 0x00, 0x00, 0x12, 0x34]
+        );
+    }
+}
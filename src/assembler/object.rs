@@ -0,0 +1,347 @@
+//! Relocatable object output
+//!
+//! In object mode there is no `ORG`. Code and data go into named sections
+//! instead, each with its own location counter starting at zero:
+//!
+//! - `SECTION name` switches to (or back to) a section; code before the
+//!   first one goes in `CODE`.
+//! - `EXTERN name, ...` declares labels defined in other objects.
+//! - `PUBLIC name, ...` exports labels defined here.
+//!
+//! Every field holding a label's address gets a relocation record, so the
+//! [linker](crate::linker) can patch it once sections are placed. The SCRT
+//! runtime is never spliced in; link it from an object of its own.
+
+use super::diagnostic::{Diagnostic, Span};
+use super::encoder::{Encoder, Issue};
+use super::parser::Token;
+use super::pass::Pass;
+use super::{AssembleOptions, AssemblyError, AssemblyOutput, SourceProvider, prepare};
+use crate::linker::{
+    ObjectFile, ObjectSymbol, Relocation, RelocationKind, RelocationTarget, Section,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Section code goes in until the first `SECTION`
+const DEFAULT_SECTION: &str = "CODE";
+
+/// Assemble `source` into a relocatable object, held in the output's
+/// `object`
+///
+/// Addresses in the listing and symbol table are offsets within each
+/// label's section.
+pub fn assemble_object(
+    source: &str,
+    options: &AssembleOptions,
+    provider: &dyn SourceProvider,
+) -> Result<AssemblyOutput, AssemblyError> {
+    let options = AssembleOptions {
+        scrt: false,
+        relax_branches: false,
+        ..options.clone()
+    };
+    let (lines, files) = prepare(source, &options, provider)?;
+    Pass::run(&lines, &files, &options, &HashSet::new(), true).finish()
+}
+
+/// A field of the line just encoded that holds a label's address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Fixup {
+    /// Offset of the field from the line's first byte
+    pub offset: u16,
+    pub kind: RelocationKind,
+    pub label: String,
+}
+
+impl Encoder<'_> {
+    /// Resolve a target held in a field `offset` bytes into the line,
+    /// noting a fixup if it names a label
+    pub(crate) fn resolve_field(
+        &mut self,
+        operand: &Token,
+        offset: u16,
+        kind: RelocationKind,
+    ) -> Result<u16, Issue> {
+        let before = self.references.len();
+        let value = self.resolve_target(operand)?;
+        if let Some(label) = self.references.get(before) {
+            self.fixups.push(Fixup {
+                offset,
+                kind,
+                label: label.clone(),
+            });
+        }
+        Ok(value)
+    }
+}
+
+/// Sections, symbols and relocations collected while assembling an object
+pub(super) struct ObjectBuilder {
+    sections: Vec<Section>,
+    /// Location counter of each section while it is not current
+    counters: Vec<u16>,
+    /// Index of the section code is going into
+    pub current: usize,
+    /// Section of each source line
+    line_sections: Vec<usize>,
+    /// Section each label was defined in
+    label_sections: HashMap<String, usize>,
+    pub imports: Vec<String>,
+    /// `PUBLIC` names, with the file, line and span declaring them
+    pub exports: Vec<(String, usize, usize, Span)>,
+}
+
+impl ObjectBuilder {
+    pub fn new(lines: usize) -> Self {
+        Self {
+            sections: vec![Section::new(DEFAULT_SECTION)],
+            counters: vec![0],
+            current: 0,
+            line_sections: vec![0; lines],
+            label_sections: HashMap::new(),
+            imports: Vec::new(),
+            exports: Vec::new(),
+        }
+    }
+
+    /// Make `name` the current section, leaving the old one at `address`;
+    /// returns where the new section carries on from
+    pub fn switch(&mut self, name: &str, address: u16) -> u16 {
+        let name = name.to_uppercase();
+        self.counters[self.current] = address;
+        self.current = match self.sections.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                self.sections.push(Section::new(name));
+                self.counters.push(0);
+                self.sections.len() - 1
+            }
+        };
+        self.counters[self.current]
+    }
+
+    /// Note that line `index` and the label on it are in the current section
+    pub fn place(&mut self, index: usize, label: Option<&str>) {
+        self.line_sections[index] = self.current;
+        if let Some(label) = label {
+            self.label_sections.insert(label.to_string(), self.current);
+        }
+    }
+
+    /// Store the bytes line `index` assembled to at `offset` in its section,
+    /// with a relocation for each label field
+    pub fn emit(
+        &mut self,
+        index: usize,
+        offset: u16,
+        bytes: &[u8],
+        fixups: Vec<Fixup>,
+        labels: &HashMap<String, u16>,
+    ) {
+        let section = &mut self.sections[self.line_sections[index]];
+        let start = offset as usize;
+        if section.data.len() < start + bytes.len() {
+            section.data.resize(start + bytes.len(), 0);
+        }
+        section.data[start..start + bytes.len()].copy_from_slice(bytes);
+
+        for fixup in fixups {
+            let (target, addend) = if self.imports.contains(&fixup.label) {
+                (RelocationTarget::Symbol(fixup.label), 0)
+            } else {
+                let section = self.label_sections[&fixup.label];
+                (RelocationTarget::Section(section), labels[&fixup.label])
+            };
+            section.relocations.push(Relocation {
+                offset: offset.wrapping_add(fixup.offset),
+                kind: fixup.kind,
+                target,
+                addend,
+            });
+        }
+    }
+
+    /// The finished object, and errors for exports that are not defined
+    /// here
+    pub fn finish(
+        self,
+        name: &str,
+        labels: &HashMap<String, u16>,
+        files: &[String],
+    ) -> (ObjectFile, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut exports = Vec::new();
+        for (symbol, file, line, span) in self.exports {
+            match self.label_sections.get(&symbol) {
+                Some(&section) if !self.imports.contains(&symbol) => exports.push(ObjectSymbol {
+                    offset: labels[&symbol],
+                    name: symbol,
+                    section,
+                }),
+                _ => {
+                    let error = AssemblyError::UndefinedLabel(symbol);
+                    diagnostics.push(Diagnostic::error(
+                        &files[file],
+                        line,
+                        span,
+                        error.to_string(),
+                    ));
+                }
+            }
+        }
+
+        let object = ObjectFile {
+            name: name.to_string(),
+            sections: self.sections,
+            exports,
+            imports: self.imports,
+        };
+        (object, diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::MemoryFiles;
+    use crate::linker::{MemoryLayout, link};
+
+    fn object(name: &str, source: &str) -> ObjectFile {
+        let options = AssembleOptions {
+            file_name: name.to_string(),
+            ..Default::default()
+        };
+        assemble_object(source, &options, &MemoryFiles::new())
+            .unwrap()
+            .object
+            .unwrap()
+    }
+
+    const MAIN: &str = "        EXTERN PRINT, MESSAGE
+        PUBLIC START
+START:  LDI HIGH(MESSAGE)
+        PHI RF
+        LDI LOW(MESSAGE)
+        PLO RF
+        LBR PRINT
+        SECTION DATA
+COUNT:  DB 0
+        DW COUNT";
+
+    const LIB: &str = "        PUBLIC PRINT, MESSAGE
+PRINT:  LDA RF
+        BZ DONE
+        OUT 1
+        BR PRINT
+DONE:   IDL
+        SECTION DATA
+MESSAGE: DB \"HI\", 0";
+
+    #[test]
+    fn test_object_sections_and_relocations() {
+        let main = object("main.asm", MAIN);
+
+        assert_eq!(main.imports, vec!["PRINT", "MESSAGE"]);
+        assert_eq!(main.exports[0].name, "START");
+        let names: Vec<&str> = main.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["CODE", "DATA"]);
+
+        let code = &main.sections[0];
+        assert_eq!(code.data.len(), 9);
+        let kinds: Vec<RelocationKind> = code.relocations.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RelocationKind::High,
+                RelocationKind::Low,
+                RelocationKind::Word
+            ]
+        );
+        assert_eq!(
+            main.sections[1].relocations[0],
+            Relocation {
+                offset: 1,
+                kind: RelocationKind::Word,
+                target: RelocationTarget::Section(1),
+                addend: 0,
+            }
+        );
+
+        let text = main.to_json();
+        assert_eq!(ObjectFile::from_json(&text).unwrap(), main);
+    }
+
+    #[test]
+    fn test_link_two_objects() {
+        let layout = MemoryLayout::parse("CODE 0x0000 0x00FF\nDATA 0x0100  # after code").unwrap();
+        let objects = [object("main.asm", MAIN), object("lib.asm", LIB)];
+        let image = link(&objects, &layout).unwrap();
+
+        assert_eq!(image.symbols["START"], 0x0000);
+        assert_eq!(image.symbols["PRINT"], 0x0009);
+        assert_eq!(image.symbols["MESSAGE"], 0x0103);
+
+        let code = &image.segments[0];
+        assert_eq!(code.address, 0x0000);
+        assert_eq!(
+            code.data[..9],
+            [0xF8, 0x01, 0xBF, 0xF8, 0x03, 0xAF, 0xC0, 0x00, 0x09]
+        );
+        // BZ DONE and BR PRINT inside lib.asm's code, now at 0x0009
+        assert_eq!(code.data[0x0A..0x0E], [0x32, 0x0F, 0x61, 0x30]);
+        assert_eq!(code.data[0x0E], 0x09);
+        assert_eq!(
+            image.segments[1].data,
+            vec![0x00, 0x01, 0x00, 0x48, 0x49, 0x00]
+        );
+
+        let map = image.map_file();
+        assert!(map.contains("  0009   000F   0007  CODE     lib.asm\n"));
+        assert!(map.contains("  0103  MESSAGE\n"));
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let layout = MemoryLayout::parse("CODE F2\nDATA 200").unwrap();
+        let objects = [object("main.asm", MAIN), object("lib.asm", LIB)];
+        let err = link(&objects, &layout).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lib.asm: short branch at 0x00FD to 0x0101 leaves its page"
+        );
+
+        let err = link(&objects[..1], &layout).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Undefined symbol PRINT (imported by main.asm)"
+        );
+
+        let layout = MemoryLayout::parse("CODE 0 0x0E\nDATA 0x10").unwrap();
+        assert!(link(&objects, &layout).is_err());
+        assert!(MemoryLayout::parse("CODE 0\nCODE 100").is_err());
+    }
+
+    #[test]
+    fn test_object_directive_errors() {
+        let err = assemble_object(
+            "ORG 0x100\nPUBLIC NOWHERE",
+            &AssembleOptions::default(),
+            &MemoryFiles::new(),
+        )
+        .unwrap_err();
+        let messages: Vec<&str> = err
+            .diagnostics()
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid operand: ORG is not allowed in a relocatable object; use SECTION",
+                "Undefined label: NOWHERE",
+            ]
+        );
+
+        assert!(crate::assembler::assemble("SECTION DATA").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use super::diagnostic::Span;
+use super::dialect::{Dialect, translate_a18};
+
+/// A piece of source text and the columns it occupies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub span: Span,
+}
+
+/// One source line split into label, mnemonic, operands and comment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedLine {
+    /// Index of the file the line came from (0 is the main source)
+    pub file: usize,
+    /// 1-based line number within that file
+    pub number: usize,
+    /// The whole line as written
+    pub text: String,
+    pub label: Option<Token>,
+    pub mnemonic: Option<Token>,
+    pub operands: Vec<Token>,
+    /// Comment text including its leading `;` or `#`
+    pub comment: Option<Token>,
+}
+
+impl ParsedLine {
+    /// Span covering the mnemonic and all operands
+    pub fn statement_span(&self) -> Span {
+        let start = self.mnemonic.as_ref().map_or(0, |m| m.span.start);
+        let end = self
+            .operands
+            .last()
+            .or(self.mnemonic.as_ref())
+            .map_or(start, |t| t.span.end);
+        Span::new(start, end)
+    }
+}
+
+/// Parse a single line of assembly source written in `dialect`, giving it
+/// in native syntax
+///
+/// Layout is `[LABEL:] [MNEMONIC [OPERAND {, OPERAND}]] [; comment]`.
+/// Operands are separated by commas; a lone operand may contain spaces.
+pub fn parse_line(number: usize, line: &str, dialect: Dialect) -> ParsedLine {
+    let chars: Vec<char> = line.chars().collect();
+    let mut parsed = ParsedLine {
+        number,
+        text: line.to_string(),
+        ..Default::default()
+    };
+
+    // Comments run from the first ; or # outside quotes to the end of the line
+    let code_end = find_unquoted(&chars, 0, chars.len(), |i| dialect.comment_at(&chars, i))
+        .unwrap_or(chars.len());
+    if code_end < chars.len() {
+        parsed.comment = Some(token(&chars, code_end, chars.len()));
+    }
+
+    let mut pos = 0;
+
+    // Label (everything before a colon, or a word in column 1)
+    if let Some(colon) = find_unquoted(&chars, 0, code_end, |i| chars[i] == ':') {
+        parsed.label = trimmed_token(&chars, 0, colon);
+        pos = colon + 1;
+    } else if dialect.column_labels() && code_end > 0 && !chars[0].is_whitespace() {
+        while pos < code_end && !chars[pos].is_whitespace() {
+            pos += 1;
+        }
+        parsed.label = Some(token(&chars, 0, pos));
+    }
+
+    // Mnemonic (first word after the label)
+    let start = skip_whitespace(&chars, pos, code_end);
+    if start == code_end {
+        return parsed;
+    }
+    let mut end = start;
+    while end < code_end && !chars[end].is_whitespace() {
+        end += 1;
+    }
+    parsed.mnemonic = Some(token(&chars, start, end));
+
+    // Operands (comma separated)
+    let rest = skip_whitespace(&chars, end, code_end);
+    let mut field_start = rest;
+    while field_start < code_end {
+        let field_end =
+            find_unquoted(&chars, field_start, code_end, |i| chars[i] == ',').unwrap_or(code_end);
+        parsed.operands.push(
+            trimmed_token(&chars, field_start, field_end)
+                .unwrap_or_else(|| token(&chars, field_start, field_start)),
+        );
+        field_start = field_end + 1;
+    }
+
+    if dialect == Dialect::A18 {
+        translate_a18(&mut parsed);
+    }
+    parsed
+}
+
+/// Parse every line of the file with index `file`
+pub fn parse_file(file: usize, source: &str, dialect: Dialect) -> Vec<ParsedLine> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| ParsedLine {
+            file,
+            ..parse_line(i + 1, line, dialect)
+        })
+        .collect()
+}
+
+/// Position of the first character in `chars[start..end]` whose index
+/// matches `pred` and that is not inside a '...' or "..." literal
+fn find_unquoted(
+    chars: &[char],
+    start: usize,
+    end: usize,
+    pred: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    let mut quote = None;
+    for (i, &c) in chars.iter().enumerate().take(end).skip(start) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if pred(i) => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+fn skip_whitespace(chars: &[char], mut pos: usize, end: usize) -> usize {
+    while pos < end && chars[pos].is_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+fn token(chars: &[char], start: usize, end: usize) -> Token {
+    Token {
+        text: chars[start..end].iter().collect(),
+        span: Span::new(start, end),
+    }
+}
+
+/// Token for `chars[start..end]` with surrounding whitespace removed
+fn trimmed_token(chars: &[char], start: usize, end: usize) -> Option<Token> {
+    let start = skip_whitespace(chars, start, end);
+    let mut end = end;
+    while end > start && chars[end - 1].is_whitespace() {
+        end -= 1;
+    }
+    (start < end).then(|| token(chars, start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_line() {
+        let line = parse_line(7, "LOOP:  ADI 0x05, 3  ; add", Dialect::Native);
+        assert_eq!(line.number, 7);
+        assert_eq!(line.label.unwrap().text, "LOOP");
+
+        let mnemonic = line.mnemonic.unwrap();
+        assert_eq!(mnemonic.text, "ADI");
+        assert_eq!(mnemonic.span, Span::new(7, 10));
+
+        assert_eq!(line.operands.len(), 2);
+        assert_eq!(line.operands[0].text, "0x05");
+        assert_eq!(line.operands[0].span, Span::new(11, 15));
+        assert_eq!(line.operands[1].text, "3");
+        assert_eq!(line.comment.unwrap().text, "; add");
+    }
+
+    #[test]
+    fn test_parse_label_only_and_blank() {
+        let line = parse_line(1, "START:", Dialect::Native);
+        assert_eq!(line.label.unwrap().text, "START");
+        assert!(line.mnemonic.is_none());
+
+        let line = parse_line(2, "   # just a comment", Dialect::Native);
+        assert!(line.label.is_none());
+        assert!(line.mnemonic.is_none());
+        assert_eq!(line.comment.unwrap().span.start, 3);
+    }
+
+    #[test]
+    fn test_parse_quoted_operands() {
+        let line = parse_line(1, "MSG: DB \"A; B, C\", ':'  ; text", Dialect::Native);
+        assert_eq!(line.label.unwrap().text, "MSG");
+        assert_eq!(line.operands.len(), 2);
+        assert_eq!(line.operands[0].text, "\"A; B, C\"");
+        assert_eq!(line.operands[1].text, "':'");
+        assert_eq!(line.comment.unwrap().text, "; text");
+    }
+}
@@ -0,0 +1,456 @@
+mod warnings;
+
+use super::branch::relaxed_length;
+use super::data::DataKind;
+use super::diagnostic::Span;
+use super::encoder::{Encoder, Issue, parse_number, statement_length, unknown_mnemonic};
+use super::lint::{self, Lint, Unit};
+use super::object::ObjectBuilder;
+use super::parser::ParsedLine;
+use super::scope::is_anonymous;
+use super::source_map::{SourceLocation, SourceMap};
+use super::{
+    AssembleOptions, AssemblyError, AssemblyOutput, Diagnostic, FileLine, ListedLine, Severity,
+    Symbol,
+};
+use std::collections::{HashMap, HashSet};
+
+/// One complete layout and encoding of the source
+pub(super) struct Pass<'a> {
+    options: &'a AssembleOptions,
+    /// File names, indexed by `ParsedLine::file`
+    files: &'a [String],
+    diagnostics: Vec<Diagnostic>,
+    labels: HashMap<String, u16>,
+    /// Caller defines with numeric values, for ORG and the encoder
+    defines: HashMap<String, u16>,
+    /// File, line and span each label is defined at, and the file and
+    /// line of each use
+    definitions: HashMap<String, (usize, usize, Span)>,
+    references: HashMap<String, Vec<(usize, usize)>>,
+    /// Address of each line, or `None` if the line could not be laid out
+    addresses: Vec<Option<u16>>,
+    machine_code: Vec<u8>,
+    disassembly: Vec<String>,
+    listed: Vec<ListedLine>,
+    source_map: SourceMap,
+    /// Lines holding short branches that could be relaxed
+    pub off_page: Vec<usize>,
+    /// Sections and relocations, when assembling a relocatable object
+    object: Option<ObjectBuilder>,
+    /// Instructions and data emitted, for the lints
+    units: Vec<Unit>,
+    /// Lints silenced on each (file, line)
+    allowed: HashMap<(usize, usize), Vec<Lint>>,
+}
+
+impl<'a> Pass<'a> {
+    pub fn run(
+        lines: &[ParsedLine],
+        files: &'a [String],
+        options: &'a AssembleOptions,
+        relaxed: &HashSet<usize>,
+        relocatable: bool,
+    ) -> Self {
+        let mut pass = Self {
+            options,
+            files,
+            diagnostics: Vec::new(),
+            labels: HashMap::new(),
+            defines: options
+                .defines
+                .iter()
+                .filter_map(|(name, value)| Some((name.to_uppercase(), parse_number(value).ok()?)))
+                .collect(),
+            definitions: HashMap::new(),
+            references: HashMap::new(),
+            addresses: vec![None; lines.len()],
+            machine_code: Vec::new(),
+            disassembly: Vec::new(),
+            listed: Vec::new(),
+            source_map: SourceMap::default(),
+            off_page: Vec::new(),
+            object: relocatable.then(|| ObjectBuilder::new(lines.len())),
+            units: Vec::new(),
+            allowed: HashMap::new(),
+        };
+        pass.layout(lines, relaxed);
+        pass.encode(lines, relaxed);
+        pass
+    }
+
+    fn report(&mut self, severity: Severity, line: &ParsedLine, issue: Issue) {
+        let file = &self.files[line.file];
+        let diagnostic = match severity {
+            Severity::Error => Diagnostic::error(file, line.number, issue.span, issue.message),
+            Severity::Warning => Diagnostic::warning(file, line.number, issue.span, issue.message),
+        };
+        self.diagnostics
+            .push(diagnostic.with_suggestion(issue.suggestion));
+    }
+
+    /// First pass: assign addresses, collect labels and find lines whose
+    /// mnemonic is unknown
+    fn layout(&mut self, lines: &[ParsedLine], relaxed: &HashSet<usize>) {
+        let mut current_address: u16 = 0;
+
+        for (i, line) in lines.iter().enumerate() {
+            let mnemonic = line.mnemonic.as_ref();
+
+            // ORG, PAGE and SECTION move the location counter before any
+            // label is bound
+            let directive = mnemonic.map(|m| m.text.to_uppercase());
+            let moved = match directive.as_deref() {
+                Some(d @ ("ORG" | "PAGE")) if self.object.is_some() => Err(Issue::error(
+                    line.statement_span(),
+                    AssemblyError::InvalidOperand(format!(
+                        "{} is not allowed in a relocatable object; use SECTION",
+                        d
+                    )),
+                )),
+                Some("ORG") => {
+                    org_address(line, &self.defines).map(|address| current_address = address)
+                }
+                Some("PAGE") => {
+                    current_address = current_address.wrapping_add(0xFF) & 0xFF00;
+                    Ok(())
+                }
+                Some(d @ ("SECTION" | "EXTERN" | "PUBLIC")) => {
+                    self.object_directive(line, d, &mut current_address)
+                }
+                _ => Ok(()),
+            };
+            if let Err(issue) = moved {
+                self.report(Severity::Error, line, issue);
+                continue;
+            }
+
+            if let Some(label) = &line.label {
+                if label.text.contains(char::is_whitespace) {
+                    let issue =
+                        Issue::error(label.span, AssemblyError::InvalidLabel(label.text.clone()));
+                    self.report(Severity::Error, line, issue);
+                } else {
+                    let name = label.text.to_uppercase();
+                    if let Some(&(_, first, _)) = self.definitions.get(&name) {
+                        let error = AssemblyError::DuplicateLabel { name, line: first };
+                        self.report(Severity::Error, line, Issue::error(label.span, error));
+                    } else {
+                        self.definitions
+                            .insert(name.clone(), (line.file, line.number, label.span));
+                        if let Some(object) = &mut self.object {
+                            object.place(i, Some(&name));
+                        }
+                        self.labels.insert(name, current_address);
+                    }
+                }
+            }
+
+            if let Some(object) = &mut self.object {
+                object.place(i, None);
+            }
+            let length = match mnemonic {
+                None => 0,
+                Some(m) if is_layout_directive(&m.text) => 0,
+                Some(m) if relaxed.contains(&i) => relaxed_length(&m.text).unwrap_or(0),
+                Some(m) => match statement_length(line) {
+                    Some(length) => length,
+                    None => {
+                        self.report(Severity::Error, line, unknown_mnemonic(m));
+                        continue;
+                    }
+                },
+            };
+
+            self.addresses[i] = Some(current_address);
+            current_address = current_address.wrapping_add(length);
+        }
+    }
+
+    /// Second pass: assemble instructions
+    fn encode(&mut self, lines: &[ParsedLine], relaxed: &HashSet<usize>) {
+        let labels = std::mem::take(&mut self.labels);
+        let mut encoder = Encoder::new(&labels);
+        encoder.constants = self.defines.clone();
+        encoder.relocatable = self.object.is_some();
+
+        for (i, line) in lines.iter().enumerate() {
+            let Some(address) = self.addresses[i] else {
+                continue;
+            };
+            // Included files are listed whole, as render_listing has only
+            // the main file's text
+            let Some(mnemonic) = &line.mnemonic else {
+                if line.label.is_some() || line.file != 0 {
+                    self.list_empty(line, address);
+                }
+                continue;
+            };
+
+            if is_layout_directive(&mnemonic.text) {
+                if line.file != 0 {
+                    self.list_empty(line, address);
+                }
+                let moves = ["ORG", "PAGE"];
+                if !moves.iter().any(|d| mnemonic.text.eq_ignore_ascii_case(d)) {
+                    continue;
+                }
+                if (address as usize) < self.machine_code.len() {
+                    let issue = Issue::error(
+                        line.statement_span(),
+                        AssemblyError::InvalidOperand(format!(
+                            "ORG {:#06X} overlaps code already assembled up to {:#06X}",
+                            address,
+                            self.machine_code.len()
+                        )),
+                    );
+                    self.report(Severity::Error, line, issue);
+                } else {
+                    self.machine_code.resize(address as usize, 0);
+                }
+                continue;
+            }
+
+            let bytes = match encoder.encode(line, address, relaxed.contains(&i)) {
+                Ok(bytes) => bytes,
+                Err(issue) => {
+                    if encoder.off_page
+                        && self.options.relax_branches
+                        && !relaxed.contains(&i)
+                        && relaxed_length(&mnemonic.text).is_some()
+                    {
+                        self.off_page.push(i);
+                    }
+                    self.report(Severity::Error, line, issue);
+                    // Keep addresses of later lines in step with the first pass
+                    let length = statement_length(line).unwrap_or(0);
+                    vec![0; length as usize]
+                }
+            };
+
+            let fixups = std::mem::take(&mut encoder.fixups);
+            if let Some(object) = &mut self.object {
+                object.emit(i, address, &bytes, fixups, &labels);
+            }
+            for warning in std::mem::take(&mut encoder.warnings) {
+                self.report(Severity::Warning, line, warning);
+            }
+            for name in std::mem::take(&mut encoder.references) {
+                let uses = self.references.entry(name).or_default();
+                if uses.last() != Some(&(line.file, line.number)) {
+                    uses.push((line.file, line.number));
+                }
+            }
+            let listed = ListedLine {
+                file: line.file,
+                line: line.number,
+                text: line.text.clone(),
+                address,
+                bytes: bytes.clone(),
+                expansion: std::mem::take(&mut encoder.expansion),
+            };
+            let data = DataKind::from_mnemonic(&mnemonic.text).is_some();
+            self.units.extend(lint::line_units(
+                line,
+                address,
+                &bytes,
+                &listed.expansion,
+                data,
+            ));
+            let allowed = lint::allowed(line);
+            if !allowed.is_empty() {
+                self.allowed.insert((line.file, line.number), allowed);
+            }
+            self.map_line(line, &listed);
+            self.listed.push(listed);
+
+            // Format: address: opcodes | assembly
+            let opcodes = bytes
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let instruction_line = source_text(line);
+            let disasm = format!("{:04X}: {:<8} | {}", address, opcodes, instruction_line);
+            self.disassembly.push(disasm);
+            self.machine_code.extend_from_slice(&bytes);
+        }
+
+        self.labels = labels;
+    }
+
+    /// List a line that emits nothing
+    fn list_empty(&mut self, line: &ParsedLine, address: u16) {
+        self.listed.push(ListedLine {
+            file: line.file,
+            line: line.number,
+            text: line.text.clone(),
+            address,
+            bytes: Vec::new(),
+            expansion: Vec::new(),
+        });
+    }
+
+    /// Record where each instruction of a line came from
+    fn map_line(&mut self, line: &ParsedLine, listed: &ListedLine) {
+        let location = SourceLocation {
+            file: self.files[line.file].clone(),
+            line: line.number,
+            span: line.statement_span(),
+        };
+        if listed.expansion.is_empty() {
+            self.source_map
+                .push(listed.address, listed.bytes.len() as u16, location);
+        } else {
+            for expansion in &listed.expansion {
+                let length = expansion.bytes.len() as u16;
+                self.source_map
+                    .push(expansion.address, length, location.clone());
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Result<AssemblyOutput, AssemblyError> {
+        self.warn_unused();
+        if self.object.is_none() && !self.diagnostics.iter().any(Diagnostic::is_error) {
+            self.lint();
+        }
+        let object = self.object.take().map(|builder| {
+            let (object, errors) =
+                builder.finish(&self.options.file_name, &self.labels, self.files);
+            self.diagnostics.extend(errors);
+            object
+        });
+        // By file in include order, then by position
+        let files = self.files;
+        self.diagnostics.sort_by_key(|d| {
+            (
+                files.iter().position(|f| *f == d.file),
+                d.line,
+                d.span.start,
+            )
+        });
+        if self.diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(AssemblyError::Diagnostics(self.diagnostics));
+        }
+
+        let mut symbols: Vec<Symbol> = self
+            .labels
+            .iter()
+            .filter(|(name, _)| !is_anonymous(name))
+            .map(|(name, &value)| Symbol {
+                name: name.clone(),
+                value,
+                file: self.files[self.definitions[name].0].clone(),
+                defined: self.definitions[name].1,
+                references: (self.references.remove(name).unwrap_or_default())
+                    .into_iter()
+                    .map(|(file, line)| FileLine {
+                        file: self.files[file].clone(),
+                        line,
+                    })
+                    .collect(),
+            })
+            .collect();
+        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        self.source_map.sort();
+
+        Ok(AssemblyOutput {
+            machine_code: self.machine_code,
+            disassembly: self.disassembly,
+            diagnostics: self.diagnostics,
+            files: self.files.to_vec(),
+            lines: self.listed,
+            symbols,
+            source_map: self.source_map,
+            object,
+        })
+    }
+}
+
+impl Pass<'_> {
+    /// Handle SECTION, EXTERN and PUBLIC, which only relocatable objects
+    /// accept
+    fn object_directive(
+        &mut self,
+        line: &ParsedLine,
+        directive: &str,
+        current_address: &mut u16,
+    ) -> Result<(), Issue> {
+        let span = line.statement_span();
+        let Some(object) = &mut self.object else {
+            return Err(Issue::error(
+                span,
+                AssemblyError::InvalidOperand(format!(
+                    "{} is only allowed in a relocatable object",
+                    directive
+                )),
+            ));
+        };
+        if line.operands.is_empty() {
+            return Err(Issue::error(
+                span,
+                AssemblyError::InvalidOperand(format!("{} requires a name", directive)),
+            ));
+        }
+
+        for operand in &line.operands {
+            let name = operand.text.to_uppercase();
+            match directive {
+                "SECTION" => {
+                    *current_address = object.switch(&name, *current_address);
+                    break;
+                }
+                "EXTERN" => {
+                    if let Some(&(_, first, _)) = self.definitions.get(&name) {
+                        let error = AssemblyError::DuplicateLabel { name, line: first };
+                        return Err(Issue::error(operand.span, error));
+                    }
+                    self.definitions
+                        .insert(name.clone(), (line.file, line.number, operand.span));
+                    self.labels.insert(name.clone(), 0);
+                    object.imports.push(name);
+                }
+                _ => {
+                    let references = self.references.entry(name.clone()).or_default();
+                    references.push((line.file, line.number));
+                    object
+                        .exports
+                        .push((name, line.file, line.number, operand.span));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Directives that only affect layout and emit nothing
+fn is_layout_directive(mnemonic: &str) -> bool {
+    ["ORG", "PAGE", "SECTION", "EXTERN", "PUBLIC"]
+        .iter()
+        .any(|d| mnemonic.eq_ignore_ascii_case(d))
+}
+
+/// The address an ORG line moves to: a number or a caller define
+fn org_address(line: &ParsedLine, defines: &HashMap<String, u16>) -> Result<u16, Issue> {
+    let operand = line.operands.first().ok_or_else(|| {
+        Issue::error(
+            line.statement_span(),
+            AssemblyError::InvalidOperand("ORG requires an address".to_string()),
+        )
+    })?;
+    if let Some(&address) = defines.get(&operand.text.to_uppercase()) {
+        return Ok(address);
+    }
+    parse_number(&operand.text).map_err(|e| Issue::error(operand.span, e))
+}
+
+/// The instruction text (mnemonic and operands) of a parsed line
+fn source_text(line: &ParsedLine) -> String {
+    let span = line.statement_span();
+    line.text
+        .chars()
+        .skip(span.start)
+        .take(span.end - span.start)
+        .collect()
+}
@@ -0,0 +1,70 @@
+use super::Pass;
+use crate::assembler::diagnostic::Span;
+use crate::assembler::lint;
+use crate::assembler::scope::is_anonymous;
+use crate::assembler::{Diagnostic, SCRT_FILE, STDLIB_PREFIX};
+use std::collections::HashSet;
+
+impl Pass<'_> {
+    /// Is `file` the SCRT runtime or part of the standard library?
+    pub(super) fn is_library(&self, file: usize) -> bool {
+        let name = &self.files[file];
+        name == SCRT_FILE || name.starts_with(STDLIB_PREFIX)
+    }
+
+    /// Warn about common pitfalls, except where a lint is allowed
+    pub(super) fn lint(&mut self) {
+        self.units.sort_by_key(|u| u.address);
+        let labelled: HashSet<u16> = self.labels.values().copied().collect();
+        let findings = lint::check(&self.units, &labelled, |file| !self.is_library(file));
+
+        for (index, lint, message) in findings {
+            let unit = &self.units[index];
+            let allowed = self
+                .allowed
+                .get(&(unit.file, unit.line))
+                .is_some_and(|a| a.contains(&lint));
+            if allowed || self.options.allowed_lints.contains(&lint) {
+                continue;
+            }
+            let file = &self.files[unit.file];
+            let warning = Diagnostic::warning(file, unit.line, unit.span, message);
+            self.diagnostics.push(warning.with_code(lint.id()));
+        }
+    }
+
+    /// Warn about labels nothing refers to
+    ///
+    /// Labels in the SCRT runtime and the standard library are left out, as
+    /// is a label on the program's first instruction (its entry point,
+    /// which SCRT start-up code may precede).
+    pub(super) fn warn_unused(&mut self) {
+        let entry = self
+            .source_map
+            .ranges()
+            .iter()
+            .filter(|r| r.location.file != SCRT_FILE)
+            .map(|r| r.address)
+            .min();
+        let mut unused: Vec<(&String, &(usize, usize, Span))> = self
+            .definitions
+            .iter()
+            .filter(|(name, (file, ..))| {
+                !self.references.contains_key(*name)
+                    && !is_anonymous(name)
+                    && !self.is_library(*file)
+                    && Some(self.labels[*name]) != entry
+            })
+            .collect();
+        unused.sort_by_key(|&(_, &(file, line, _))| (file, line));
+
+        let warnings: Vec<Diagnostic> = unused
+            .into_iter()
+            .map(|(name, &(file, line, span))| {
+                let message = format!("Label {} is never used", name);
+                Diagnostic::warning(&self.files[file], line, span, message)
+            })
+            .collect();
+        self.diagnostics.extend(warnings);
+    }
+}
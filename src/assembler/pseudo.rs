@@ -0,0 +1,247 @@
+//! 16-bit pseudo-instructions
+//!
+//! Shorthand for register-pair idioms the 1802 needs several instructions
+//! for. Each expands to real opcodes, listed under the source line along
+//! with its total byte and machine-cycle cost. Every expansion moves bytes
+//! through D, so D is overwritten. `CALL` and `RETN` are different: their
+//! SCRT routines keep D intact, and R6 holds the caller's link, which is
+//! also the pointer to any inline arguments (see [`super::scrt`]).
+//!
+//! | Pseudo          | Expansion                                  | Bytes | Cycles |
+//! |-----------------|--------------------------------------------|-------|--------|
+//! | `LOAD Rn, expr` | `LDI HIGH / PHI Rn / LDI LOW / PLO Rn`     | 6     | 8      |
+//! | `MOV Rd, Rs`    | `GHI Rs / PHI Rd / GLO Rs / PLO Rd`        | 4     | 8      |
+//! | `ADD16 Rd, Rs`  | `GLO Rs / STXD / IRX / GLO Rd / ADD / PLO Rd`, then the same with GHI/ADC/PHI | 12 | 24 |
+//! | `PUSH Rn`       | `GHI Rn / STXD / GLO Rn / STXD`            | 4     | 8      |
+//! | `POP Rn`        | `IRX / LDXA / PLO Rn / LDX / PHI Rn`       | 5     | 10     |
+//!
+//! `ADD16 Rd, Rs` adds Rs to Rd with carry between the bytes and leaves the
+//! final carry in DF. Each byte of Rs is written with `STXD / IRX`, so
+//! R(X) must point at a free byte, as R2 does under SCRT. `PUSH` and `POP`
+//! use the SCRT stack layout: the high byte is pushed first and R(X) is
+//! left pointing at the next free byte.
+
+use super::AssemblyError;
+use super::encoder::{Encoder, Issue, parse_register};
+use super::listing::Expansion;
+use super::object::Fixup;
+use super::parser::{ParsedLine, Token};
+use crate::linker::RelocationKind;
+
+/// Each pseudo-instruction with its operand count and expanded length
+const PSEUDOS: &[(&str, usize, u16)] = &[
+    ("LOAD", 2, 6),
+    ("MOV", 2, 4),
+    ("ADD16", 2, 12),
+    ("PUSH", 1, 4),
+    ("POP", 1, 5),
+];
+
+/// Length of a pseudo-instruction's expansion
+pub(crate) fn pseudo_length(mnemonic: &str) -> Option<u16> {
+    let mnemonic = mnemonic.to_uppercase();
+    PSEUDOS
+        .iter()
+        .find(|(name, ..)| *name == mnemonic)
+        .map(|&(_, _, length)| length)
+}
+
+/// Is `mnemonic` one of the pseudo-instructions above?
+pub fn is_pseudo(mnemonic: &str) -> bool {
+    pseudo_length(mnemonic).is_some()
+}
+
+impl Encoder<'_> {
+    /// Assemble `line` if `mnemonic` names a pseudo-instruction
+    pub(crate) fn encode_pseudo(
+        &mut self,
+        mnemonic: &str,
+        line: &ParsedLine,
+        address: u16,
+    ) -> Option<Result<Vec<u8>, Issue>> {
+        let &(_, count, _) = PSEUDOS.iter().find(|(name, ..)| *name == mnemonic)?;
+        Some(self.assemble_pseudo(mnemonic, count, line, address))
+    }
+
+    fn assemble_pseudo(
+        &mut self,
+        mnemonic: &str,
+        count: usize,
+        line: &ParsedLine,
+        address: u16,
+    ) -> Result<Vec<u8>, Issue> {
+        let ops = &line.operands;
+        if ops.len() < count {
+            let span = line.statement_span();
+            let needed = if count == 1 {
+                "an operand".to_string()
+            } else {
+                format!("{} operands", count)
+            };
+            return Err(Issue::error(
+                span,
+                AssemblyError::InvalidOperand(format!("{} requires {}", mnemonic, needed)),
+            ));
+        }
+        for extra in ops.iter().skip(count) {
+            self.warnings.push(Issue::warning(
+                extra.span,
+                format!("Extra operand ignored: {}", extra.text),
+            ));
+        }
+
+        let steps = match mnemonic {
+            "LOAD" => {
+                let n = register(&ops[0])?;
+                let before = self.fixups.len();
+                let [hi, lo] = self
+                    .resolve_field(&ops[1], 1, RelocationKind::High)?
+                    .to_be_bytes();
+                if let Some(high) = self.fixups.get(before).cloned() {
+                    self.fixups.push(Fixup {
+                        offset: 4,
+                        kind: RelocationKind::Low,
+                        ..high
+                    });
+                }
+                let expr = &ops[1].text;
+                vec![
+                    (vec![0xF8, hi], format!("LDI HIGH({})", expr)),
+                    (vec![0xB0 | n], format!("PHI R{:X}", n)),
+                    (vec![0xF8, lo], format!("LDI LOW({})", expr)),
+                    (vec![0xA0 | n], format!("PLO R{:X}", n)),
+                ]
+            }
+            "MOV" => {
+                let (d, s) = (register(&ops[0])?, register(&ops[1])?);
+                vec![
+                    (vec![0x90 | s], format!("GHI R{:X}", s)),
+                    (vec![0xB0 | d], format!("PHI R{:X}", d)),
+                    (vec![0x80 | s], format!("GLO R{:X}", s)),
+                    (vec![0xA0 | d], format!("PLO R{:X}", d)),
+                ]
+            }
+            "ADD16" => {
+                let (d, s) = (register(&ops[0])?, register(&ops[1])?);
+                vec![
+                    (vec![0x80 | s], format!("GLO R{:X}", s)),
+                    (vec![0x73], "STXD".to_string()),
+                    (vec![0x60], "IRX".to_string()),
+                    (vec![0x80 | d], format!("GLO R{:X}", d)),
+                    (vec![0xF4], "ADD".to_string()),
+                    (vec![0xA0 | d], format!("PLO R{:X}", d)),
+                    (vec![0x90 | s], format!("GHI R{:X}", s)),
+                    (vec![0x73], "STXD".to_string()),
+                    (vec![0x60], "IRX".to_string()),
+                    (vec![0x90 | d], format!("GHI R{:X}", d)),
+                    (vec![0x74], "ADC".to_string()),
+                    (vec![0xB0 | d], format!("PHI R{:X}", d)),
+                ]
+            }
+            "PUSH" => {
+                let n = register(&ops[0])?;
+                vec![
+                    (vec![0x90 | n], format!("GHI R{:X}", n)),
+                    (vec![0x73], "STXD".to_string()),
+                    (vec![0x80 | n], format!("GLO R{:X}", n)),
+                    (vec![0x73], "STXD".to_string()),
+                ]
+            }
+            "POP" => {
+                let n = register(&ops[0])?;
+                vec![
+                    (vec![0x60], "IRX".to_string()),
+                    (vec![0x72], "LDXA".to_string()),
+                    (vec![0xA0 | n], format!("PLO R{:X}", n)),
+                    (vec![0xF0], "LDX".to_string()),
+                    (vec![0xB0 | n], format!("PHI R{:X}", n)),
+                ]
+            }
+            _ => unreachable!("not a pseudo-instruction: {}", mnemonic),
+        };
+
+        let mut bytes = Vec::new();
+        for (code, text) in steps {
+            let at = address.wrapping_add(bytes.len() as u16);
+            bytes.extend_from_slice(&code);
+            self.expansion.push(Expansion::instruction(at, code, text));
+        }
+        Ok(bytes)
+    }
+}
+
+fn register(operand: &Token) -> Result<u8, Issue> {
+    parse_register(&operand.text).map_err(|e| Issue::error(operand.span, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::{assemble, render_listing};
+    use crate::cpu::Cpu;
+
+    fn run(source: &str) -> Cpu {
+        let output = assemble(source).unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&output.machine_code, 0).unwrap();
+        while !cpu.halted && cpu.instructions_executed < 1_000 {
+            cpu.step().unwrap();
+        }
+        cpu
+    }
+
+    #[test]
+    fn test_lengths_match_expansions() {
+        for &(name, count, length) in PSEUDOS {
+            // "8" reads as both a register and a value
+            let operands = ["7", "8"][..count].join(", ");
+            let output = assemble(&format!("{} {}", name, operands)).unwrap();
+            assert_eq!(output.machine_code.len() as u16, length, "{}", name);
+            assert_eq!(pseudo_length(name), Some(length));
+        }
+    }
+
+    #[test]
+    fn test_pseudo_instructions_execute() {
+        let source = r#"
+        LOAD R2, 0x00FF     ; stack
+        SEX R2
+        LOAD R7, 0x12F0
+        LOAD R8, DATA
+        MOV R9, R8
+        ADD16 R7, R9
+        PUSH R7
+        LOAD R7, 0
+        POP RA
+        IDL
+DATA:   DB 0
+"#;
+        let cpu = run(source);
+
+        assert_eq!(cpu.get_register(9).unwrap(), 0x0033);
+        assert_eq!(cpu.get_register(7).unwrap(), 0);
+        assert_eq!(cpu.get_register(10).unwrap(), 0x12F0 + 0x0033);
+        assert_eq!(cpu.get_register(2).unwrap(), 0x00FF);
+    }
+
+    #[test]
+    fn test_listing_shows_expansion_and_cost() {
+        let source = "LOAD R7, 0x1234";
+        let output = assemble(source).unwrap();
+        let listing = render_listing(source, &output);
+
+        assert!(listing.contains("      0000  F8 12        +   LDI HIGH(0x1234)\n"));
+        assert!(listing.contains("      0005  A7           +   PLO R7\n"));
+        assert!(listing.contains("; 6 bytes, 8 cycles\n"));
+    }
+
+    #[test]
+    fn test_missing_operand() {
+        let err = assemble("MOV R1").unwrap_err();
+        assert!(
+            err.diagnostics()[0]
+                .message
+                .contains("MOV requires 2 operands")
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! Label scopes
+//!
+//! Scoped labels are given unique names before layout, and references to
+//! them are rewritten to match, so the rest of the assembler only ever sees
+//! global labels:
+//!
+//! - `.name` is local to the closest global label before it, so `.loop`
+//!   after `MAIN:` becomes `MAIN.LOOP`.
+//! - `PROC name` ... `ENDP` defines `name` and puts every label inside it in
+//!   its own scope (`NAME.label`). A name used inside a procedure refers to
+//!   the procedure's own label if there is one. Procedures may nest.
+//! - `-:` and `+:` are anonymous labels. An operand of `-` refers to the
+//!   closest `-:` at or before the line, `--` to the one before that; `+`
+//!   and `++` count forward through the `+:` labels after the line.
+
+use super::AssemblyError;
+use super::diagnostic::{Diagnostic, Span};
+use super::parser::{ParsedLine, Token};
+use std::collections::HashSet;
+
+/// Prefix of the names given to anonymous labels
+const ANONYMOUS: &str = "@";
+
+/// Is this a generated name for an anonymous label?
+pub(super) fn is_anonymous(name: &str) -> bool {
+    name.starts_with(ANONYMOUS)
+}
+
+/// Scopes in effect on one line
+#[derive(Debug, Clone, Default)]
+struct Context {
+    /// Qualified names of the enclosing procedures, innermost last
+    procs: Vec<String>,
+    /// Label that `.name` labels are local to
+    local: Option<String>,
+}
+
+impl Context {
+    fn prefix(&self) -> String {
+        self.procs
+            .last()
+            .map_or(String::new(), |p| format!("{}.", p))
+    }
+}
+
+/// Give every scoped label its unique name and rewrite references to match
+///
+/// PROC and ENDP lines lose their mnemonic; the procedure name becomes the
+/// line's label.
+pub(super) fn resolve_scopes(
+    lines: &mut [ParsedLine],
+    files: &[String],
+) -> Result<(), AssemblyError> {
+    let mut diagnostics = Vec::new();
+    let mut error = |line: &ParsedLine, span: Span, error: AssemblyError| {
+        diagnostics.push(Diagnostic::error(
+            &files[line.file],
+            line.number,
+            span,
+            error.to_string(),
+        ));
+    };
+
+    // Definitions: qualify labels and record the scopes of every line
+    let mut context = Context::default();
+    // Local scope to restore when each open procedure ends, and the line
+    // that opened it
+    let mut outer_locals: Vec<(Option<String>, usize)> = Vec::new();
+    let mut contexts = Vec::with_capacity(lines.len());
+    let mut defined = HashSet::new();
+    let mut backward = Vec::new();
+    let mut forward = Vec::new();
+
+    for (i, line) in lines.iter_mut().enumerate() {
+        let directive = line.mnemonic.as_ref().map(|m| m.text.to_uppercase());
+        match directive.as_deref() {
+            Some("PROC") => {
+                let name = line.operands.first().cloned().or_else(|| line.label.take());
+                let Some(name) = name.filter(|n| !n.text.is_empty()) else {
+                    let span = line.statement_span();
+                    error(
+                        line,
+                        span,
+                        AssemblyError::InvalidOperand("PROC requires a name".to_string()),
+                    );
+                    contexts.push(context.clone());
+                    continue;
+                };
+                let qualified = format!("{}{}", context.prefix(), name.text.to_uppercase());
+                defined.insert(qualified.clone());
+                line.label = Some(Token {
+                    text: qualified.clone(),
+                    span: name.span,
+                });
+                line.mnemonic = None;
+                line.operands.clear();
+
+                contexts.push(context.clone());
+                outer_locals.push((context.local.take(), i));
+                context.procs.push(qualified.clone());
+                context.local = Some(qualified);
+                continue;
+            }
+            Some("ENDP") => {
+                let span = line.statement_span();
+                line.mnemonic = None;
+                line.operands.clear();
+                match outer_locals.pop() {
+                    Some((local, _)) => {
+                        context.procs.pop();
+                        context.local = local;
+                    }
+                    None => error(
+                        line,
+                        span,
+                        AssemblyError::InvalidOperand("ENDP without matching PROC".to_string()),
+                    ),
+                }
+            }
+            _ => {}
+        }
+
+        let mut orphan = None;
+        if let Some(label) = &mut line.label
+            && !label.text.contains(char::is_whitespace)
+        {
+            let name = label.text.to_uppercase();
+            if name == "-" || name == "+" {
+                label.text = format!("{}{}", ANONYMOUS, i);
+                if name == "-" {
+                    backward.push(i);
+                } else {
+                    forward.push(i);
+                }
+            } else if let Some(local) = name.strip_prefix('.') {
+                match &context.local {
+                    Some(base) => label.text = format!("{}.{}", base, local),
+                    None => orphan = Some((label.span, name.clone())),
+                }
+            } else {
+                label.text = format!("{}{}", context.prefix(), name);
+                context.local = Some(label.text.clone());
+            }
+            defined.insert(label.text.to_uppercase());
+        }
+        if let Some((span, name)) = orphan {
+            let message = format!("{} has no global label before it", name);
+            error(line, span, AssemblyError::InvalidLabel(message));
+        }
+        contexts.push(context.clone());
+    }
+
+    if let Some(&(_, index)) = outer_locals.last() {
+        let line = &lines[index];
+        error(
+            line,
+            line.label.as_ref().map_or(Span::default(), |l| l.span),
+            AssemblyError::InvalidOperand("PROC without ENDP".to_string()),
+        );
+    }
+
+    // References: rewrite operands in the scope of their line
+    for (i, line) in lines.iter_mut().enumerate() {
+        let is_org = line
+            .mnemonic
+            .as_ref()
+            .is_some_and(|m| m.text.eq_ignore_ascii_case("ORG"));
+        if is_org {
+            continue;
+        }
+        for operand in &mut line.operands {
+            let text = operand.text.trim();
+            if !text.is_empty() && text.chars().all(|c| c == '-') {
+                let target = backward
+                    .iter()
+                    .rev()
+                    .filter(|&&l| l <= i)
+                    .nth(text.len() - 1);
+                operand.text = anonymous_target(target, text);
+            } else if !text.is_empty() && text.chars().all(|c| c == '+') {
+                let target = forward.iter().filter(|&&l| l > i).nth(text.len() - 1);
+                operand.text = anonymous_target(target, text);
+            } else if !text.starts_with(['"', '\'']) {
+                operand.text = qualify(&operand.text, &contexts[i], &defined);
+            }
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(AssemblyError::Diagnostics(diagnostics))
+    }
+}
+
+/// Generated name of an anonymous label, or the operand unchanged (and so
+/// reported as undefined) if there is no such label
+fn anonymous_target(target: Option<&usize>, text: &str) -> String {
+    target.map_or(text.to_string(), |l| format!("{}{}", ANONYMOUS, l))
+}
+
+/// Rewrite the label names in an operand for the scopes in `context`
+fn qualify(text: &str, context: &Context, defined: &HashSet<String>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+        if c.is_ascii_alphabetic() || c == '_' || c == '.' {
+            let start = i;
+            while i < chars.len() && is_name(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            out.push_str(&resolve_name(&word, context, defined));
+        } else if c.is_ascii_digit() || c == '$' {
+            // Numbers pass through whole, so the hex digits are not names
+            while i < chars.len() && (is_name(chars[i]) || chars[i] == '$') {
+                out.push(chars[i]);
+                i += 1;
+            }
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// The label a name written in `context` refers to
+fn resolve_name(word: &str, context: &Context, defined: &HashSet<String>) -> String {
+    let name = word.to_uppercase();
+    if let Some(local) = name.strip_prefix('.') {
+        return match &context.local {
+            Some(base) => format!("{}.{}", base, local),
+            None => word.to_string(),
+        };
+    }
+    // Innermost procedure first
+    for proc in context.procs.iter().rev() {
+        let scoped = format!("{}.{}", proc, name);
+        if defined.contains(&scoped) {
+            return scoped;
+        }
+    }
+    word.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+
+    #[test]
+    fn test_local_labels() {
+        let source = r#"
+FIRST:  LDI 0x03
+.loop:  SMI 0x01
+        BNZ .loop
+SECOND: LDI 0x02
+.loop:  SMI 0x01
+        BNZ .loop
+        BR FIRST.LOOP
+"#;
+        let output = assemble(source).unwrap();
+
+        assert_eq!(output.machine_code[4..6], [0x3A, 0x02]);
+        assert_eq!(output.machine_code[10..12], [0x3A, 0x08]);
+        assert_eq!(output.machine_code[12..14], [0x30, 0x02]);
+        let names: Vec<&str> = output.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert!(names.contains(&"SECOND.LOOP"));
+    }
+
+    #[test]
+    fn test_anonymous_labels() {
+        let source = "-:  DEC R1\n    GLO R1\n    BZ ++\n    BR -\n+:  BR +\n-:  BR --\n+:  IDL";
+        let output = assemble(source).unwrap();
+
+        assert_eq!(
+            output.machine_code,
+            vec![
+                0x21, 0x81, 0x32, 0x0A, 0x30, 0x00, 0x30, 0x0A, 0x30, 0x00, 0x00
+            ]
+        );
+        assert!(output.symbols.iter().all(|s| !s.name.starts_with('@')));
+    }
+
+    #[test]
+    fn test_proc_scopes() {
+        let source = r#"
+        CALL FIRST
+        CALL SECOND
+        IDL
+        PROC FIRST
+DONE:   RETN
+        ENDP
+        PROC SECOND
+        BR DONE
+DONE:   RETN
+        ENDP
+"#;
+        let output = assemble(source).unwrap();
+        let value = |name: &str| {
+            output
+                .symbols
+                .iter()
+                .find(|s| s.name == name)
+                .unwrap()
+                .value
+        };
+
+        assert_ne!(value("FIRST.DONE"), value("SECOND.DONE"));
+        let branch = output.source_map.addresses("input.asm", 9)[0];
+        assert_eq!(
+            output.machine_code[branch as usize + 1],
+            value("SECOND.DONE") as u8
+        );
+    }
+
+    #[test]
+    fn test_duplicate_label() {
+        let err = assemble("LOOP: INC R1\nLOOP: BR LOOP").unwrap_err();
+        let diagnostic = &err.diagnostics()[0];
+
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(
+            diagnostic.message,
+            "Duplicate label: LOOP (first defined on line 1)"
+        );
+    }
+
+    #[test]
+    fn test_unused_label_warnings() {
+        let source = "START: CALL SUB\nIDL\nSPARE: NOP\nSUB: RETN\nINCLUDE \"std/bcd.asm\"";
+        let output = assemble(source).unwrap();
+        let warnings: Vec<&str> = output
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+
+        assert_eq!(warnings, vec!["Label SPARE is never used"]);
+        assert_eq!(output.diagnostics[0].line, 3);
+    }
+
+    #[test]
+    fn test_unbalanced_proc() {
+        let err = assemble("PROC MAIN\nIDL").unwrap_err();
+        assert_eq!(
+            err.diagnostics()[0].message,
+            "Invalid operand: PROC without ENDP"
+        );
+
+        let err = assemble("IDL\nENDP").unwrap_err();
+        assert_eq!(err.diagnostics()[0].line, 2);
+    }
+}
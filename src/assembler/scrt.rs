@@ -0,0 +1,161 @@
+//! Standard Call and Return Technique (SCRT)
+//!
+//! The usual 1802 subroutine convention: R3 is the program counter, R2 the
+//! stack pointer (used with X = 2), R4 and R5 point at the call and return
+//! routines and R6 holds the return address. `CALL target` assembles to
+//! `SEP R4` followed by `DW target`; `RETN` assembles to `SEP R5`. RE.0 is
+//! used as scratch to preserve D across both routines.
+
+use super::dialect::Dialect;
+use super::encoder::{Encoder, Issue};
+use super::listing::Expansion;
+use super::parser::{ParsedLine, Token, parse_file};
+use crate::linker::RelocationKind;
+
+/// Name the runtime's lines are reported under
+pub const SCRT_FILE: &str = "scrt.asm";
+
+/// Label the start-up code hands control to
+const MAIN_LABEL: &str = "SCRT_MAIN";
+
+/// Source of the SCRT runtime with R2 starting at `stack`
+///
+/// Everything up to `SCRT_MAIN:` is start-up code placed before the first
+/// statement of the program; the call and return routines go at the end.
+pub fn runtime_source(stack: u16) -> String {
+    format!(
+        "\
+; SCRT start-up: R2 = stack, R4 = CALL, R5 = RETURN, then run as P = 3
+SCRT_INIT:
+        LDI HIGH(0x{stack:04X})
+        PHI R2
+        LDI LOW(0x{stack:04X})
+        PLO R2
+        LDI HIGH(SCRT_CALL)
+        PHI R4
+        LDI LOW(SCRT_CALL)
+        PLO R4
+        LDI HIGH(SCRT_RETURN)
+        PHI R5
+        LDI LOW(SCRT_RETURN)
+        PLO R5
+        LDI HIGH({MAIN_LABEL})
+        PHI R3
+        LDI LOW({MAIN_LABEL})
+        PLO R3
+        SEX R2
+        SEP R3
+{MAIN_LABEL}:
+
+; CALL (SEP R4 / DW target): push R6, R6 = return address, R3 = target
+SCRT_CALL_EXIT:
+        SEP R3
+SCRT_CALL:
+        PLO RE
+        GHI R6
+        STXD
+        GLO R6
+        STXD
+        GHI R3
+        PHI R6
+        GLO R3
+        PLO R6
+        LDA R6
+        PHI R3
+        LDA R6
+        PLO R3
+        GLO RE
+        LBR SCRT_CALL_EXIT
+
+; RETURN (SEP R5): R3 = R6, pop R6
+SCRT_RETURN_EXIT:
+        SEP R3
+SCRT_RETURN:
+        PLO RE
+        GHI R6
+        PHI R3
+        GLO R6
+        PLO R3
+        SEX R2
+        IRX
+        LDXA
+        PLO R6
+        LDX
+        PHI R6
+        GLO RE
+        LBR SCRT_RETURN_EXIT
+"
+    )
+}
+
+/// Does the program use CALL or RETN without supplying its own runtime?
+pub(super) fn needs_runtime(lines: &[ParsedLine]) -> bool {
+    let uses = lines.iter().any(|line| {
+        line.mnemonic.as_ref().is_some_and(|m| {
+            m.text.eq_ignore_ascii_case("CALL") || m.text.eq_ignore_ascii_case("RETN")
+        })
+    });
+    let defines = lines.iter().any(|line| {
+        line.label
+            .as_ref()
+            .is_some_and(|l| l.text.eq_ignore_ascii_case("SCRT_CALL"))
+    });
+    uses && !defines
+}
+
+/// Splice the runtime into the program as file `file`
+///
+/// The start-up code goes before the first label or statement other than
+/// ORG, so a program that starts with ORG gets its runtime there.
+pub(super) fn include_runtime(lines: &mut Vec<ParsedLine>, file: usize, stack: u16) {
+    let runtime = parse_file(file, &runtime_source(stack), Dialect::Native);
+    let split = runtime
+        .iter()
+        .position(|l| l.label.as_ref().is_some_and(|t| t.text == MAIN_LABEL))
+        .map_or(0, |i| i + 1);
+    let (startup, routines) = runtime.split_at(split);
+
+    let first = lines
+        .iter()
+        .position(|line| {
+            line.label.is_some()
+                || line
+                    .mnemonic
+                    .as_ref()
+                    .is_some_and(|m| !m.text.eq_ignore_ascii_case("ORG"))
+        })
+        .unwrap_or(lines.len());
+    lines.splice(first..first, startup.iter().cloned());
+    lines.extend(routines.iter().cloned());
+}
+
+impl Encoder<'_> {
+    /// CALL target: SEP R4 followed by the target address
+    pub(crate) fn assemble_call(
+        &mut self,
+        operand: &Token,
+        address: u16,
+    ) -> Result<Vec<u8>, Issue> {
+        let target = self.resolve_field(operand, 1, RelocationKind::Word)?;
+        let word = target.to_be_bytes().to_vec();
+        self.expansion = vec![
+            Expansion::instruction(address, vec![0xD4], "SEP R4".to_string()),
+            Expansion::data(
+                address.wrapping_add(1),
+                word.clone(),
+                format!("DW {}", operand.text),
+            ),
+        ];
+        Ok([vec![0xD4], word].concat())
+    }
+
+    /// RETN: SEP R5
+    pub(crate) fn assemble_return(&mut self, address: u16) -> Vec<u8> {
+        self.expansion = vec![Expansion::instruction(
+            address,
+            vec![0xD5],
+            "SEP R5".to_string(),
+        )];
+        vec![0xD5]
+    }
+}
@@ -0,0 +1,130 @@
+use super::diagnostic::Span;
+use serde::{Deserialize, Serialize};
+
+/// A place in the source: file, 1-based line and column span
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub span: Span,
+}
+
+/// A run of machine code produced by one source statement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappedRange {
+    pub address: u16,
+    pub length: u16,
+    pub location: SourceLocation,
+}
+
+impl MappedRange {
+    fn contains(&self, address: u16) -> bool {
+        address >= self.address && (address as u32) < self.address as u32 + self.length as u32
+    }
+}
+
+/// Two-way mapping between machine-code addresses and source locations
+///
+/// Each instruction (or each instruction of an expansion) and each data
+/// directive gets its own range, so any byte of the program can be traced
+/// back to the statement that emitted it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Ranges sorted by address
+    ranges: Vec<MappedRange>,
+}
+
+impl SourceMap {
+    pub(crate) fn push(&mut self, address: u16, length: u16, location: SourceLocation) {
+        if length > 0 {
+            self.ranges.push(MappedRange {
+                address,
+                length,
+                location,
+            });
+        }
+    }
+
+    pub(crate) fn sort(&mut self) {
+        self.ranges.sort_by_key(|r| r.address);
+    }
+
+    pub fn ranges(&self) -> &[MappedRange] {
+        &self.ranges
+    }
+
+    /// The range covering `address`, if any
+    pub fn range(&self, address: u16) -> Option<&MappedRange> {
+        let index = self.ranges.partition_point(|r| r.address <= address);
+        self.ranges[..index].last().filter(|r| r.contains(address))
+    }
+
+    /// Source location of the statement that emitted the byte at `address`
+    pub fn location(&self, address: u16) -> Option<&SourceLocation> {
+        self.range(address).map(|r| &r.location)
+    }
+
+    /// Start address of every range emitted by `line` of `file`
+    pub fn addresses(&self, file: &str, line: usize) -> Vec<u16> {
+        self.ranges
+            .iter()
+            .filter(|r| r.location.line == line && r.location.file == file)
+            .map(|r| r.address)
+            .collect()
+    }
+
+    /// Address a breakpoint on `line` should use: the first address of
+    /// that line, or of the next line in the file that emits code
+    pub fn breakpoint_address(&self, file: &str, line: usize) -> Option<u16> {
+        self.ranges
+            .iter()
+            .filter(|r| r.location.file == file && r.location.line >= line)
+            .min_by_key(|r| (r.location.line, r.address))
+            .map(|r| r.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::{AssembleOptions, assemble, assemble_with};
+
+    #[test]
+    fn test_address_to_source() {
+        let source = "START:\n  LDI 0x05\n  DB 1, 2, 3\n  IDL";
+        let map = assemble(source).unwrap().source_map;
+
+        let location = map.location(0x0001).unwrap();
+        assert_eq!(location.line, 2);
+        assert_eq!(location.file, "input.asm");
+        assert_eq!((location.span.start, location.span.end), (2, 10));
+
+        assert_eq!(map.location(0x0004).unwrap().line, 3);
+        assert_eq!(map.location(0x0005).unwrap().line, 4);
+        assert!(map.location(0x0006).is_none());
+    }
+
+    #[test]
+    fn test_source_to_address() {
+        let source = "; comment\nLDI 1\nORG 0x10\n\nLOOP: BR LOOP";
+        let map = assemble(source).unwrap().source_map;
+
+        assert_eq!(map.addresses("input.asm", 2), vec![0x0000]);
+        assert_eq!(map.addresses("input.asm", 3), Vec::<u16>::new());
+        assert_eq!(map.breakpoint_address("input.asm", 1), Some(0x0000));
+        assert_eq!(map.breakpoint_address("input.asm", 3), Some(0x0010));
+        assert_eq!(map.breakpoint_address("other.asm", 1), None);
+    }
+
+    #[test]
+    fn test_expansions_map_each_instruction() {
+        let options = AssembleOptions {
+            relax_branches: true,
+            ..Default::default()
+        };
+        let source = "B1 FAR\nORG 0x100\nFAR: IDL";
+        let map = assemble_with(source, &options).unwrap().source_map;
+
+        assert_eq!(map.addresses("input.asm", 1), vec![0x0000, 0x0002]);
+        assert_eq!(map.range(0x0003).unwrap().address, 0x0002);
+    }
+}
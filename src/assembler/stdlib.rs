@@ -0,0 +1,103 @@
+//! Bundled standard library of 1802 routines
+//!
+//! Each file is included by name, e.g. `INCLUDE "std/mul8.asm"`, whatever
+//! [`SourceProvider`](super::SourceProvider) is in use. The routines are
+//! SCRT subroutines: call them with `CALL` and place the `INCLUDE` where
+//! execution cannot fall into it, such as after the program's `IDL`.
+
+/// Prefix that selects the bundled library in an include name
+pub const STDLIB_PREFIX: &str = "std/";
+
+/// File name and source of every library file
+const FILES: &[(&str, &str)] = &[
+    ("mul8.asm", include_str!("stdlib/mul8.asm")),
+    ("div8.asm", include_str!("stdlib/div8.asm")),
+    ("bcd.asm", include_str!("stdlib/bcd.asm")),
+    ("print.asm", include_str!("stdlib/print.asm")),
+];
+
+/// Source of the library file `name` (with its `std/` prefix)
+pub fn stdlib_source(name: &str) -> Option<&'static str> {
+    let name = name.strip_prefix(STDLIB_PREFIX)?;
+    FILES
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|&(_, source)| source)
+}
+
+/// Include names of every library file
+pub fn stdlib_names() -> impl Iterator<Item = String> {
+    FILES
+        .iter()
+        .map(|(file, _)| format!("{}{}", STDLIB_PREFIX, file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::cpu::Cpu;
+
+    /// Call `routine` from `std/file` with RF = `input`, after which `data`
+    /// is assembled; returns the CPU once it halts
+    fn run(file: &str, routine: &str, input: &str, data: &str) -> Cpu {
+        let source = format!(
+            "        LOAD RF, {}\n        CALL {}\n        IDL\n{}\n        INCLUDE \"std/{}\"\n",
+            input, routine, data, file
+        );
+        let output = assemble(&source).unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&output.machine_code, 0).unwrap();
+        while !cpu.halted && cpu.instructions_executed < 100_000 {
+            cpu.step().unwrap();
+        }
+        assert!(cpu.halted);
+        cpu
+    }
+
+    /// Call `routine` from `std/file` with RF = `input`; returns RF and DF
+    fn call(file: &str, routine: &str, input: u16) -> (u16, bool) {
+        let cpu = run(file, routine, &format!("0x{:04X}", input), "");
+        (cpu.get_register(0xF).unwrap(), cpu.df)
+    }
+
+    #[test]
+    fn test_every_file_assembles() {
+        for name in stdlib_names() {
+            let source = format!("IDL\nINCLUDE \"{}\"", name);
+            assert!(assemble(&source).is_ok(), "{}", name);
+        }
+        assert!(stdlib_source("mul8.asm").is_none());
+    }
+
+    #[test]
+    fn test_mul8() {
+        assert_eq!(call("mul8.asm", "MUL8", 0x0C0A).0, 120);
+        assert_eq!(call("mul8.asm", "MUL8", 0xFFFF).0, 0xFE01);
+        assert_eq!(call("mul8.asm", "MUL8", 0x1200).0, 0);
+    }
+
+    #[test]
+    fn test_div8() {
+        assert_eq!(call("div8.asm", "DIV8", 0x6407), (0x0E02, false));
+        assert_eq!(call("div8.asm", "DIV8", 0x0509), (0x0005, false));
+        assert_eq!(call("div8.asm", "DIV8", 0x4200), (0x4200, true));
+    }
+
+    #[test]
+    fn test_bcd8() {
+        assert_eq!(call("bcd.asm", "BCD8", 0x00FF).0, 0x0255);
+        assert_eq!(call("bcd.asm", "BCD8", 0x0009).0, 0x0009);
+        assert_eq!(call("bcd.asm", "BCD8", 0x0064).0, 0x0100);
+    }
+
+    #[test]
+    fn test_print() {
+        let cpu = run("print.asm", "PRINT", "MSG", "MSG:    DB \"HI!\", 0");
+        assert_eq!(cpu.ports.output_on(1), b"HI!");
+        let end = cpu.get_register(0xF).unwrap();
+        assert_eq!(cpu.memory[end as usize], 0);
+        assert_eq!(cpu.memory[end as usize - 3..end as usize], *b"HI!");
+        assert_eq!(cpu.x, 2);
+    }
+}
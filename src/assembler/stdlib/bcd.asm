@@ -0,0 +1,36 @@
+; BCD8 - binary to BCD (SCRT subroutine)
+;
+; In:  RF.0 = value
+; Out: RF.1 = hundreds digit, RF.0 = tens digit << 4 | units digit
+; Uses D and DF; RD is preserved on the stack
+
+BCD8:       PUSH RD
+            GLO RF
+            PLO RD              ; RD.0 = what is left to convert
+            LDI 0
+            PHI RF              ; RF.1 = hundreds
+            PHI RD              ; RD.1 = tens, in the high nibble
+BCD8_100:   GLO RD
+            SMI 0x64
+            LBNF BCD8_10
+            PLO RD
+            GHI RF
+            ADI 0x01
+            PHI RF
+            LBR BCD8_100
+BCD8_10:    GLO RD
+            SMI 0x0A
+            LBNF BCD8_1
+            PLO RD
+            GHI RD
+            ADI 0x10
+            PHI RD
+            LBR BCD8_10
+BCD8_1:     GHI RD              ; combine tens and units through M(R2)
+            STXD
+            IRX
+            GLO RD
+            OR
+            PLO RF
+            POP RD
+            RETN
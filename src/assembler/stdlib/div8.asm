@@ -0,0 +1,28 @@
+; DIV8 - unsigned 8 / 8 bit divide (SCRT subroutine)
+;
+; In:  RF.1 = dividend, RF.0 = divisor
+; Out: RF.1 = quotient, RF.0 = remainder, DF = 0
+;      Dividing by zero leaves RF unchanged and sets DF = 1
+; Uses D; RD is preserved on the stack
+
+DIV8:       PUSH RD
+            LDI 0
+            PLO RD              ; RD.0 = quotient
+            GLO RF
+            LBZ DIV8_ZERO
+            STXD                ; divisor at M(R2) for SM
+            IRX
+            GHI RF
+DIV8_LOOP:  PHI RD              ; RD.1 = remainder so far
+            SM
+            LBNF DIV8_DONE      ; borrow: remainder < divisor
+            INC RD
+            LBR DIV8_LOOP
+DIV8_DONE:  GHI RD
+            PLO RF
+            GLO RD
+            PHI RF
+            LBR DIV8_EXIT
+DIV8_ZERO:  SMI 0               ; D is 0 here; no borrow sets DF
+DIV8_EXIT:  POP RD
+            RETN
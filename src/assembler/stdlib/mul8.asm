@@ -0,0 +1,25 @@
+; MUL8 - unsigned 8 x 8 bit multiply (SCRT subroutine)
+;
+; In:  RF.1 = multiplicand, RF.0 = multiplier
+; Out: RF   = 16-bit product
+; Uses D and DF; RC and RD are preserved on the stack
+
+MUL8:       PUSH RC
+            PUSH RD
+            GHI RF              ; RC = multiplicand widened to 16 bits
+            PLO RC
+            LDI 0
+            PHI RC
+            LOAD RD, 0          ; RD = product
+MUL8_LOOP:  GLO RF
+            LBZ MUL8_DONE
+            SHR                 ; next multiplier bit into DF
+            PLO RF
+            LBNF MUL8_SHIFT
+            ADD16 RD, RC
+MUL8_SHIFT: ADD16 RC, RC        ; multiplicand << 1
+            LBR MUL8_LOOP
+MUL8_DONE:  MOV RF, RD
+            POP RD
+            POP RC
+            RETN
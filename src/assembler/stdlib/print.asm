@@ -0,0 +1,13 @@
+; PRINT - write a zero-terminated string to output port 1 (SCRT subroutine)
+;
+; In:  RF = address of the string
+; Out: RF = address of the terminating zero
+; Uses D; X is set back to R2 on return
+
+PRINT:      SEX RF
+PRINT_LOOP: LDN RF
+            LBZ PRINT_DONE
+            OUT 1               ; M(RF) to the port, then RF + 1
+            LBR PRINT_LOOP
+PRINT_DONE: SEX R2
+            RETN
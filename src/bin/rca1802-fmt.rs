@@ -0,0 +1,70 @@
+//! Format 1802 assembly source in place
+//!
+//! Usage: `rca1802-fmt [--check] [FILE...]`. With no files, standard input
+//! is formatted to standard output. `--check` changes nothing and exits
+//! with status 1 if any file is not formatted.
+
+use rca_1802_emulator::assembler::{check_format, format_source, render_diagnostics};
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut files = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("Usage: rca1802-fmt [--check] [FILE...]");
+                return ExitCode::SUCCESS;
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let mut unformatted = false;
+    if files.is_empty() {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("error: reading standard input: {}", e);
+            return ExitCode::FAILURE;
+        }
+        if check {
+            unformatted = report(&source, "<stdin>");
+        } else {
+            print!("{}", format_source(&source));
+        }
+    }
+
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("error: {}: {}", file, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if check {
+            unformatted |= report(&source, file);
+        } else if let Err(e) = std::fs::write(file, format_source(&source)) {
+            eprintln!("error: {}: {}", file, e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Print the lines of `source` that are not formatted; returns whether
+/// there were any
+fn report(source: &str, name: &str) -> bool {
+    let diagnostics = check_format(source, name);
+    if !diagnostics.is_empty() {
+        eprint!("{}", render_diagnostics(&diagnostics, source));
+    }
+    !diagnostics.is_empty()
+}
@@ -0,0 +1,10 @@
+//! Language server for RCA 1802 assembly, over stdio
+
+use rca_1802_emulator::lsp;
+use std::io;
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    lsp::run(stdin.lock(), stdout.lock())
+}
@@ -0,0 +1,13 @@
+//! Assemble, run, disassemble and trace 1802 programs from the shell
+//!
+//! Usage: `rca1802 <asm|run|disasm|trace> FILE [options]`; see
+//! `rca1802 --help`.
+
+use std::io;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let status = rca_1802_emulator::cli::main(&args, &mut io::stdout(), &mut io::stderr());
+    ExitCode::from(status)
+}
@@ -0,0 +1,266 @@
+use super::{Challenge, Report, Score, Solution};
+use crate::assembler::{
+    AssembleOptions, FileSystem, SourceProvider, assemble_file, render_file_diagnostics,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+/// One student's graded submission
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    /// The file name without its extension
+    pub student: String,
+    pub file: String,
+    pub passed: bool,
+    /// Rendered assembler diagnostics, when the file did not assemble
+    pub assembly_errors: Option<String>,
+    /// How the program did; absent when it did not assemble
+    pub report: Option<Report>,
+    /// The first failing assertion, or the first assembler error
+    pub first_failure: Option<String>,
+    /// Size and average machine cycles, when every test case passed
+    pub score: Option<Score>,
+}
+
+/// Assemble the file `name` read through `provider` and grade it against
+/// every test case, hidden ones included
+pub fn grade_file(
+    challenge: &Challenge,
+    name: &str,
+    provider: &dyn SourceProvider,
+    options: &AssembleOptions,
+) -> Submission {
+    let student = Path::new(name)
+        .file_stem()
+        .map_or(name.to_string(), |s| s.to_string_lossy().into_owned());
+    let mut submission = Submission {
+        student,
+        file: name.to_string(),
+        passed: false,
+        assembly_errors: None,
+        report: None,
+        first_failure: None,
+        score: None,
+    };
+    let solution = match assemble_file(name, provider, options) {
+        Ok(output) => Solution::assembled(&output),
+        Err(error) => {
+            let errors = match error.diagnostics() {
+                [] => format!("error: {}: {}", name, error),
+                diagnostics => render_file_diagnostics(diagnostics, provider),
+            };
+            submission.first_failure = errors.lines().next().map(str::to_string);
+            submission.assembly_errors = Some(errors);
+            return submission;
+        }
+    };
+
+    let report = challenge.grade(&solution);
+    submission.passed = report.passed();
+    let counterexample = (report.verification.as_ref()).and_then(|v| v.counterexample.as_ref());
+    submission.first_failure = report
+        .rejected
+        .clone()
+        .or_else(|| report.cases.iter().find_map(|c| c.message.clone()))
+        .or_else(|| counterexample.map(|c| c.message.clone()));
+    submission.score = Score::of(&report);
+    submission.report = Some(report);
+    submission
+}
+
+/// Grade every `.asm` file in `dir`, in file name order
+///
+/// `INCLUDE`s are resolved against `dir`.
+pub fn grade_directory(
+    challenge: &Challenge,
+    dir: &Path,
+    options: &AssembleOptions,
+) -> std::io::Result<Vec<Submission>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_asm = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("asm"));
+        if is_asm && path.is_file() {
+            names.push(path.file_name().unwrap().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    let provider = FileSystem::new(dir);
+    Ok(names
+        .iter()
+        .map(|name| grade_file(challenge, name, &provider, options))
+        .collect())
+}
+
+/// A JUnit XML report: a test suite per submission and a test case per
+/// challenge test case, plus one for the reference model when the program
+/// was checked against it
+///
+/// A submission that did not assemble has a single `assemble` test case in
+/// error; one over the size limit has a single failing `size` test case.
+pub fn junit(challenge: &Challenge, submissions: &[Submission]) -> String {
+    let mut suites = String::new();
+    let (mut tests, mut failures, mut errors) = (0, 0, 0);
+    for submission in submissions {
+        let class = escape(&format!("{}.{}", challenge.id, submission.student));
+        let mut cases = String::new();
+        let (mut suite_tests, mut suite_failures, mut suite_errors) = (0, 0, 0);
+        let mut case = |name: &str, element: Option<(&str, &str, &str)>| {
+            suite_tests += 1;
+            let _ = write!(
+                cases,
+                "    <testcase classname=\"{}\" name=\"{}\"",
+                class,
+                escape(name)
+            );
+            match element {
+                None => cases.push_str("/>\n"),
+                Some((tag, message, text)) => {
+                    match tag {
+                        "error" => suite_errors += 1,
+                        _ => suite_failures += 1,
+                    }
+                    let _ = write!(
+                        cases,
+                        ">\n      <{tag} message=\"{}\">{}</{tag}>\n    </testcase>\n",
+                        escape(message),
+                        escape(text)
+                    );
+                }
+            }
+        };
+
+        match (&submission.assembly_errors, &submission.report) {
+            (Some(errors), _) => {
+                let message = submission.first_failure.as_deref().unwrap_or_default();
+                case("assemble", Some(("error", message, errors)));
+            }
+            (None, Some(report)) if report.rejected.is_some() => {
+                let message = report.rejected.as_deref().unwrap_or_default();
+                case("size", Some(("failure", message, message)));
+            }
+            (None, Some(report)) => {
+                for result in &report.cases {
+                    let name = match result.hidden {
+                        true => format!("{} (hidden)", result.name),
+                        false => result.name.clone(),
+                    };
+                    let message = result.message.as_deref().unwrap_or_default();
+                    let failure = (!result.passed).then_some(("failure", message, message));
+                    case(&name, failure);
+                }
+                if let Some(verification) = &report.verification {
+                    let failure = (verification.counterexample.as_ref())
+                        .map(|c| ("failure", c.message.as_str(), c.message.as_str()));
+                    case("reference model", failure);
+                }
+            }
+            (None, None) => {}
+        }
+
+        let _ = write!(
+            suites,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n{}  </testsuite>\n",
+            escape(&submission.student),
+            suite_tests,
+            suite_failures,
+            suite_errors,
+            cases
+        );
+        tests += suite_tests;
+        failures += suite_failures;
+        errors += suite_errors;
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n{}</testsuites>\n",
+        escape(&challenge.id),
+        tests,
+        failures,
+        errors,
+        suites
+    )
+}
+
+/// `text` with the characters XML gives meaning to replaced by entities
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::MemoryFiles;
+
+    #[test]
+    fn test_grade_submissions() {
+        let challenge = super::super::builtin()
+            .into_iter()
+            .find(|c| c.id == "add-two-inputs")
+            .unwrap();
+        let mut files = MemoryFiles::new();
+        files.insert(
+            "alice.asm",
+            "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL\n",
+        );
+        files.insert(
+            "bob.asm",
+            "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nLBDF 0x0000\nIDL\n",
+        );
+        files.insert("carol.asm", "LDI 0x05\nFROB R2\n");
+        let options = AssembleOptions::default();
+        let graded: Vec<Submission> = ["alice.asm", "bob.asm", "carol.asm"]
+            .iter()
+            .map(|name| grade_file(&challenge, name, &files, &options))
+            .collect();
+
+        assert!(graded[0].passed);
+        assert_eq!(graded[0].student, "alice");
+        assert_eq!(graded[0].first_failure, None);
+        assert_eq!(graded[0].score.unwrap().bytes, 9);
+
+        // Starts over when the sum carries, which only the hidden case does
+        assert!(!graded[1].passed);
+        let first = graded[1].first_failure.as_deref().unwrap();
+        assert!(first.contains("(carry is dropped)"), "{}", first);
+        assert_eq!(graded[1].report.as_ref().unwrap().passed_count(), 2);
+
+        assert!(graded[2].report.is_none());
+        let errors = graded[2].assembly_errors.as_deref().unwrap();
+        assert!(errors.contains("--> carol.asm:2:"), "{}", errors);
+        assert!(
+            graded[2]
+                .first_failure
+                .as_deref()
+                .unwrap()
+                .starts_with("error")
+        );
+
+        let xml = junit(&challenge, &graded);
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites name=\"add-two-inputs\" tests=\"8\" failures=\"1\" errors=\"1\">\n"
+        ));
+        assert!(xml.contains(
+            "  <testsuite name=\"alice\" tests=\"4\" failures=\"0\" errors=\"0\">\n    \
+             <testcase classname=\"add-two-inputs.alice\" name=\"5 + 7\"/>\n"
+        ));
+        assert!(xml.contains("alice\" name=\"reference model\"/>"));
+        assert!(xml.contains("name=\"carry is dropped (hidden)\">\n      <failure message=\"❌"));
+        assert!(xml.contains("<testcase classname=\"add-two-inputs.carol\" name=\"assemble\">"));
+        assert_eq!(escape("a<b & \"c\""), "a&lt;b &amp; &quot;c&quot;");
+    }
+}
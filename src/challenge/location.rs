@@ -0,0 +1,198 @@
+use super::ChallengeError;
+use crate::cpu::Cpu;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A register, or half of one, that a challenge sets or checks
+///
+/// Written `d`, `df`, `p`, `x`, `q`, `ie`, `r0`-`rf`, or `r1.hi` / `r1.lo`
+/// for one byte of a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Location {
+    D,
+    Df,
+    P,
+    X,
+    Q,
+    Ie,
+    R(u8),
+    High(u8),
+    Low(u8),
+}
+
+impl Location {
+    pub fn get(self, cpu: &Cpu) -> u16 {
+        match self {
+            Self::D => cpu.d as u16,
+            Self::Df => cpu.df as u16,
+            Self::P => cpu.p as u16,
+            Self::X => cpu.x as u16,
+            Self::Q => cpu.q as u16,
+            Self::Ie => cpu.ie as u16,
+            Self::R(n) => cpu.registers[n as usize],
+            Self::High(n) => cpu.registers[n as usize] >> 8,
+            Self::Low(n) => cpu.registers[n as usize] & 0xFF,
+        }
+    }
+
+    pub fn set(self, cpu: &mut Cpu, value: u16) {
+        match self {
+            Self::D => cpu.d = value as u8,
+            Self::Df => cpu.df = value & 1 != 0,
+            Self::P => cpu.p = value as u8 & 0x0F,
+            Self::X => cpu.x = value as u8 & 0x0F,
+            Self::Q => cpu.q = value & 1 != 0,
+            Self::Ie => cpu.ie = value & 1 != 0,
+            Self::R(n) => cpu.registers[n as usize] = value,
+            Self::High(n) => {
+                let r = &mut cpu.registers[n as usize];
+                *r = (*r & 0x00FF) | (value << 8);
+            }
+            Self::Low(n) => {
+                let r = &mut cpu.registers[n as usize];
+                *r = (*r & 0xFF00) | (value & 0xFF);
+            }
+        }
+    }
+
+    /// `value` as hex, as wide as the location
+    pub fn format(self, value: u16) -> String {
+        match self {
+            Self::R(_) => format!("0x{:04X}", value),
+            Self::Df | Self::Q | Self::Ie => value.to_string(),
+            _ => format!("0x{:02X}", value),
+        }
+    }
+}
+
+impl TryFrom<String> for Location {
+    type Error = ChallengeError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let lower = text.to_lowercase();
+        let register = |digit: &str| {
+            (digit.len() == 1)
+                .then(|| u8::from_str_radix(digit, 16).ok())
+                .flatten()
+        };
+        let location = match lower.as_str() {
+            "d" => Some(Self::D),
+            "df" => Some(Self::Df),
+            "p" => Some(Self::P),
+            "x" => Some(Self::X),
+            "q" => Some(Self::Q),
+            "ie" => Some(Self::Ie),
+            _ => lower
+                .strip_prefix('r')
+                .and_then(|rest| match rest.split_once('.') {
+                    None => register(rest).map(Self::R),
+                    Some((n, "hi")) => register(n).map(Self::High),
+                    Some((n, "lo")) => register(n).map(Self::Low),
+                    Some(_) => None,
+                }),
+        };
+        location.ok_or(ChallengeError::Location(text))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::D => write!(f, "d"),
+            Self::Df => write!(f, "df"),
+            Self::P => write!(f, "p"),
+            Self::X => write!(f, "x"),
+            Self::Q => write!(f, "q"),
+            Self::Ie => write!(f, "ie"),
+            Self::R(n) => write!(f, "r{:x}", n),
+            Self::High(n) => write!(f, "r{:x}.hi", n),
+            Self::Low(n) => write!(f, "r{:x}.lo", n),
+        }
+    }
+}
+
+impl From<Location> for String {
+    fn from(location: Location) -> Self {
+        location.to_string()
+    }
+}
+
+/// A memory address key, written in hex (`0x0010`) or decimal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Address(pub u16);
+
+impl TryFrom<String> for Address {
+    type Error = ChallengeError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let value = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => text.parse(),
+        };
+        value.map(Self).map_err(|_| ChallengeError::Address(text))
+    }
+}
+
+impl From<Address> for String {
+    fn from(address: Address) -> Self {
+        format!("0x{:04X}", address.0)
+    }
+}
+
+/// An I/O port key, 1-7
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Port(pub u8);
+
+impl TryFrom<String> for Port {
+    type Error = ChallengeError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        match text.parse() {
+            Ok(port @ 1..=7) => Ok(Self(port)),
+            _ => Err(ChallengeError::Port(text)),
+        }
+    }
+}
+
+impl From<Port> for String {
+    fn from(port: Port) -> Self {
+        port.0.to_string()
+    }
+}
+
+/// Where one generated input byte goes: a register (`r3.lo`), a byte of
+/// memory (`0x0010`) or the next byte waiting on an input port (`port1`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Slot {
+    Register(Location),
+    Memory(Address),
+    Port(Port),
+}
+
+impl TryFrom<String> for Slot {
+    type Error = ChallengeError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        if let Some(port) = text.strip_prefix("port") {
+            return Port::try_from(port.to_string()).map(Self::Port);
+        }
+        match text.starts_with(|c: char| c.is_ascii_digit()) {
+            true => Address::try_from(text).map(Self::Memory),
+            false => Location::try_from(text).map(Self::Register),
+        }
+    }
+}
+
+impl From<Slot> for String {
+    fn from(slot: Slot) -> Self {
+        match slot {
+            Slot::Register(location) => location.to_string(),
+            Slot::Memory(address) => address.into(),
+            Slot::Port(port) => format!("port{}", port.0),
+        }
+    }
+}
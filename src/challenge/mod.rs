@@ -0,0 +1,358 @@
+//! Data-driven challenges
+//!
+//! Challenges are written in TOML as an array of `[[challenge]]` tables.
+//! Each gives the state its program starts from (registers, memory and
+//! bytes fed to input ports), cycle and size limits, and test cases that
+//! add their own setup and say which registers, memory and port output
+//! they expect. Hidden test cases are run without revealing their
+//! details. Feedback messages can be replaced per check, keyed `halted`,
+//! `max_bytes`, a register such as `r1.hi`, `memory` or `output`, with
+//! `{expected}`, `{actual}`, `{address}` and `{port}` filled in.
+//!
+//! An accepted solution is scored by its size and the machine cycles it
+//! takes on average, and compared with the challenge's `reference`
+//! solution. Personal bests are kept through a [`ScoreStorage`].
+//!
+//! Hand-picked test cases can miss programs that only work for some
+//! inputs, so a challenge may also name a reference model written in Rust
+//! (see [`model`]) and say where its input bytes go. A program that
+//! passes every test case is then checked against the model on seeded
+//! random inputs, and when graded on every combination of up to
+//! [`EXHAUSTIVE_BYTES`] input bytes, and the smallest input it gets wrong
+//! is reported.
+//!
+//! For instructors, [`grade_directory`] assembles and grades a directory of
+//! submissions, hidden test cases described in full, and [`junit`] turns
+//! the results into a JUnit XML report.
+//!
+//! ```toml
+//! [[challenge]]
+//! id = "double"
+//! title = "Double It"
+//! description = "Read a byte from port 1 and write twice its value to port 4."
+//! max_bytes = 16
+//!
+//! [[challenge.tests]]
+//! name = "doubles 21"
+//! setup = { input = { "1" = [21] } }
+//! expect = { output = { "4" = [42] } }
+//!
+//! [challenge.verify]
+//! model = "double"
+//! inputs = ["port1"]
+//! ```
+
+mod grader;
+mod location;
+mod models;
+mod runner;
+mod score;
+mod storage;
+mod verify;
+
+pub use grader::{Submission, grade_directory, grade_file, junit};
+pub use location::{Address, Location, Port, Slot};
+pub use models::{Model, model};
+pub use runner::{CaseResult, Report, Solution};
+pub use score::{LEADERBOARD_SIZE, Leaderboard, NewBest, Score, describe};
+#[cfg(target_arch = "wasm32")]
+pub use storage::BrowserStorage;
+pub use storage::{FileStorage, ScoreStorage, StorageError, leaderboard, record};
+pub use verify::{Counterexample, Coverage, EXHAUSTIVE_BYTES, Verification};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// Machine cycles a test case may run for unless the challenge says
+/// otherwise
+pub const DEFAULT_MAX_CYCLES: u64 = 100_000;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ChallengeError {
+    #[error("{0}")]
+    Parse(String),
+    #[error("unknown register `{0}`")]
+    Location(String),
+    #[error("invalid address `{0}`")]
+    Address(String),
+    #[error("invalid port `{0}`: ports are 1-7")]
+    Port(String),
+    #[error("challenge `{0}` has no test cases")]
+    NoTests(String),
+    #[error("duplicate challenge id `{0}`")]
+    Duplicate(String),
+    #[error("memory at {0:#06x} runs past the end of the address space")]
+    Overflow(u16),
+    #[error("unknown reference model `{0}`")]
+    Model(String),
+    #[error("reference model `{0}` takes {1} input bytes")]
+    Inputs(String, usize),
+}
+
+/// A programming challenge and the test cases that grade it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// State every test case starts from
+    #[serde(default)]
+    pub setup: Setup,
+    #[serde(default = "default_max_cycles")]
+    pub max_cycles: u64,
+    /// Largest program accepted, in bytes
+    #[serde(default)]
+    pub max_bytes: Option<usize>,
+    /// Shown when every test case passes
+    #[serde(default)]
+    pub success: Option<String>,
+    /// Replacement failure messages, by check
+    #[serde(default)]
+    pub feedback: BTreeMap<String, String>,
+    /// Source of a solution to score players against
+    #[serde(default)]
+    pub reference: Option<String>,
+    /// Checking against a reference model on generated inputs
+    #[serde(default)]
+    pub verify: Option<Verify>,
+    pub tests: Vec<TestCase>,
+}
+
+/// Registers, memory and input port bytes set before the program runs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Setup {
+    #[serde(default)]
+    pub registers: BTreeMap<Location, u16>,
+    #[serde(default)]
+    pub memory: BTreeMap<Address, Vec<u8>>,
+    #[serde(default)]
+    pub input: BTreeMap<Port, Vec<u8>>,
+}
+
+/// One run of the program, with setup on top of the challenge's own
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub setup: Setup,
+    #[serde(default)]
+    pub expect: Expect,
+}
+
+/// What must hold once a test case's run ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expect {
+    /// The program must reach IDL within the cycle limit
+    #[serde(default = "default_halted")]
+    pub halted: bool,
+    #[serde(default)]
+    pub registers: BTreeMap<Location, u16>,
+    #[serde(default)]
+    pub memory: BTreeMap<Address, Vec<u8>>,
+    /// Every byte written to each port, in order
+    #[serde(default)]
+    pub output: BTreeMap<Port, Vec<u8>>,
+}
+
+impl Default for Expect {
+    fn default() -> Self {
+        Self {
+            halted: true,
+            registers: BTreeMap::new(),
+            memory: BTreeMap::new(),
+            output: BTreeMap::new(),
+        }
+    }
+}
+
+/// How to check a program against a reference model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verify {
+    /// The [`model`] giving what a correct program leaves behind
+    pub model: String,
+    /// Where each input byte goes, in the order the model takes them; as
+    /// many as the model takes
+    pub inputs: Vec<Slot>,
+    /// Random inputs to try when there are too many to try them all
+    #[serde(default = "default_samples")]
+    pub samples: u64,
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+}
+
+fn default_samples() -> u64 {
+    1000
+}
+
+fn default_seed() -> u64 {
+    1802
+}
+
+fn default_max_cycles() -> u64 {
+    DEFAULT_MAX_CYCLES
+}
+
+fn default_halted() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct File {
+    #[serde(default)]
+    challenge: Vec<Challenge>,
+}
+
+/// Parse and check a TOML file of `[[challenge]]` tables
+pub fn parse(text: &str) -> Result<Vec<Challenge>, ChallengeError> {
+    let file: File = toml::from_str(text).map_err(|e| ChallengeError::Parse(e.to_string()))?;
+    let mut ids = BTreeSet::new();
+    for challenge in &file.challenge {
+        if !ids.insert(challenge.id.as_str()) {
+            return Err(ChallengeError::Duplicate(challenge.id.clone()));
+        }
+        if challenge.tests.is_empty() {
+            return Err(ChallengeError::NoTests(challenge.id.clone()));
+        }
+        if let Some(verify) = &challenge.verify {
+            let model =
+                model(&verify.model).ok_or_else(|| ChallengeError::Model(verify.model.clone()))?;
+            if verify.inputs.len() != model.inputs {
+                return Err(ChallengeError::Inputs(model.name.to_string(), model.inputs));
+            }
+        }
+        let blocks = challenge.setup.memory.iter().chain(
+            challenge
+                .tests
+                .iter()
+                .flat_map(|t| t.setup.memory.iter().chain(&t.expect.memory)),
+        );
+        for (address, bytes) in blocks {
+            if address.0 as usize + bytes.len() > crate::cpu::Cpu::MEMORY_SIZE {
+                return Err(ChallengeError::Overflow(address.0));
+            }
+        }
+    }
+    Ok(file.challenge)
+}
+
+/// The challenges that ship with the game
+pub fn builtin() -> Vec<Challenge> {
+    parse(include_str!("builtin.toml")).expect("built-in challenges are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_challenge() {
+        let text = r#"
+[[challenge]]
+id = "store"
+title = "Store"
+description = "Store D at R3"
+max_bytes = 8
+setup = { registers = { r3 = 0x0010, "R4.HI" = 0x12 }, memory = { "0x0100" = [1, 2] } }
+feedback = { "r1.hi" = "R1.high is {actual}" }
+
+[[challenge.tests]]
+name = "visible"
+setup = { input = { "1" = [5] } }
+expect = { memory = { "16" = [0xFF] }, output = { "4" = [12] } }
+
+[[challenge.tests]]
+name = "hidden"
+hidden = true
+expect = { halted = false }
+"#;
+        let challenges = parse(text).unwrap();
+        let challenge = &challenges[0];
+        assert_eq!(challenge.max_cycles, DEFAULT_MAX_CYCLES);
+        assert_eq!(challenge.max_bytes, Some(8));
+        assert_eq!(challenge.setup.registers[&Location::R(3)], 0x0010);
+        assert_eq!(challenge.setup.registers[&Location::High(4)], 0x12);
+        assert_eq!(challenge.setup.memory[&Address(0x0100)], vec![1, 2]);
+        let (visible, hidden) = (&challenge.tests[0], &challenge.tests[1]);
+        assert_eq!(visible.setup.input[&Port(1)], vec![5]);
+        assert_eq!(visible.expect.memory[&Address(0x10)], vec![0xFF]);
+        assert!(visible.expect.halted && !visible.hidden);
+        assert!(!hidden.expect.halted && hidden.hidden);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let challenge = |body: &str| {
+            format!(
+                "[[challenge]]\nid = \"a\"\ntitle = \"A\"\ndescription = \"\"\n{}",
+                body
+            )
+        };
+        let error = |text: &str| parse(text).unwrap_err();
+        assert_eq!(
+            error(&challenge("tests = []")),
+            ChallengeError::NoTests("a".into())
+        );
+        let one = challenge("[[challenge.tests]]\nname = \"t\"\n");
+        assert_eq!(
+            error(&format!("{}{}", one, one)),
+            ChallengeError::Duplicate("a".into())
+        );
+        assert_eq!(
+            error(&challenge(
+                "setup = { memory = { \"0xFFFF\" = [1, 2] } }\n[[challenge.tests]]\nname = \"t\""
+            )),
+            ChallengeError::Overflow(0xFFFF)
+        );
+        assert_eq!(
+            error(&format!(
+                "{}[challenge.verify]\nmodel = \"nope\"\ninputs = [\"port1\"]\n",
+                one
+            )),
+            ChallengeError::Model("nope".into())
+        );
+        let verify = |inputs: &str| {
+            error(&format!(
+                "{}[challenge.verify]\nmodel = \"double\"\ninputs = [{}]\n",
+                one, inputs
+            ))
+        };
+        assert!(matches!(verify("\"port9\""), ChallengeError::Parse(_)));
+        assert_eq!(
+            verify("\"r3.lo\", \"0x0010\""),
+            ChallengeError::Inputs("double".into(), 1)
+        );
+        for bad in [
+            "registers = { r10 = 1 }",
+            "input = { \"8\" = [1] }",
+            "memory = { \"0xG\" = [] }",
+        ] {
+            let text = challenge(&format!(
+                "setup = {{ {} }}\n[[challenge.tests]]\nname = \"t\"",
+                bad
+            ));
+            assert!(matches!(error(&text), ChallengeError::Parse(_)), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_builtin_challenges_load() {
+        let challenges = builtin();
+        assert!(challenges.len() >= 5);
+        let solve = |id: &str, source: &str| {
+            let challenge = challenges.iter().find(|c| c.id == id).unwrap();
+            let output = crate::assembler::assemble(source).unwrap();
+            challenge.check(&Solution::assembled(&output))
+        };
+        assert!(solve("memory-write", "LDI 0xFF\nSTR R3\nIDL").passed());
+        let report = solve("memory-write", "LDI 0x10\nPLO R4\nLDI 0xFF\nSTR R4\nIDL");
+        assert_eq!(report.passed_count(), 1);
+        let sum = "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL";
+        assert!(solve("add-two-inputs", sum).passed());
+        for challenge in &challenges {
+            assert!(challenge.reference_score().is_some(), "{}", challenge.id);
+        }
+    }
+}
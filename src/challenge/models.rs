@@ -0,0 +1,58 @@
+use super::{Expect, Port};
+
+/// A reference model: what a correct program leaves behind given its
+/// input bytes
+#[derive(Debug, Clone, Copy)]
+pub struct Model {
+    pub name: &'static str,
+    /// Input bytes it takes
+    pub inputs: usize,
+    pub expect: fn(&[u8]) -> Expect,
+}
+
+/// Every model a challenge can name in `verify.model`
+const MODELS: &[Model] = &[
+    Model {
+        name: "double",
+        inputs: 1,
+        expect: double,
+    },
+    Model {
+        name: "add-bytes",
+        inputs: 2,
+        expect: add_bytes,
+    },
+    Model {
+        name: "add-words",
+        inputs: 4,
+        expect: add_words,
+    },
+];
+
+/// The reference model called `name`
+pub fn model(name: &str) -> Option<Model> {
+    MODELS.iter().find(|m| m.name == name).copied()
+}
+
+/// `bytes` written to port 4
+fn output(bytes: Vec<u8>) -> Expect {
+    let mut expect = Expect::default();
+    expect.output.insert(Port(4), bytes);
+    expect
+}
+
+/// Twice the input, dropping the carry
+fn double(input: &[u8]) -> Expect {
+    output(input.iter().map(|b| b.wrapping_mul(2)).collect())
+}
+
+/// The sum of the inputs, dropping the carry
+fn add_bytes(input: &[u8]) -> Expect {
+    output(vec![input.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))])
+}
+
+/// The sum of two 16-bit inputs, high byte first, dropping the carry
+fn add_words(input: &[u8]) -> Expect {
+    let word = |i: usize| u16::from_be_bytes([input[i], input[i + 1]]);
+    output(word(0).wrapping_add(word(2)).to_be_bytes().to_vec())
+}
@@ -0,0 +1,421 @@
+use super::{Challenge, Coverage, Setup, TestCase, Verification};
+use crate::assembler::AssemblyOutput;
+use crate::cpu::{Cpu, CpuError};
+use serde::{Deserialize, Serialize};
+
+/// A program to check: its memory image from address 0 and the size it
+/// is scored by
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Solution {
+    pub image: Vec<u8>,
+    /// Bytes the program emitted, leaving out ORG gaps and the SCRT runtime
+    pub bytes: usize,
+}
+
+impl Solution {
+    /// An assembled program, sized by [`AssemblyOutput::program_bytes`]
+    pub fn assembled(output: &AssemblyOutput) -> Self {
+        Solution {
+            image: output.machine_code.clone(),
+            bytes: output.program_bytes(),
+        }
+    }
+
+    /// A raw image, every byte of which counts
+    pub fn image(image: Vec<u8>) -> Self {
+        let bytes = image.len();
+        Solution { image, bytes }
+    }
+}
+
+/// How one test case went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub hidden: bool,
+    pub passed: bool,
+    /// Why it failed; a hidden test case only says that it did
+    pub message: Option<String>,
+    /// Machine cycles the run took
+    pub cycles: u64,
+}
+
+/// How a program did on a whole challenge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    pub challenge: String,
+    /// Program size in bytes, as [`Solution::bytes`]
+    pub bytes: usize,
+    /// Set when the program was turned away before any test case ran
+    pub rejected: Option<String>,
+    pub cases: Vec<CaseResult>,
+    /// Checking against the reference model, once every test case passed
+    #[serde(default)]
+    pub verification: Option<Verification>,
+    /// What to tell the player: the success message, or the first failure
+    pub summary: String,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.rejected.is_none()
+            && self.cases.iter().all(|c| c.passed)
+            && self
+                .verification
+                .as_ref()
+                .is_none_or(|v| v.counterexample.is_none())
+    }
+
+    pub fn passed_count(&self) -> usize {
+        self.cases.iter().filter(|c| c.passed).count()
+    }
+}
+
+impl Challenge {
+    /// A CPU with `program` loaded at 0, then the challenge's setup and
+    /// `case`'s applied
+    pub fn prepare(&self, program: &[u8], case: Option<&TestCase>) -> Result<Cpu, CpuError> {
+        let mut cpu = Cpu::new();
+        cpu.load_program(program, 0)?;
+        apply(&mut cpu, &self.setup)?;
+        if let Some(case) = case {
+            apply(&mut cpu, &case.setup)?;
+        }
+        Ok(cpu)
+    }
+
+    /// The CPU the player starts from: the challenge's setup and its first
+    /// visible test case's
+    pub fn initial_cpu(&self, program: &[u8]) -> Result<Cpu, CpuError> {
+        self.prepare(program, self.tests.iter().find(|t| !t.hidden))
+    }
+
+    /// Run `solution` through every test case, then against the reference
+    /// model on sampled inputs
+    pub fn check(&self, solution: &Solution) -> Report {
+        self.run(solution, false, Some(Coverage::Sampled))
+    }
+
+    /// As [`Challenge::check`], but hidden test cases' failures are
+    /// described in full and the reference model gets every input it can,
+    /// for the instructor grading the program
+    pub fn grade(&self, solution: &Solution) -> Report {
+        self.run(solution, true, Some(Coverage::Exhaustive))
+    }
+
+    /// Run `solution` through the test cases only, leaving out the
+    /// reference model
+    pub(super) fn test(&self, solution: &Solution) -> Report {
+        self.run(solution, false, None)
+    }
+
+    fn run(&self, solution: &Solution, reveal: bool, coverage: Option<Coverage>) -> Report {
+        let program = &solution.image;
+        let mut report = Report {
+            challenge: self.id.clone(),
+            bytes: solution.bytes,
+            rejected: None,
+            cases: Vec::new(),
+            verification: None,
+            summary: String::new(),
+        };
+        if let Some(max) = self.max_bytes
+            && solution.bytes > max
+        {
+            let message = self.message(
+                "max_bytes",
+                "❌ Your program is {actual} bytes; the limit is {expected}.",
+                &[
+                    ("actual", solution.bytes.to_string()),
+                    ("expected", max.to_string()),
+                ],
+            );
+            report.summary = message.clone();
+            report.rejected = Some(message);
+            return report;
+        }
+
+        report.cases = (self.tests.iter().enumerate())
+            .map(|(i, case)| self.run_case(program, i, case, reveal))
+            .collect();
+        let total = report.cases.len();
+        if report.cases.iter().all(|c| c.passed) {
+            report.verification = coverage.and_then(|c| self.verify(program, c));
+        }
+        let tested = match &report.verification {
+            Some(verification) => verification.describe(total),
+            None if total == 1 => String::new(),
+            None => format!("All {} tests passed.", total),
+        };
+        report.summary = match report.cases.iter().find(|c| !c.passed) {
+            None => {
+                let verification = report.verification.as_ref();
+                let text = match verification.and_then(|v| v.counterexample.as_ref()) {
+                    Some(counterexample) => counterexample.message.clone(),
+                    None => self
+                        .success
+                        .as_deref()
+                        .unwrap_or("✅ Challenge completed!")
+                        .to_string(),
+                };
+                match tested.is_empty() {
+                    true => text,
+                    false => format!("{}\n\n{}", text, tested),
+                }
+            }
+            Some(failed) => {
+                let message = failed.message.clone().unwrap_or_default();
+                match total {
+                    1 => message,
+                    _ => format!(
+                        "{}\n\n{} of {} tests passed.",
+                        message,
+                        report.passed_count(),
+                        total
+                    ),
+                }
+            }
+        };
+        report
+    }
+
+    fn run_case(&self, program: &[u8], index: usize, case: &TestCase, reveal: bool) -> CaseResult {
+        let (failure, cycles) = self.execute(program, case);
+        let message = failure.map(|m| match case.hidden && !reveal {
+            true => format!("❌ Hidden test {} failed.", index + 1),
+            false => format!("{} ({})", m, case.name),
+        });
+        CaseResult {
+            name: case.name.clone(),
+            hidden: case.hidden,
+            passed: message.is_none(),
+            message,
+            cycles,
+        }
+    }
+
+    /// Run `program` on `case`, returning the first expectation it misses
+    /// and the machine cycles it took
+    pub(super) fn execute(&self, program: &[u8], case: &TestCase) -> (Option<String>, u64) {
+        let outcome = self.prepare(program, Some(case)).and_then(|mut cpu| {
+            while !cpu.halted && cpu.cycles < self.max_cycles {
+                cpu.step()?;
+            }
+            Ok(cpu)
+        });
+        match outcome {
+            Ok(cpu) => (self.failure(&cpu, case), cpu.cycles),
+            Err(e) => (Some(format!("❌ Execution error: {}", e)), 0),
+        }
+    }
+
+    /// The first expectation `cpu` misses, as a feedback message
+    fn failure(&self, cpu: &Cpu, case: &TestCase) -> Option<String> {
+        let expect = &case.expect;
+        if expect.halted && !cpu.halted {
+            return Some(self.message(
+                "halted",
+                "❌ CPU not halted within {expected} cycles. Make sure your program ends with IDL.",
+                &[("expected", self.max_cycles.to_string())],
+            ));
+        }
+
+        for (&location, &expected) in &expect.registers {
+            let actual = location.get(cpu);
+            if actual != expected {
+                let name = location.to_string();
+                let default = format!(
+                    "❌ {} = {{actual}} (expected {{expected}})",
+                    name.to_uppercase()
+                );
+                return Some(self.message(
+                    &name,
+                    &default,
+                    &[
+                        ("actual", location.format(actual)),
+                        ("expected", location.format(expected)),
+                    ],
+                ));
+            }
+        }
+
+        for (address, bytes) in &expect.memory {
+            for (offset, &expected) in bytes.iter().enumerate() {
+                let at = address.0 as usize + offset;
+                let actual = cpu.memory[at];
+                if actual != expected {
+                    return Some(self.message(
+                        "memory",
+                        "❌ Memory[{address}] = {actual} (expected {expected})",
+                        &[
+                            ("address", format!("0x{:04X}", at)),
+                            ("actual", format!("0x{:02X}", actual)),
+                            ("expected", format!("0x{:02X}", expected)),
+                        ],
+                    ));
+                }
+            }
+        }
+
+        for (port, expected) in &expect.output {
+            let actual = cpu.ports.output_on(port.0);
+            if &actual != expected {
+                return Some(self.message(
+                    "output",
+                    "❌ Port {port} output [{actual}] (expected [{expected}])",
+                    &[
+                        ("port", port.0.to_string()),
+                        ("actual", hex_list(&actual)),
+                        ("expected", hex_list(expected)),
+                    ],
+                ));
+            }
+        }
+        None
+    }
+
+    /// The feedback for `key`, or `default`, with `{name}` fields filled in
+    fn message(&self, key: &str, default: &str, fields: &[(&str, String)]) -> String {
+        let template = self.feedback.get(key).map_or(default, String::as_str);
+        fields
+            .iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+}
+
+fn apply(cpu: &mut Cpu, setup: &Setup) -> Result<(), CpuError> {
+    for (address, bytes) in &setup.memory {
+        cpu.load_program(bytes, address.0)?;
+    }
+    for (&location, &value) in &setup.registers {
+        location.set(cpu, value);
+    }
+    for (port, bytes) in &setup.input {
+        cpu.ports.feed(port.0, bytes);
+    }
+    Ok(())
+}
+
+pub(super) fn hex_list(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("0x{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::Solution;
+    use crate::assembler::assemble;
+
+    const ADD_PORTS: &str = r#"
+[[challenge]]
+id = "add"
+title = "Add Two Inputs"
+description = "Read two bytes from port 1 and write their sum to port 4."
+max_bytes = 24
+max_cycles = 200
+setup = { registers = { r2 = 0x0100 } }
+success = "Well done"
+feedback = { output = "Port {port} got [{actual}], wanted [{expected}]" }
+
+[[challenge.tests]]
+name = "5 + 7"
+setup = { input = { "1" = [5, 7] } }
+expect = { output = { "4" = [12] }, registers = { x = 2 } }
+
+[[challenge.tests]]
+name = "wraps"
+hidden = true
+setup = { input = { "1" = [0xF0, 0x20] } }
+expect = { output = { "4" = [0x10] } }
+"#;
+
+    fn program(source: &str) -> Solution {
+        Solution::assembled(&assemble(source).unwrap())
+    }
+
+    #[test]
+    fn test_check_passes_all_cases() {
+        let challenge = &parse(ADD_PORTS).unwrap()[0];
+        let code = program("SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL");
+        let report = challenge.check(&code);
+        assert!(report.passed(), "{}", report.summary);
+        assert_eq!(report.summary, "Well done\n\nAll 2 tests passed.");
+        assert_eq!(report.cases[0].cycles, 18);
+
+        // The app starts from the first visible case
+        let cpu = challenge.initial_cpu(&code.image).unwrap();
+        assert_eq!(cpu.registers[2], 0x0100);
+        assert_eq!(cpu.memory[..code.bytes], code.image[..]);
+    }
+
+    #[test]
+    fn test_check_failures_and_feedback() {
+        let challenge = &parse(ADD_PORTS).unwrap()[0];
+
+        // Writes the sum to the wrong port
+        let report = challenge.check(&program(
+            "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 5\nIDL",
+        ));
+        assert!(!report.passed());
+        assert_eq!(report.passed_count(), 0);
+        assert_eq!(
+            report.cases[0].message.as_deref(),
+            Some("Port 4 got [], wanted [0x0C] (5 + 7)")
+        );
+        assert_eq!(
+            report.cases[1].message.as_deref(),
+            Some("❌ Hidden test 2 failed.")
+        );
+        assert!(report.summary.ends_with("0 of 2 tests passed."));
+        let graded = challenge.grade(&program(
+            "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 5\nIDL",
+        ));
+        assert_eq!(
+            graded.cases[1].message.as_deref(),
+            Some("Port 4 got [], wanted [0x10] (wraps)")
+        );
+
+        let report = challenge.check(&program("LOOP: BR LOOP"));
+        assert!(
+            report.cases[0]
+                .message
+                .as_deref()
+                .unwrap()
+                .contains("within 200 cycles")
+        );
+
+        let report = challenge.check(&Solution::image(vec![0xC4; 25]));
+        assert!(report.cases.is_empty());
+        assert_eq!(
+            report.rejected.as_deref(),
+            Some("❌ Your program is 25 bytes; the limit is 24.")
+        );
+    }
+
+    #[test]
+    fn test_size_leaves_out_padding_and_runtime() {
+        let challenge = &parse(ADD_PORTS).unwrap()[0];
+
+        // A 73-byte image, of which 11 bytes are the program's
+        let padded = program(
+            "BR START\nORG 0x40\nSTART: SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL",
+        );
+        assert_eq!((padded.image.len(), padded.bytes), (0x49, 11));
+        let report = challenge.check(&padded);
+        assert!(report.passed(), "{}", report.summary);
+        assert_eq!(report.bytes, 11);
+
+        // SCRT's runtime is spliced in ahead of the program
+        let scrt = program(
+            "CALL SUM\nIDL\nSUM: SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nRETN",
+        );
+        assert!(scrt.image.len() > 24);
+        assert_eq!(challenge.check(&scrt).bytes, 13);
+    }
+}
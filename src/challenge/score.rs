@@ -0,0 +1,206 @@
+use super::{Challenge, Report, Solution};
+use crate::assembler::assemble;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Accepted solutions a leaderboard keeps
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// How small and how fast an accepted solution is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    /// Program size
+    pub bytes: usize,
+    /// Machine cycles, averaged over the test cases
+    pub cycles: f64,
+}
+
+impl Score {
+    /// The score of a report whose test cases all passed
+    pub fn of(report: &Report) -> Option<Self> {
+        if !report.passed() || report.cases.is_empty() {
+            return None;
+        }
+        let total: u64 = report.cases.iter().map(|c| c.cycles).sum();
+        Some(Self {
+            bytes: report.bytes,
+            cycles: total as f64 / report.cases.len() as f64,
+        })
+    }
+
+    /// This score next to `reference`, e.g. `12 bytes (reference 10, +2)`
+    pub fn compare(&self, reference: &Score) -> String {
+        let bytes = self.bytes as i64 - reference.bytes as i64;
+        let cycles = self.cycles - reference.cycles;
+        format!(
+            "{} bytes (reference {}, {:+}), {:.1} cycles (reference {:.1}, {:+.1})",
+            self.bytes, reference.bytes, bytes, self.cycles, reference.cycles, cycles
+        )
+    }
+
+    /// Smaller, with speed breaking ties
+    fn smaller_than(&self, other: &Score) -> bool {
+        (self.bytes, self.cycles) < (other.bytes, other.cycles)
+    }
+
+    /// Faster, with size breaking ties
+    fn faster_than(&self, other: &Score) -> bool {
+        (self.cycles, self.bytes) < (other.cycles, other.bytes)
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes, {:.1} cycles", self.bytes, self.cycles)
+    }
+}
+
+/// Personal bests on one challenge, and the best accepted solutions by size
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub smallest: Option<Score>,
+    pub fastest: Option<Score>,
+    /// Smallest first, at most [`LEADERBOARD_SIZE`]
+    pub entries: Vec<Score>,
+}
+
+/// Which personal bests a new score beat
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NewBest {
+    pub smallest: bool,
+    pub fastest: bool,
+}
+
+impl Leaderboard {
+    /// Add an accepted solution's score
+    pub fn insert(&mut self, score: Score) -> NewBest {
+        let new = NewBest {
+            smallest: self.smallest.is_none_or(|best| score.smaller_than(&best)),
+            fastest: self.fastest.is_none_or(|best| score.faster_than(&best)),
+        };
+        if new.smallest {
+            self.smallest = Some(score);
+        }
+        if new.fastest {
+            self.fastest = Some(score);
+        }
+        if !self.entries.contains(&score) {
+            let at = self.entries.partition_point(|e| !score.smaller_than(e));
+            self.entries.insert(at, score);
+            self.entries.truncate(LEADERBOARD_SIZE);
+        }
+        new
+    }
+}
+
+/// Lines on an accepted solution: its score, how it compares with the
+/// reference solution, and the personal bests after recording it
+pub fn describe(
+    score: &Score,
+    reference: Option<&Score>,
+    board: &Leaderboard,
+    new: NewBest,
+) -> String {
+    let mut lines = vec![format!("Score: {}", score)];
+    if let Some(reference) = reference {
+        lines.push(format!(
+            "Against the reference: {}",
+            score.compare(reference)
+        ));
+    }
+    match (new.smallest, new.fastest) {
+        (true, true) => lines.push("New personal best for size and speed!".to_string()),
+        (true, false) => lines.push("New personal best for size!".to_string()),
+        (false, true) => lines.push("New personal best for speed!".to_string()),
+        (false, false) => {}
+    }
+    if let (Some(smallest), Some(fastest)) = (board.smallest, board.fastest) {
+        lines.push(format!(
+            "Personal bests: smallest {}; fastest {}",
+            smallest, fastest
+        ));
+    }
+    lines.join("\n")
+}
+
+impl Challenge {
+    /// The score of the challenge's reference solution, when it has one
+    /// that passes
+    ///
+    /// The reference is trusted to match the reference model, so only the
+    /// test cases are run.
+    pub fn reference_score(&self) -> Option<Score> {
+        let source = self.reference.as_deref()?;
+        let output = assemble(source).ok()?;
+        Score::of(&self.test(&Solution::assembled(&output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(bytes: usize, cycles: f64) -> Score {
+        Score { bytes, cycles }
+    }
+
+    #[test]
+    fn test_leaderboard_keeps_personal_bests() {
+        let mut board = Leaderboard::default();
+        let first = board.insert(score(12, 30.0));
+        assert!(first.smallest && first.fastest);
+
+        let smaller = board.insert(score(10, 40.0));
+        assert_eq!(
+            smaller,
+            NewBest {
+                smallest: true,
+                fastest: false
+            }
+        );
+        let faster = board.insert(score(14, 20.0));
+        assert!(!faster.smallest && faster.fastest);
+        assert_eq!(board.insert(score(12, 30.0)), NewBest::default());
+
+        assert_eq!(board.smallest, Some(score(10, 40.0)));
+        assert_eq!(board.fastest, Some(score(14, 20.0)));
+        let sizes: Vec<usize> = board.entries.iter().map(|e| e.bytes).collect();
+        assert_eq!(sizes, vec![10, 12, 14]);
+
+        for bytes in 20..40 {
+            board.insert(score(bytes, 1.0));
+        }
+        assert_eq!(board.entries.len(), LEADERBOARD_SIZE);
+        assert_eq!(board.entries[0], score(10, 40.0));
+    }
+
+    #[test]
+    fn test_score_and_reference() {
+        let challenge = super::super::builtin()
+            .into_iter()
+            .find(|c| c.id == "add-two-inputs")
+            .unwrap();
+        let reference = challenge.reference_score().unwrap();
+        let output =
+            assemble("SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nNOP\nIDL").unwrap();
+        let mine = Score::of(&challenge.check(&Solution::assembled(&output))).unwrap();
+        assert_eq!(mine.bytes, reference.bytes + 1);
+        assert_eq!(
+            mine.compare(&reference),
+            format!(
+                "{} bytes (reference {}, +1), {:.1} cycles (reference {:.1}, +3.0)",
+                mine.bytes, reference.bytes, mine.cycles, reference.cycles
+            )
+        );
+        assert_eq!(
+            Score::of(&challenge.check(&Solution::image(vec![0x00]))),
+            None
+        );
+
+        let mut board = Leaderboard::default();
+        let new = board.insert(mine);
+        let text = describe(&mine, Some(&reference), &board, new);
+        assert!(text.starts_with(&format!("Score: {}\nAgainst the reference: ", mine)));
+        assert!(text.contains("New personal best for size and speed!"));
+    }
+}
@@ -0,0 +1,161 @@
+use super::score::{Leaderboard, NewBest, Score};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{path}: {source}")]
+    Format {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("browser storage: {0}")]
+    Browser(String),
+}
+
+/// Where leaderboards are kept: string values under string keys
+///
+/// In the browser this is `localStorage`; the command-line tool keeps a
+/// JSON file. A `BTreeMap` serves as storage that lasts as long as it does.
+pub trait ScoreStorage {
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&mut self, key: &str, value: &str) -> Result<(), StorageError>;
+}
+
+impl ScoreStorage for BTreeMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        BTreeMap::get(self, key).cloned()
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+fn key(challenge: &str) -> String {
+    format!("rca1802.scores.{}", challenge)
+}
+
+/// The leaderboard for `challenge`; empty if none was stored or it cannot
+/// be read
+pub fn leaderboard(storage: &dyn ScoreStorage, challenge: &str) -> Leaderboard {
+    storage
+        .get(&key(challenge))
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Add an accepted solution's score to `challenge`'s leaderboard and store
+/// it, returning the updated leaderboard and which bests it beat
+pub fn record(
+    storage: &mut dyn ScoreStorage,
+    challenge: &str,
+    score: Score,
+) -> Result<(Leaderboard, NewBest), StorageError> {
+    let mut board = leaderboard(storage, challenge);
+    let new = board.insert(score);
+    let json = serde_json::to_string(&board).expect("leaderboards serialize");
+    storage.set(&key(challenge), &json)?;
+    Ok((board, new))
+}
+
+/// Leaderboards in a JSON file of keys and values, rewritten on every
+/// change
+pub struct FileStorage {
+    path: std::path::PathBuf,
+    values: BTreeMap<String, String>,
+}
+
+impl FileStorage {
+    /// Open `path`, which need not exist yet
+    pub fn open(path: impl Into<std::path::PathBuf>) -> Result<Self, StorageError> {
+        let path = path.into();
+        let values = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|source| StorageError::Format {
+                path: path.display().to_string(),
+                source,
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(source) => {
+                return Err(StorageError::Io {
+                    path: path.display().to_string(),
+                    source,
+                });
+            }
+        };
+        Ok(Self { path, values })
+    }
+}
+
+impl ScoreStorage for FileStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.values.insert(key.to_string(), value.to_string());
+        let json = serde_json::to_string_pretty(&self.values).expect("strings serialize");
+        std::fs::write(&self.path, json + "\n").map_err(|source| StorageError::Io {
+            path: self.path.display().to_string(),
+            source,
+        })
+    }
+}
+
+/// The browser's `localStorage`
+#[cfg(target_arch = "wasm32")]
+pub struct BrowserStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl ScoreStorage for BrowserStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        use gloo::storage::{LocalStorage, Storage};
+        LocalStorage::raw().get_item(key).ok().flatten()
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
+        use gloo::storage::{LocalStorage, Storage};
+        LocalStorage::raw()
+            .set_item(key, value)
+            .map_err(|e| StorageError::Browser(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_in_memory_and_file() {
+        let mut memory = BTreeMap::new();
+        let score = Score {
+            bytes: 9,
+            cycles: 18.0,
+        };
+        let (board, new) = record(&mut memory, "add", score).unwrap();
+        assert!(new.smallest && new.fastest);
+        assert_eq!(board.entries, vec![score]);
+        assert_eq!(leaderboard(&memory, "add"), board);
+        assert_eq!(leaderboard(&memory, "other"), Leaderboard::default());
+
+        let path = std::env::temp_dir().join(format!("rca1802-scores-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut file = FileStorage::open(&path).unwrap();
+        record(&mut file, "add", score).unwrap();
+        let reopened = FileStorage::open(&path).unwrap();
+        assert_eq!(leaderboard(&reopened, "add").smallest, Some(score));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(matches!(
+            FileStorage::open(&path),
+            Err(StorageError::Format { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use super::runner::hex_list;
+use super::{Challenge, ChallengeError, Model, Setup, Slot, TestCase, Verify, model};
+use serde::{Deserialize, Serialize};
+
+/// Input bytes up to which grading tries every combination rather than
+/// random ones
+pub const EXHAUSTIVE_BYTES: usize = 2;
+
+/// Which inputs to try against the reference model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coverage {
+    /// The seeded random samples, or every input when there are fewer:
+    /// quick enough for each check a player makes
+    Sampled,
+    /// Every input, up to [`EXHAUSTIVE_BYTES`] of them, for grading
+    Exhaustive,
+}
+
+/// How a program did against a challenge's reference model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verification {
+    /// Inputs tried, up to and including the first one that failed
+    pub runs: u64,
+    /// Seed of the random inputs; none when every input was tried
+    pub seed: Option<u64>,
+    /// The smallest input found that the program gets wrong; with no runs,
+    /// why the model could not be run
+    pub counterexample: Option<Counterexample>,
+}
+
+/// An input a program gets wrong, and how
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counterexample {
+    pub input: Vec<u8>,
+    pub message: String,
+}
+
+impl Verification {
+    /// A line for the summary, after `total` test cases passed
+    pub fn describe(&self, total: usize) -> String {
+        let tests = match total {
+            1 => "The test passed".to_string(),
+            _ => format!("All {} tests passed", total),
+        };
+        match (&self.counterexample, self.seed) {
+            (Some(_), _) if self.runs == 0 => {
+                format!("{}, but the reference model could not be run.", tests)
+            }
+            (None, None) => format!(
+                "{} and all {} possible inputs match the reference model.",
+                tests, self.runs
+            ),
+            (None, Some(seed)) => format!(
+                "{} and {} random inputs (seed {}) match the reference model.",
+                tests, self.runs, seed
+            ),
+            (Some(_), None) => format!("{}, but not every possible input works.", tests),
+            (Some(_), Some(seed)) => format!(
+                "{}, but not every random input (seed {}) works.",
+                tests, seed
+            ),
+        }
+    }
+}
+
+impl Challenge {
+    /// Check `program` against the reference model on the inputs
+    /// `coverage` asks for, and shrink the first input it gets wrong; none
+    /// when the challenge has no model
+    ///
+    /// A model that [`model`] does not know, or that takes a different
+    /// number of input bytes, fails the check without running the program.
+    pub fn verify(&self, program: &[u8], coverage: Coverage) -> Option<Verification> {
+        let verify = self.verify.as_ref()?;
+        let mut verification = Verification {
+            runs: 0,
+            seed: None,
+            counterexample: None,
+        };
+        let width = verify.inputs.len();
+        let model = match model(&verify.model) {
+            Some(model) if model.inputs == width => Ok(model),
+            Some(model) => Err(ChallengeError::Inputs(model.name.to_string(), model.inputs)),
+            None => Err(ChallengeError::Model(verify.model.clone())),
+        };
+        let model = match model {
+            Ok(model) => model,
+            Err(error) => {
+                verification.counterexample = Some(Counterexample {
+                    input: Vec::new(),
+                    message: format!("❌ {}", error),
+                });
+                return Some(verification);
+            }
+        };
+        let fails = |input: &[u8]| self.try_input(program, verify, model, input);
+
+        let all = 1u64.checked_shl(8 * width as u32).unwrap_or(u64::MAX);
+        let exhaustive = match coverage {
+            Coverage::Sampled => all <= verify.samples,
+            Coverage::Exhaustive => width <= EXHAUSTIVE_BYTES,
+        };
+        let mut found = None;
+        if exhaustive {
+            // In order, so the first failure is already the smallest
+            for n in 0..all {
+                let input = n.to_be_bytes()[8 - width..].to_vec();
+                verification.runs += 1;
+                if let Some(message) = fails(&input) {
+                    found = Some((input, message));
+                    break;
+                }
+            }
+        } else {
+            verification.seed = Some(verify.seed);
+            let mut random = SplitMix(verify.seed);
+            for _ in 0..verify.samples {
+                let input: Vec<u8> = (0..width).map(|_| random.next() as u8).collect();
+                verification.runs += 1;
+                if let Some(message) = fails(&input) {
+                    found = Some((input, message));
+                    break;
+                }
+            }
+        }
+
+        verification.counterexample = found.map(|(input, message)| {
+            let (input, message) = shrink(input, message, fails);
+            Counterexample {
+                message: format!("{} (input [{}])", message, hex_list(&input)),
+                input,
+            }
+        });
+        Some(verification)
+    }
+
+    /// Why `program` gets `input` wrong, if it does
+    fn try_input(
+        &self,
+        program: &[u8],
+        verify: &Verify,
+        model: Model,
+        input: &[u8],
+    ) -> Option<String> {
+        let mut setup = Setup::default();
+        for (&slot, &byte) in verify.inputs.iter().zip(input) {
+            match slot {
+                Slot::Register(location) => {
+                    setup.registers.insert(location, byte as u16);
+                }
+                Slot::Memory(address) => {
+                    setup.memory.insert(address, vec![byte]);
+                }
+                Slot::Port(port) => setup.input.entry(port).or_default().push(byte),
+            }
+        }
+        let case = TestCase {
+            name: String::new(),
+            hidden: false,
+            setup,
+            expect: (model.expect)(input),
+        };
+        self.execute(program, &case).0
+    }
+}
+
+/// Lower the bytes of a failing `input` one at a time, to zero, by half or
+/// by one, for as long as it still fails
+fn shrink(
+    mut input: Vec<u8>,
+    mut message: String,
+    fails: impl Fn(&[u8]) -> Option<String>,
+) -> (Vec<u8>, String) {
+    loop {
+        let mut shrunk = false;
+        for i in 0..input.len() {
+            let byte = input[i];
+            for smaller in [0, byte / 2, byte.saturating_sub(1)] {
+                if smaller >= byte {
+                    continue;
+                }
+                let mut candidate = input.clone();
+                candidate[i] = smaller;
+                if let Some(failure) = fails(&candidate) {
+                    (input, message, shrunk) = (candidate, failure, true);
+                    break;
+                }
+            }
+        }
+        if !shrunk {
+            return (input, message);
+        }
+    }
+}
+
+/// The SplitMix64 generator: small, fast and the same everywhere for a
+/// given seed
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Solution, builtin};
+    use super::*;
+    use crate::assembler::assemble;
+
+    fn challenge(id: &str) -> Challenge {
+        builtin().into_iter().find(|c| c.id == id).unwrap()
+    }
+
+    fn program(source: &str) -> Solution {
+        Solution::assembled(&assemble(source).unwrap())
+    }
+
+    #[test]
+    fn test_exhaustive_finds_smallest_input() {
+        let challenge = challenge("add-two-inputs");
+        let right = challenge.grade(&program(
+            "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL",
+        ));
+        assert!(right.passed(), "{}", right.summary);
+        let verification = right.verification.unwrap();
+        assert_eq!((verification.runs, verification.seed), (65536, None));
+
+        // Right for every visible and hidden case, wrong once the second
+        // input reaches 0x80
+        let wrong =
+            program("SEX R2\nINP 1\nIRX\nINP 1\nSHL\nLBDF 0\nLDX\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL");
+        let checked = challenge.check(&wrong).verification.unwrap();
+        assert_eq!((checked.runs, checked.seed), (2, Some(1802)));
+        assert_eq!(checked.counterexample.unwrap().input, vec![0x00, 0x80]);
+
+        let wrong = challenge.grade(&wrong);
+        assert!(!wrong.passed());
+        assert_eq!(wrong.passed_count(), wrong.cases.len());
+        let counterexample = wrong.verification.unwrap().counterexample.unwrap();
+        assert_eq!(counterexample.input, vec![0x00, 0x80]);
+        assert!(
+            counterexample.message.ends_with("(input [0x00, 0x80])"),
+            "{}",
+            counterexample.message
+        );
+        assert!(
+            wrong
+                .summary
+                .ends_with("All 3 tests passed, but not every possible input works."),
+            "{}",
+            wrong.summary
+        );
+    }
+
+    #[test]
+    fn test_random_inputs_shrink() {
+        let challenge = challenge("add-words");
+        let reference = program(challenge.reference.as_deref().unwrap());
+        let right = challenge.check(&reference).verification.unwrap();
+        assert_eq!(right.counterexample, None);
+        assert_eq!((right.runs, right.seed), (1000, Some(1802)));
+
+        // Drops the carry from the low bytes
+        let source = challenge
+            .reference
+            .as_deref()
+            .unwrap()
+            .replace("ADC", "ADD");
+        let wrong = challenge.check(&program(&source));
+        assert_eq!(wrong.passed_count(), wrong.cases.len());
+        let counterexample = wrong.verification.unwrap().counterexample.unwrap();
+        let input = counterexample.input;
+        assert_eq!((input[0], input[2]), (0, 0), "{:?}", input);
+        assert_eq!(input[1] as u16 + input[3] as u16, 0x100, "{:?}", input);
+
+        // The same seed gives the same inputs
+        let again = challenge
+            .verify(&program(&source).image, Coverage::Sampled)
+            .unwrap();
+        assert_eq!(again.counterexample.unwrap().input, input);
+    }
+
+    #[test]
+    fn test_mismatched_model_fails_without_panicking() {
+        // Parsing rejects unknown models, but a challenge built in code
+        // is not parsed
+        let mut challenge = challenge("add-two-inputs");
+        challenge.verify.as_mut().unwrap().model = "subtract-bytes".to_string();
+        let report = challenge.check(&program(
+            "SEX R2\nINP 1\nIRX\nINP 1\nDEC R2\nADD\nSTR R2\nOUT 4\nIDL",
+        ));
+        assert!(!report.passed());
+        let verification = report.verification.unwrap();
+        assert_eq!(verification.runs, 0);
+        assert_eq!(
+            verification.counterexample.unwrap().message,
+            "❌ unknown reference model `subtract-bytes`"
+        );
+        assert!(
+            report
+                .summary
+                .ends_with("All 3 tests passed, but the reference model could not be run."),
+            "{}",
+            report.summary
+        );
+
+        // A model taking four bytes given two
+        challenge.verify.as_mut().unwrap().model = "add-words".to_string();
+        let verification = challenge.verify(&[0x00], Coverage::Exhaustive).unwrap();
+        assert_eq!(
+            verification.counterexample.unwrap().message,
+            "❌ reference model `add-words` takes 4 input bytes"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use super::CliError;
+
+/// Options that take a value
+const VALUE_OPTIONS: &[&str] = &[
+    "-o",
+    "-f",
+    "-D",
+    "--base",
+    "--entry",
+    "--max-cycles",
+    "--exit-code",
+    "--script",
+    "--port",
+    "--challenges",
+    "--challenge",
+    "--scores",
+];
+
+/// Options that stand alone
+const FLAGS: &[&str] = &["--relax", "--a18", "--no-scrt", "--dump"];
+
+/// Parsed command line: the subcommand, its files and its options
+pub(super) struct Args {
+    pub command: String,
+    pub files: Vec<String>,
+    values: Vec<(&'static str, String)>,
+    flags: Vec<&'static str>,
+}
+
+impl Args {
+    pub fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut args = args.iter();
+        let command = args
+            .next()
+            .ok_or_else(|| CliError::Usage("missing command".to_string()))?
+            .clone();
+        let mut parsed = Self {
+            command,
+            files: Vec::new(),
+            values: Vec::new(),
+            flags: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            if let Some(&option) = VALUE_OPTIONS.iter().find(|&&o| o == name) {
+                let value = match inline {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or_else(|| CliError::Usage(format!("{} needs a value", option)))?
+                        .clone(),
+                };
+                parsed.values.push((option, value));
+            } else if let Some(&flag) = FLAGS.iter().find(|&&f| f == name) {
+                parsed.flags.push(flag);
+            } else if let Some(define) = arg.strip_prefix("-D").filter(|d| !d.is_empty()) {
+                parsed.values.push(("-D", define.to_string()));
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                return Err(CliError::Usage(format!("unknown option {}", arg)));
+            } else {
+                parsed.files.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The one input file
+    pub fn file(&self) -> Result<&str, CliError> {
+        match self.files.as_slice() {
+            [file] => Ok(file),
+            [] => Err(CliError::Usage(format!("{} needs a FILE", self.command))),
+            _ => Err(CliError::Usage(format!(
+                "{} takes one FILE, got {}",
+                self.command,
+                self.files.len()
+            ))),
+        }
+    }
+
+    /// Every value given for `option`, in order
+    pub fn all(&self, option: &str) -> impl Iterator<Item = &str> {
+        self.values
+            .iter()
+            .filter(move |(name, _)| *name == option)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The last value given for `option`
+    pub fn value(&self, option: &str) -> Option<&str> {
+        self.all(option).last()
+    }
+
+    pub fn flag(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// The last value of `option` as a number, or `default`
+    pub fn number(&self, option: &str, default: u64) -> Result<u64, CliError> {
+        self.value(option)
+            .map_or(Ok(default), |text| parse_number(option, text))
+    }
+
+    /// The last value of `option` as a 16-bit address
+    pub fn address(&self, option: &str) -> Result<Option<u16>, CliError> {
+        self.value(option)
+            .map(|text| address(option, text))
+            .transpose()
+    }
+}
+
+/// A number in decimal, `0x`/`$` hex or `0b` binary
+pub(super) fn parse_number(option: &str, text: &str) -> Result<u64, CliError> {
+    let (digits, radix) = if let Some(hex) = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+    {
+        (hex, 16)
+    } else if let Some(binary) = text.strip_prefix("0b") {
+        (binary, 2)
+    } else {
+        (text, 10)
+    };
+    let digits = digits.replace('_', "");
+    u64::from_str_radix(&digits, radix)
+        .map_err(|_| CliError::Usage(format!("{}: invalid number {}", option, text)))
+}
+
+pub(super) fn address(option: &str, text: &str) -> Result<u16, CliError> {
+    let value = parse_number(option, text)?;
+    u16::try_from(value)
+        .map_err(|_| CliError::Usage(format!("{}: address {} is out of range", option, text)))
+}
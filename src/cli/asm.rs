@@ -0,0 +1,106 @@
+use super::{Args, CliError, extension, load};
+use crate::assembler::render_listing;
+use crate::disassembler;
+use crate::formats::{to_binary, to_ihex, to_srec};
+use std::io::Write;
+use std::path::Path;
+
+/// Output formats for `asm`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Binary,
+    Hex,
+    Srec,
+    Listing,
+}
+
+impl OutputFormat {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bin" | "rom" => Some(Self::Binary),
+            "hex" | "ihx" => Some(Self::Hex),
+            "srec" | "s19" | "s28" | "s37" | "mot" => Some(Self::Srec),
+            "listing" | "lst" => Some(Self::Listing),
+            _ => None,
+        }
+    }
+}
+
+/// `asm FILE [-o OUT] [-f FORMAT]`: assemble to an image or listing
+///
+/// The format is `-f`, else the extension of `-o`, else Intel HEX. With
+/// no `-o` the output goes to standard output.
+pub(super) fn assemble(
+    args: &Args,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<u8, CliError> {
+    let path = args.file()?;
+    let target = args.value("-o");
+    let format = match (args.value("-f"), target) {
+        (Some(name), _) => OutputFormat::parse(name)
+            .ok_or_else(|| CliError::Usage(format!("unknown format {}", name)))?,
+        (None, Some(target)) => {
+            OutputFormat::parse(&extension(target)).unwrap_or(OutputFormat::Hex)
+        }
+        (None, None) => OutputFormat::Hex,
+    };
+
+    let program = load(args, path, err)?;
+    let (Some(source), Some(output)) = (&program.source, &program.output) else {
+        return Err(CliError::Usage(format!("{} is not assembly source", path)));
+    };
+    let format_error = |source| CliError::Format {
+        path: path.to_string(),
+        source,
+    };
+    let bytes = match format {
+        OutputFormat::Binary => {
+            let base = args.address("--base")?.unwrap_or(0);
+            to_binary(&program.segments, base).map_err(format_error)?
+        }
+        OutputFormat::Hex => to_ihex(&program.segments).into_bytes(),
+        OutputFormat::Srec => {
+            let header = Path::new(path)
+                .file_stem()
+                .map_or(String::new(), |s| s.to_string_lossy().into_owned());
+            let entry = args.address("--entry")?.unwrap_or(0);
+            to_srec(&program.segments, &header, entry).into_bytes()
+        }
+        OutputFormat::Listing => render_listing(source, output).into_bytes(),
+    };
+
+    match target {
+        Some(target) => std::fs::write(target, bytes).map_err(|source| CliError::Io {
+            path: target.to_string(),
+            source,
+        })?,
+        None => out.write_all(&bytes)?,
+    }
+    Ok(0)
+}
+
+/// `disasm FILE [--entry ADDR]...`: print reassemblable source
+///
+/// Code is traced from every `--entry`, or from the lowest loaded address.
+pub(super) fn disassemble(
+    args: &Args,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<u8, CliError> {
+    let path = args.file()?;
+    let program = load(args, path, err)?;
+    let mut entries = args
+        .all("--entry")
+        .map(|text| super::args::address("--entry", text))
+        .collect::<Result<Vec<u16>, CliError>>()?;
+    if entries.is_empty() {
+        entries.extend(program.segments.iter().map(|s| s.address).min());
+    }
+    write!(
+        out,
+        "{}",
+        disassembler::disassemble(&program.segments, &entries).source()
+    )?;
+    Ok(0)
+}
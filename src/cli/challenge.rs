@@ -0,0 +1,90 @@
+use super::{Args, CliError, EXIT_ERROR, Program, load, read};
+use crate::challenge::{
+    self, Challenge, FileStorage, Score, Solution, describe, leaderboard, record,
+};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Score file in the home directory when `--scores` is not given
+const SCORES_FILE: &str = ".rca1802-scores.json";
+
+/// `challenge` lists the challenges; `challenge ID FILE` checks FILE
+/// against one, scores an accepted solution and records personal bests
+pub(super) fn challenge(
+    args: &Args,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<u8, CliError> {
+    let challenges = challenges(args)?;
+    let (id, path) = match args.files.as_slice() {
+        [] => {
+            let storage = FileStorage::open(scores_path(args))?;
+            for challenge in &challenges {
+                write!(out, "{:<20} {}", challenge.id, challenge.title)?;
+                match leaderboard(&storage, &challenge.id).smallest {
+                    Some(best) => writeln!(out, "  (best: {})", best)?,
+                    None => writeln!(out)?,
+                }
+            }
+            return Ok(0);
+        }
+        [id, path] => (id, path),
+        _ => {
+            return Err(CliError::Usage(
+                "challenge takes an ID and a FILE".to_string(),
+            ));
+        }
+    };
+    let challenge = challenges
+        .iter()
+        .find(|c| &c.id == id)
+        .ok_or_else(|| CliError::Usage(format!("no challenge {}", id)))?;
+
+    let program = load(args, path, err)?;
+    let mut storage = FileStorage::open(scores_path(args))?;
+    let report = challenge.check(&solution(&program));
+    writeln!(out, "{}", report.summary)?;
+    let Some(score) = Score::of(&report) else {
+        return Ok(EXIT_ERROR);
+    };
+    let (board, new) = record(&mut storage, &challenge.id, score)?;
+    let reference = challenge.reference_score();
+    writeln!(out, "{}", describe(&score, reference.as_ref(), &board, new))?;
+    Ok(0)
+}
+
+/// The built-in challenges, or those in `--challenges`
+pub(super) fn challenges(args: &Args) -> Result<Vec<Challenge>, CliError> {
+    match args.value("--challenges") {
+        None => Ok(challenge::builtin()),
+        Some(path) => challenge::parse(&read(path)?).map_err(|source| CliError::Challenge {
+            path: path.to_string(),
+            source,
+        }),
+    }
+}
+
+fn scores_path(args: &Args) -> PathBuf {
+    match args.value("--scores") {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .map_or_else(PathBuf::new, PathBuf::from)
+            .join(SCORES_FILE),
+    }
+}
+
+/// The program as one block of memory from address 0, sized by the bytes
+/// its segments hold
+fn solution(program: &Program) -> Solution {
+    if let Some(output) = &program.output {
+        return Solution::assembled(output);
+    }
+    let end = program.segments.iter().map(|s| s.end()).max().unwrap_or(0);
+    let mut image = vec![0; end as usize];
+    for segment in &program.segments {
+        let start = segment.address as usize;
+        image[start..start + segment.data.len()].copy_from_slice(&segment.data);
+    }
+    let bytes = program.segments.iter().map(|s| s.data.len()).sum();
+    Solution { image, bytes }
+}
@@ -0,0 +1,28 @@
+use super::{Args, CliError, load, machine};
+use std::io::Write;
+use std::net::TcpListener;
+
+/// Port `gdb` listens on when `--port` is not given
+const DEFAULT_PORT: u64 = 1234;
+
+/// `gdb FILE [--port N]`: wait for one debugger on 127.0.0.1 and serve the
+/// program to it
+pub(super) fn gdb(args: &Args, err: &mut dyn Write) -> Result<u8, CliError> {
+    let path = args.file()?;
+    let port = u16::try_from(args.number("--port", DEFAULT_PORT)?)
+        .map_err(|_| CliError::Usage("--port must be below 65536".to_string()))?;
+    let program = load(args, path, err)?;
+
+    let cpu = machine(args, path, &program)?;
+
+    let address = format!("127.0.0.1:{}", port);
+    let listener = TcpListener::bind(&address).map_err(|source| CliError::Io {
+        path: address.clone(),
+        source,
+    })?;
+    writeln!(err, "Waiting for GDB: target remote {}", address)?;
+    let (stream, peer) = listener.accept()?;
+    writeln!(err, "Debugger connected from {}", peer)?;
+    crate::gdb::serve(stream, cpu)?;
+    Ok(0)
+}
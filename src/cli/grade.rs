@@ -0,0 +1,75 @@
+use super::challenge::challenges;
+use super::{Args, CliError, assemble_options};
+use crate::challenge::{Challenge, grade_directory, junit};
+use std::io::Write;
+use std::path::Path;
+
+/// Results directory when `-o` is not given
+const RESULTS_DIR: &str = "grades";
+
+/// `grade DIR` grades every submission in DIR against one challenge,
+/// writing `STUDENT.json` for each and `junit.xml` to the results directory
+pub(super) fn grade(args: &Args, out: &mut dyn Write) -> Result<u8, CliError> {
+    let dir = args.file()?;
+    let mut challenge = select(args)?;
+    if args.value("--max-cycles").is_some() {
+        challenge.max_cycles = args.number("--max-cycles", challenge.max_cycles)?;
+    }
+
+    let submissions = grade_directory(&challenge, Path::new(dir), &assemble_options(args))
+        .map_err(io_error(Path::new(dir)))?;
+    let results = Path::new(args.value("-o").unwrap_or(RESULTS_DIR));
+    std::fs::create_dir_all(results).map_err(io_error(results))?;
+    for submission in &submissions {
+        let json = serde_json::to_string_pretty(submission).expect("submissions serialize");
+        let path = results.join(format!("{}.json", submission.student));
+        std::fs::write(&path, json + "\n").map_err(io_error(&path))?;
+
+        let passed = submission.report.as_ref().map_or(0, |r| r.passed_count());
+        let outcome = match (&submission.score, &submission.first_failure) {
+            (Some(score), _) => score.to_string(),
+            (None, Some(failure)) => failure.clone(),
+            (None, None) => String::new(),
+        };
+        writeln!(
+            out,
+            "{:<20} {}/{}  {}",
+            submission.student,
+            passed,
+            challenge.tests.len(),
+            outcome
+        )?;
+    }
+    let path = results.join("junit.xml");
+    std::fs::write(&path, junit(&challenge, &submissions)).map_err(io_error(&path))?;
+
+    let passed = submissions.iter().filter(|s| s.passed).count();
+    writeln!(
+        out,
+        "{} of {} submissions passed; results in {}",
+        passed,
+        submissions.len(),
+        results.display()
+    )?;
+    Ok(0)
+}
+
+/// The challenge named by `--challenge`, or the only one in `--challenges`
+fn select(args: &Args) -> Result<Challenge, CliError> {
+    let mut challenges = challenges(args)?;
+    match args.value("--challenge") {
+        Some(id) => challenges
+            .into_iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| CliError::Usage(format!("no challenge {}", id))),
+        None if challenges.len() == 1 => Ok(challenges.remove(0)),
+        None => Err(CliError::Usage("grade needs --challenge ID".to_string())),
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> CliError + '_ {
+    move |source| CliError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
@@ -0,0 +1,136 @@
+use super::CliError;
+use super::args::Args;
+use crate::assembler::{
+    AssembleOptions, AssemblyOutput, Dialect, FileSystem, SourceMap, assemble_file, parse_define,
+    render_file_diagnostics,
+};
+use crate::cpu::Cpu;
+use crate::formats::{Segment, from_binary, from_ihex, from_srec};
+use std::io::Write;
+use std::path::Path;
+
+/// A program read from source or an image
+pub(super) struct Program {
+    /// Source text, when the program was assembled
+    pub source: Option<String>,
+    pub output: Option<AssemblyOutput>,
+    pub segments: Vec<Segment>,
+}
+
+impl Program {
+    /// Where the program's bytes came from, when it was assembled
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.output.as_ref().map(|o| &o.source_map)
+    }
+}
+
+/// Read `path` as an image or, for any other extension, assemble it,
+/// printing assembler warnings to `err`
+pub(super) fn load(args: &Args, path: &str, err: &mut dyn Write) -> Result<Program, CliError> {
+    let format_error = |source| CliError::Format {
+        path: path.to_string(),
+        source,
+    };
+    let image = |segments: Vec<Segment>| Program {
+        source: None,
+        output: None,
+        segments,
+    };
+    match extension(path).as_str() {
+        "hex" | "ihx" => Ok(image(from_ihex(&read(path)?).map_err(format_error)?)),
+        "srec" | "s19" | "s28" | "s37" | "mot" => {
+            Ok(image(from_srec(&read(path)?).map_err(format_error)?))
+        }
+        "bin" | "rom" => {
+            let bytes = std::fs::read(path).map_err(|source| CliError::Io {
+                path: path.to_string(),
+                source,
+            })?;
+            let base = args.address("--base")?.unwrap_or(0);
+            Ok(image(from_binary(&bytes, base).map_err(format_error)?))
+        }
+        _ => assemble_source(args, path, err),
+    }
+}
+
+/// A CPU with `program` loaded from `path` and the PC at `--entry`
+/// (default 0)
+pub(super) fn machine(args: &Args, path: &str, program: &Program) -> Result<Cpu, CliError> {
+    let mut cpu = Cpu::new();
+    cpu.load_segments(&program.segments)
+        .map_err(|source| CliError::Format {
+            path: path.to_string(),
+            source,
+        })?;
+    cpu.set_pc(args.address("--entry")?.unwrap_or(0));
+    Ok(cpu)
+}
+
+/// Assembler options from `--relax`, `--no-scrt`, `--a18` and `-D`
+pub(super) fn assemble_options(args: &Args) -> AssembleOptions {
+    let mut options = AssembleOptions {
+        relax_branches: args.flag("--relax"),
+        scrt: !args.flag("--no-scrt"),
+        ..Default::default()
+    };
+    if args.flag("--a18") {
+        options.dialect = Dialect::A18;
+    }
+    for define in args.all("-D") {
+        let (name, value) = parse_define(define);
+        options.defines.insert(name, value);
+    }
+    options
+}
+
+pub(super) fn assemble_source(
+    args: &Args,
+    path: &str,
+    err: &mut dyn Write,
+) -> Result<Program, CliError> {
+    let options = assemble_options(args);
+    let file = Path::new(path);
+    let root = file.parent().unwrap_or(Path::new(""));
+    let name = file
+        .file_name()
+        .map_or(path.to_string(), |n| n.to_string_lossy().into_owned());
+    let provider = FileSystem::new(root);
+    let source = read(path)?;
+    match assemble_file(&name, &provider, &options) {
+        Ok(output) => {
+            if !output.diagnostics.is_empty() {
+                let _ = writeln!(
+                    err,
+                    "{}",
+                    render_file_diagnostics(&output.diagnostics, &provider)
+                );
+            }
+            Ok(Program {
+                segments: output.segments(),
+                source: Some(source),
+                output: Some(output),
+            })
+        }
+        Err(error) if error.diagnostics().is_empty() => {
+            Err(CliError::Assembly(format!("error: {}: {}", name, error)))
+        }
+        Err(error) => Err(CliError::Assembly(render_file_diagnostics(
+            error.diagnostics(),
+            &provider,
+        ))),
+    }
+}
+
+pub(super) fn read(path: &str) -> Result<String, CliError> {
+    std::fs::read_to_string(path).map_err(|source| CliError::Io {
+        path: path.to_string(),
+        source,
+    })
+}
+
+/// Lower-case extension of `path`, empty if it has none
+pub(super) fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map_or(String::new(), |e| e.to_string_lossy().to_lowercase())
+}
@@ -0,0 +1,372 @@
+//! The `rca1802` command-line tool
+//!
+//! `asm` assembles to binary, Intel HEX, S-record or a listing, `run`
+//! executes a program and reports through its exit status, `disasm`
+//! recovers source from an image, `trace` logs every instruction as it
+//! runs, `monitor` opens the [`crate::monitor`] on a program, `gdb`
+//! serves it to a debugger, `challenge` grades it against one of the
+//! [`crate::challenge`]s and `grade` does so for a directory of them.
+//! [`main`] takes the arguments and output streams and returns the exit
+//! status, so the whole tool runs in-process under test.
+
+mod args;
+mod asm;
+mod challenge;
+mod gdb;
+mod grade;
+mod load;
+mod monitor;
+mod run;
+
+use crate::challenge::{ChallengeError, StorageError};
+use crate::cpu::CpuError;
+use crate::formats::FormatError;
+use args::Args;
+use load::{Program, assemble_options, extension, load, machine, read};
+use std::io::{self, Write};
+use thiserror::Error;
+
+/// Exit status for errors other than the program's own result
+pub const EXIT_ERROR: u8 = 1;
+/// Exit status for bad command-line arguments
+pub const EXIT_USAGE: u8 = 2;
+/// Exit status when `run` or `trace` reaches the cycle limit
+pub const EXIT_CYCLE_LIMIT: u8 = 124;
+
+const USAGE: &str = "\
+Usage: rca1802 <command> FILE [options]
+
+Commands:
+  asm FILE       Assemble a source file
+  run FILE       Run a program until IDL or the cycle limit
+  disasm FILE    Disassemble a program image
+  trace FILE     Run a program, printing each instruction as it executes
+  monitor [FILE] Machine-language monitor on the program (? for commands)
+  gdb FILE       Serve the program to one GDB remote-protocol connection
+  challenge [ID FILE]
+                 List the challenges, or check FILE against one and score it
+  grade DIR      Grade every .asm file in DIR against a challenge, writing
+                 JSON per student and junit.xml to -o (default grades)
+
+FILE is assembly source, or an image: .hex/.ihx (Intel HEX),
+.srec/.s19 (S-record) or .bin (raw binary at --base).
+
+Options:
+  -o FILE            Write the output here (asm); the extension picks the format
+                     Directory for the results (grade)
+  -f FORMAT          Output format for asm: bin, hex, srec or listing
+  -D NAME[=VALUE]    Define a symbol for conditional assembly and, if numeric,
+                     for operands such as ORG BASE or OUT PORT
+  --relax            Widen short branches that leave their page
+  --a18              Read A18/asmx syntax
+  --no-scrt          Do not include the SCRT runtime for CALL/RETN
+  --base ADDR        Load address of a raw binary (default 0)
+  --entry ADDR       Start address (run/trace); code entry point (disasm, repeatable)
+  --max-cycles N     Machine cycles before giving up (default 10000000)
+  --script FILE      Read monitor commands from FILE and print a transcript
+  --port N           TCP port gdb listens on at 127.0.0.1 (default 1234)
+  --challenges FILE  Challenges from a TOML file instead of the built-in ones
+  --challenge ID     Challenge to grade against (grade; optional when
+                     --challenges holds only one)
+  --scores FILE      Personal bests file (default ~/.rca1802-scores.json)
+  --dump             Print the registers when the program stops
+  --exit-code REG    Exit with the low byte of REG (R0-RF or D)
+
+Exit status: 0, or the chosen register, when the program reaches IDL;
+1 on errors or a failed challenge; 2 on bad arguments; 124 at the cycle
+limit.
+";
+
+/// Errors that stop a command
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("{0}")]
+    Usage(String),
+
+    #[error("{path}: {source}")]
+    Io { path: String, source: io::Error },
+
+    #[error("{path}: {source}")]
+    Format { path: String, source: FormatError },
+
+    /// Assembly failed; holds the rendered diagnostics
+    #[error("{0}")]
+    Assembly(String),
+
+    #[error("writing output: {0}")]
+    Output(#[from] io::Error),
+
+    #[error(transparent)]
+    Cpu(#[from] CpuError),
+
+    #[error("{path}: {source}")]
+    Challenge {
+        path: String,
+        source: ChallengeError,
+    },
+
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+/// Run the tool with `args` (without the program name), returning the exit
+/// status
+pub fn main(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> u8 {
+    if args.is_empty() || matches!(args[0].as_str(), "-h" | "--help" | "help") {
+        let _ = write!(out, "{}", USAGE);
+        return if args.is_empty() { EXIT_USAGE } else { 0 };
+    }
+    let result = Args::parse(args).and_then(|args| match args.command.as_str() {
+        "asm" => asm::assemble(&args, out, err),
+        "disasm" => asm::disassemble(&args, out, err),
+        "run" => run::run(&args, out, err, false),
+        "trace" => run::run(&args, out, err, true),
+        "monitor" => monitor::monitor(&args, out, err),
+        "gdb" => gdb::gdb(&args, err),
+        "challenge" => challenge::challenge(&args, out, err),
+        "grade" => grade::grade(&args, out),
+        other => Err(CliError::Usage(format!("unknown command {}", other))),
+    });
+    match result {
+        Ok(status) => status,
+        Err(CliError::Usage(message)) => {
+            let _ = writeln!(err, "error: {}\nRun `rca1802 --help` for usage", message);
+            EXIT_USAGE
+        }
+        Err(CliError::Assembly(diagnostics)) => {
+            let _ = writeln!(err, "{}", diagnostics);
+            EXIT_ERROR
+        }
+        Err(error) => {
+            let _ = writeln!(err, "error: {}", error);
+            EXIT_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "START:  LDI 0x03\n        PLO R3\nLOOP:   DEC R3\n        GLO R3\n        BNZ LOOP\n        LDI 0x2A\n        PLO R4\n        IDL\n";
+
+    const SESSION: &str = "b loop\ng\nexpect r3 3\nc loop\ng\nexpect r4 2A\n";
+
+    /// The scratch directory `DIR` stands for in a test's arguments
+    fn scratch(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rca1802-cli-{}-{}", name, std::process::id()))
+    }
+
+    /// Remove the scratch directories `names`
+    fn clean(names: &[&str]) {
+        for name in names {
+            std::fs::remove_dir_all(scratch(name)).unwrap();
+        }
+    }
+
+    /// Run the tool in a scratch directory holding `prog.asm` and
+    /// `session.mon`, returning the status, standard output and standard
+    /// error
+    fn rca1802(name: &str, args: &[&str]) -> (u8, String, String) {
+        let dir = scratch(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("prog.asm"), PROGRAM).unwrap();
+        std::fs::write(dir.join("session.mon"), SESSION).unwrap();
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| a.replace("DIR", &dir.to_string_lossy()))
+            .collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let status = main(&args, &mut out, &mut err);
+        (
+            status,
+            String::from_utf8_lossy(&out).into_owned(),
+            String::from_utf8_lossy(&err).into_owned(),
+        )
+    }
+
+    #[test]
+    fn test_asm_and_run_image() {
+        let (status, _, _) = rca1802("image", &["asm", "DIR/prog.asm", "-o", "DIR/prog.hex"]);
+        assert_eq!(status, 0);
+        let (status, out, _) = rca1802(
+            "image",
+            &["run", "DIR/prog.hex", "--exit-code", "R4", "--dump"],
+        );
+        assert_eq!(status, 0x2A);
+        assert!(out.contains("R4=002A"), "{}", out);
+        assert!(out.contains("cycles=28 instructions=14"), "{}", out);
+
+        let (status, _, _) = rca1802("image", &["asm", "DIR/prog.asm", "-o", "DIR/prog.bin"]);
+        assert_eq!(status, 0);
+        let (_, out, _) = rca1802("image", &["disasm", "DIR/prog.bin"]);
+        assert!(out.contains("BNZ L0003"), "{}", out);
+        clean(&["image"]);
+    }
+
+    #[test]
+    fn test_trace_and_cycle_limit() {
+        let (status, out, _) = rca1802("trace", &["trace", "DIR/prog.asm", "--exit-code=D"]);
+        assert_eq!(status, 0x2A);
+        let first = out.lines().next().unwrap();
+        assert!(first.starts_with("0000  F8 03     LDI 03"), "{}", first);
+        assert!(
+            first.contains("D=03") && first.ends_with("prog.asm:1"),
+            "{}",
+            first
+        );
+
+        let (status, _, err) = rca1802("limit", &["run", "DIR/prog.asm", "--max-cycles", "6"]);
+        assert_eq!(status, EXIT_CYCLE_LIMIT);
+        assert!(err.contains("cycle limit of 6"), "{}", err);
+        clean(&["trace", "limit"]);
+    }
+
+    #[test]
+    fn test_monitor_script() {
+        let (status, out, _) = rca1802(
+            "monitor",
+            &["monitor", "DIR/prog.asm", "--script", "DIR/session.mon"],
+        );
+        assert_eq!(status, 0, "{}", out);
+        assert!(out.contains("> g\nBreakpoint at 0003\n"), "{}", out);
+
+        std::fs::write(scratch("monitor").join("fail.mon"), "g\nexpect r4 0\n").unwrap();
+        let (status, out, _) = rca1802(
+            "monitor",
+            &["monitor", "DIR/prog.asm", "--script", "DIR/fail.mon"],
+        );
+        assert_eq!(status, EXIT_ERROR);
+        assert!(
+            out.contains("error: Expectation failed: R4 = 002A, expected 0"),
+            "{}",
+            out
+        );
+        clean(&["monitor"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(rca1802("usage", &["run"]).0, EXIT_USAGE);
+        assert_eq!(
+            rca1802("usage", &["run", "DIR/prog.asm", "--bogus"]).0,
+            EXIT_USAGE
+        );
+        assert_eq!(
+            rca1802("usage", &["run", "DIR/prog.asm", "--exit-code", "RX"]).0,
+            EXIT_USAGE
+        );
+        let (status, _, err) = rca1802("usage", &["asm", "DIR/missing.asm"]);
+        assert_eq!(status, EXIT_ERROR);
+        assert!(err.contains("missing.asm"), "{}", err);
+        clean(&["usage"]);
+    }
+
+    #[test]
+    fn test_challenge_scores() {
+        let (status, out, _) = rca1802("challenge", &["challenge", "--scores", "DIR/scores.json"]);
+        assert_eq!(status, 0);
+        assert!(
+            out.contains("load-a-value         Challenge 1: Load a Value\n"),
+            "{}",
+            out
+        );
+
+        let dir = scratch("challenge");
+        std::fs::write(dir.join("answer.asm"), "LDI 0x2A\nPHI R1\nNOP\nIDL\n").unwrap();
+        let _ = std::fs::remove_file(dir.join("scores.json"));
+        let check = [
+            "challenge",
+            "load-a-value",
+            "DIR/answer.asm",
+            "--scores",
+            "DIR/scores.json",
+        ];
+        let (status, out, _) = rca1802("challenge", &check);
+        assert_eq!(status, 0, "{}", out);
+        assert!(out.contains("Score: 5 bytes, 9.0 cycles"), "{}", out);
+        assert!(out.contains("5 bytes (reference 4, +1)"), "{}", out);
+        assert!(
+            out.contains("New personal best for size and speed!"),
+            "{}",
+            out
+        );
+        let (_, out, _) = rca1802("challenge", &check);
+        assert!(!out.contains("New personal best"), "{}", out);
+        let (_, out, _) = rca1802("challenge", &["challenge", "--scores", "DIR/scores.json"]);
+        assert!(out.contains("(best: 5 bytes, 9.0 cycles)"), "{}", out);
+
+        let (status, out, _) = rca1802(
+            "challenge",
+            &[
+                "challenge",
+                "load-a-value",
+                "DIR/prog.asm",
+                "--scores",
+                "DIR/scores.json",
+            ],
+        );
+        assert_eq!(status, EXIT_ERROR);
+        assert!(out.contains("R1.high = 0x00 (expected 0x2A)"), "{}", out);
+        assert_eq!(
+            rca1802("challenge", &["challenge", "nope", "DIR/prog.asm"]).0,
+            EXIT_USAGE
+        );
+        clean(&["challenge"]);
+    }
+
+    #[test]
+    fn test_grade_directory() {
+        let dir = scratch("grade");
+        let submissions = dir.join("submissions");
+        std::fs::create_dir_all(&submissions).unwrap();
+        std::fs::write(submissions.join("ana.asm"), "LDI 0x2A\nPHI R1\nIDL\n").unwrap();
+        std::fs::write(submissions.join("ben.asm"), "LDI 0x2B\nPHI R1\nIDL\n").unwrap();
+        std::fs::write(submissions.join("cy.asm"), "LDI\n").unwrap();
+        std::fs::write(submissions.join("notes.txt"), "not a submission").unwrap();
+
+        let grade = [
+            "grade",
+            "DIR/submissions",
+            "--challenge",
+            "load-a-value",
+            "-o",
+            "DIR/results",
+        ];
+        let (status, out, err) = rca1802("grade", &grade);
+        assert_eq!(status, 0, "{}", err);
+        assert!(
+            out.contains("ana                  1/1  4 bytes, 6.0 cycles\n"),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains("ben                  0/1  ❌ R1.high = 0x2B"),
+            "{}",
+            out
+        );
+        assert!(out.contains("cy                   0/1  error"), "{}", out);
+        assert!(out.contains("1 of 3 submissions passed"), "{}", out);
+
+        let ben: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("results/ben.json")).unwrap())
+                .unwrap();
+        assert_eq!(ben["passed"], false);
+        assert_eq!(ben["report"]["cases"][0]["name"], "R1.high is 0x2A");
+        let cy = std::fs::read_to_string(dir.join("results/cy.json")).unwrap();
+        assert!(cy.contains("\"assembly_errors\": \"error"), "{}", cy);
+        let xml = std::fs::read_to_string(dir.join("results/junit.xml")).unwrap();
+        assert!(
+            xml.contains("tests=\"3\" failures=\"1\" errors=\"1\""),
+            "{}",
+            xml
+        );
+        assert!(!dir.join("results/notes.json").exists());
+
+        assert_eq!(
+            rca1802("grade", &["grade", "DIR/submissions"]).0,
+            EXIT_USAGE
+        );
+        clean(&["grade"]);
+    }
+}
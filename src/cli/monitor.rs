@@ -0,0 +1,60 @@
+use super::{Args, CliError, EXIT_ERROR, load, machine, read};
+use crate::cpu::Cpu;
+use crate::monitor::{DEFAULT_MAX_CYCLES, Monitor};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// `monitor [FILE] [--script SCRIPT]`: the machine-language monitor
+///
+/// FILE is loaded first and its labels can stand in for addresses. With
+/// `--script` the commands are read from SCRIPT and a transcript is
+/// printed; the exit status is 1 if any command failed. Otherwise commands
+/// are read from standard input until `q` or end of input.
+pub(super) fn monitor(
+    args: &Args,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<u8, CliError> {
+    let (cpu, symbols) = match args.files.as_slice() {
+        [] => (Cpu::new(), Vec::new()),
+        [path] => {
+            let program = load(args, path, err)?;
+            let cpu = machine(args, path, &program)?;
+            (cpu, program.output.map(|o| o.symbols).unwrap_or_default())
+        }
+        _ => {
+            return Err(CliError::Usage(
+                "monitor takes at most one FILE".to_string(),
+            ));
+        }
+    };
+
+    let mut monitor = Monitor::new(cpu);
+    monitor.max_cycles = args.number("--max-cycles", DEFAULT_MAX_CYCLES)?;
+    for symbol in symbols.iter().filter(|s| !s.name.starts_with('@')) {
+        monitor.define(&symbol.name, symbol.value);
+    }
+
+    if let Some(script) = args.value("--script") {
+        let (transcript, failures) = monitor.run_script(&read(script)?);
+        write!(out, "{}", transcript)?;
+        return Ok(if failures > 0 { EXIT_ERROR } else { 0 });
+    }
+
+    let stdin = io::stdin();
+    let prompt = stdin.is_terminal();
+    let mut lines = stdin.lock().lines();
+    while !monitor.is_quit() {
+        if prompt {
+            write!(out, "* ")?;
+            out.flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        match monitor.execute(&line) {
+            Ok(output) => write!(out, "{}", output)?,
+            Err(error) => writeln!(err, "error: {}", error)?,
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,106 @@
+use super::{Args, CliError, EXIT_CYCLE_LIMIT, Program, load, machine};
+use crate::cpu::{Cpu, Instruction};
+use crate::monitor::{self, register_dump};
+use std::io::Write;
+
+/// Machine cycles `run` allows when `--max-cycles` is not given
+const DEFAULT_MAX_CYCLES: u64 = 10_000_000;
+
+/// Register whose low byte becomes the exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitRegister {
+    D,
+    R(u8),
+}
+
+impl ExitRegister {
+    fn parse(text: &str) -> Result<Self, CliError> {
+        let upper = text.to_uppercase();
+        if upper == "D" {
+            return Ok(Self::D);
+        }
+        upper
+            .strip_prefix('R')
+            .and_then(|n| u8::from_str_radix(n, 16).ok())
+            .filter(|&n| n < 16 && upper.len() == 2)
+            .map(Self::R)
+            .ok_or_else(|| CliError::Usage(format!("--exit-code: unknown register {}", text)))
+    }
+
+    fn status(self, cpu: &Cpu) -> u8 {
+        match self {
+            Self::D => cpu.d,
+            Self::R(n) => cpu.registers[n as usize] as u8,
+        }
+    }
+}
+
+/// `run FILE` and `trace FILE`: execute until IDL or the cycle limit
+///
+/// The program starts with P=0 and R0 at `--entry` (default 0). With
+/// `trace` every instruction is printed after it executes, with the
+/// registers it leaves behind and, for assembled programs, its source line.
+pub(super) fn run(
+    args: &Args,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+    trace: bool,
+) -> Result<u8, CliError> {
+    let path = args.file()?;
+    let limit = args.number("--max-cycles", DEFAULT_MAX_CYCLES)?;
+    let exit_register = args
+        .value("--exit-code")
+        .map(ExitRegister::parse)
+        .transpose()?;
+    let program = load(args, path, err)?;
+    let mut cpu = machine(args, path, &program)?;
+
+    let result = loop {
+        if cpu.is_halted() {
+            break Ok(());
+        }
+        if cpu.cycles >= limit {
+            break Err(EXIT_CYCLE_LIMIT);
+        }
+        let pc = cpu.get_pc();
+        let instruction = cpu.step()?;
+        if trace {
+            writeln!(out, "{}", trace_line(&cpu, &program, pc, &instruction))?;
+        }
+    };
+
+    if args.flag("--dump") {
+        write!(out, "{}", dump(&cpu))?;
+    }
+    match result {
+        Ok(()) => Ok(exit_register.map_or(0, |r| r.status(&cpu))),
+        Err(status) => {
+            writeln!(
+                err,
+                "error: cycle limit of {} reached at {:#06X}",
+                limit,
+                cpu.get_pc()
+            )?;
+            Ok(status)
+        }
+    }
+}
+
+/// A trace line with the source line the instruction came from
+fn trace_line(cpu: &Cpu, program: &Program, pc: u16, instruction: &Instruction) -> String {
+    let mut line = monitor::trace_line(cpu, pc, instruction);
+    if let Some(location) = program.source_map().and_then(|m| m.location(pc)) {
+        line.push_str(&format!("  {}:{}", location.file, location.line));
+    }
+    line
+}
+
+/// Register dump printed by `--dump`
+fn dump(cpu: &Cpu) -> String {
+    format!(
+        "{}cycles={} instructions={}\n",
+        register_dump(cpu),
+        cpu.cycles,
+        cpu.instructions_executed
+    )
+}
@@ -0,0 +1,174 @@
+use super::state::{Cpu, CpuError};
+
+/// BR - Short branch: PC = (PC.high, offset)
+///
+/// PC has already advanced past the instruction, so the page comes from the
+/// operand byte just behind it (a branch at xxFE lands on page xx, not xx+1).
+pub(super) fn execute_br(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    let pc = cpu.get_pc().wrapping_sub(1);
+    let new_pc = (pc & 0xFF00) | (offset as u16);
+    cpu.set_pc(new_pc);
+    Ok(())
+}
+
+/// BZ - Branch if zero: if D==0 then branch
+pub(super) fn execute_bz(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    if cpu.d == 0 {
+        execute_br(cpu, offset)?;
+    }
+    Ok(())
+}
+
+/// BNZ - Branch if not zero: if D!=0 then branch
+pub(super) fn execute_bnz(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    if cpu.d != 0 {
+        execute_br(cpu, offset)?;
+    }
+    Ok(())
+}
+
+/// BDF - Branch if DF=1
+pub(super) fn execute_bdf(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    if cpu.df {
+        execute_br(cpu, offset)?;
+    }
+    Ok(())
+}
+
+/// BNF - Branch if DF=0
+pub(super) fn execute_bnf(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    if !cpu.df {
+        execute_br(cpu, offset)?;
+    }
+    Ok(())
+}
+
+/// BQ - Branch if Q=1
+pub(super) fn execute_bq(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    if cpu.q {
+        execute_br(cpu, offset)?;
+    }
+    Ok(())
+}
+
+/// BNQ - Branch if Q=0
+pub(super) fn execute_bnq(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
+    if !cpu.q {
+        execute_br(cpu, offset)?;
+    }
+    Ok(())
+}
+
+/// SKP - Skip - unconditionally skip next byte
+pub(super) fn execute_skp(cpu: &mut Cpu) -> Result<(), CpuError> {
+    let pc = cpu.get_pc();
+    cpu.set_pc(pc.wrapping_add(1));
+    Ok(())
+}
+
+/// LBR - Long branch: PC = address
+pub(super) fn execute_lbr(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    cpu.set_pc(address);
+    Ok(())
+}
+
+/// LBZ - Long branch if zero
+pub(super) fn execute_lbz(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    if cpu.d == 0 {
+        cpu.set_pc(address);
+    }
+    Ok(())
+}
+
+/// LBNZ - Long branch if not zero
+pub(super) fn execute_lbnz(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    if cpu.d != 0 {
+        cpu.set_pc(address);
+    }
+    Ok(())
+}
+
+/// LBDF - Long branch if DF=1
+pub(super) fn execute_lbdf(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    if cpu.df {
+        cpu.set_pc(address);
+    }
+    Ok(())
+}
+
+/// LBNF - Long branch if DF=0
+pub(super) fn execute_lbnf(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    if !cpu.df {
+        cpu.set_pc(address);
+    }
+    Ok(())
+}
+
+/// LBQ - Long branch if Q=1
+pub(super) fn execute_lbq(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    if cpu.q {
+        cpu.set_pc(address);
+    }
+    Ok(())
+}
+
+/// LBNQ - Long branch if Q=0
+pub(super) fn execute_lbnq(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
+    if !cpu.q {
+        cpu.set_pc(address);
+    }
+    Ok(())
+}
+
+/// LSKP - Long skip
+pub(super) fn execute_lskp(cpu: &mut Cpu) -> Result<(), CpuError> {
+    let pc = cpu.get_pc();
+    cpu.set_pc(pc.wrapping_add(2));
+    Ok(())
+}
+
+/// LSNQ, LSNZ, LSNF, LSIE, LSQ, LSZ, LSDF - Long skip if the condition holds
+pub(super) fn execute_long_skip_if(cpu: &mut Cpu, condition: bool) -> Result<(), CpuError> {
+    if condition {
+        execute_lskp(cpu)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::executor::execute_instruction;
+    use super::super::instruction::{Instruction, Opcode};
+    use super::super::state::Cpu;
+
+    #[test]
+    fn test_short_branch_uses_operand_page() {
+        let mut cpu = Cpu::new();
+        // BR at 0x01FE: operand byte at 0x01FF, PC already advanced to 0x0200
+        cpu.set_pc(0x0200);
+
+        let instr = Instruction::with_immediate(Opcode::BR, 0, 0x10);
+        execute_instruction(&mut cpu, &instr).unwrap();
+        assert_eq!(cpu.get_pc(), 0x0110);
+    }
+
+    #[test]
+    fn test_skips_step_over_following_bytes() {
+        let mut cpu = Cpu::new();
+        // SKP; SEQ; LSZ; SEQ; SEQ; LSNZ; REQ
+        for (i, byte) in [0x38, 0x7B, 0xCE, 0x7B, 0x7B, 0xC6, 0x7A]
+            .iter()
+            .enumerate()
+        {
+            cpu.write_byte(i as u16, *byte).unwrap();
+        }
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_pc(), 0x0002);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_pc(), 0x0005);
+        cpu.step().unwrap();
+        assert_eq!(cpu.get_pc(), 0x0006);
+        assert!(!cpu.q);
+    }
+}
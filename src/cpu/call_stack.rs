@@ -0,0 +1,196 @@
+use super::state::Cpu;
+use serde::{Deserialize, Serialize};
+
+/// SEP R4 - enter the SCRT call routine
+const SCRT_CALL: u8 = 0xD4;
+
+/// SEP R5 - enter the SCRT return routine
+const SCRT_RETURN: u8 = 0xD5;
+
+/// SEP N, with N in the low nibble
+const SEP: u8 = 0xD0;
+
+/// How a subroutine was called
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CallKind {
+    /// `CALL` (`SEP R4` / `DW target`), returned from with `RETN`
+    Scrt,
+    /// `SEP N` to another register, returned from with a `SEP` back
+    Sep,
+}
+
+/// One active subroutine call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub kind: CallKind,
+    /// Address of the `SEP` that made the call
+    pub call_site: u16,
+    /// Subroutine entry point (the `DW` after an SCRT call)
+    pub target: u16,
+    /// Where execution resumes on return
+    pub return_address: u16,
+    /// P of the caller, which it is back to on return
+    pub caller: u8,
+    /// P the subroutine runs with (the caller's for SCRT)
+    pub routine: u8,
+}
+
+/// Call stack reconstructed by watching calls and returns
+///
+/// Call [`CallStack::observe`] before each instruction executes. `SEP R4`
+/// and `SEP R5` issued from outside the SCRT routines themselves (P other
+/// than 4 or 5) are SCRT calls and returns. Any other `SEP` to a different
+/// register is a call, unless it goes back to the register that called the
+/// running routine, which makes it a return.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    /// Record the effect of the instruction about to execute
+    pub fn observe(&mut self, cpu: &Cpu) {
+        if cpu.halted || cpu.p == 4 || cpu.p == 5 {
+            return;
+        }
+
+        let pc = cpu.get_pc();
+        let byte = |offset: u16| cpu.memory[pc.wrapping_add(offset) as usize];
+        match byte(0) {
+            SCRT_CALL => self.frames.push(Frame {
+                kind: CallKind::Scrt,
+                call_site: pc,
+                target: u16::from_be_bytes([byte(1), byte(2)]),
+                return_address: pc.wrapping_add(3),
+                caller: cpu.p,
+                routine: cpu.p,
+            }),
+            SCRT_RETURN => {
+                if let Some(i) = self.frames.iter().rposition(|f| f.kind == CallKind::Scrt) {
+                    self.frames.truncate(i);
+                }
+            }
+            op if op & 0xF0 == SEP && op & 0x0F != cpu.p => {
+                let n = op & 0x0F;
+                let returning = self.frames.last().is_some_and(|f| {
+                    f.kind == CallKind::Sep && f.caller == n && f.routine == cpu.p
+                });
+                if returning {
+                    self.frames.pop();
+                } else {
+                    self.frames.push(Frame {
+                        kind: CallKind::Sep,
+                        call_site: pc,
+                        target: cpu.registers[n as usize],
+                        return_address: pc.wrapping_add(1),
+                        caller: cpu.p,
+                        routine: n,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Active frames, outermost first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    /// Step until halted, observing every instruction; returns the deepest
+    /// SCRT stack seen
+    fn run(source: &str) -> (Cpu, Vec<Frame>) {
+        let output = assemble(source).unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&output.machine_code, 0).unwrap();
+
+        let mut stack = CallStack::default();
+        let mut deepest = Vec::new();
+        while !cpu.halted && cpu.instructions_executed < 10_000 {
+            stack.observe(&cpu);
+            let scrt: Vec<Frame> = stack
+                .frames()
+                .iter()
+                .filter(|f| f.kind == CallKind::Scrt)
+                .copied()
+                .collect();
+            if scrt.len() > deepest.len() {
+                deepest = scrt;
+            }
+            cpu.step().unwrap();
+        }
+        // Only the start-up code's hand-off to P = 3, which never returns
+        assert!(stack.frames().iter().all(|f| f.kind == CallKind::Sep));
+        (cpu, deepest)
+    }
+
+    #[test]
+    fn test_scrt_nested_calls() {
+        let source = r#"
+        LDI 0x01
+        CALL OUTER
+        PLO R7      ; D survives the calls
+        IDL
+OUTER:  CALL INNER
+        RETN
+INNER:  INC R8
+        RETN
+"#;
+        let (cpu, deepest) = run(source);
+
+        assert_eq!(cpu.get_register(7).unwrap() & 0xFF, 0x01);
+        assert_eq!(cpu.get_register(2).unwrap(), 0xFFFF);
+        assert_eq!(cpu.get_register(8).unwrap(), 1);
+        assert_eq!(cpu.p, 3);
+        assert_eq!(deepest.len(), 2);
+        assert_eq!(deepest[1].return_address, deepest[0].target + 3);
+    }
+
+    #[test]
+    fn test_sep_calls_and_returns() {
+        let source = r#"
+        LDI HIGH(SUB)
+        PHI R7
+        LDI LOW(SUB)
+        PLO R7
+        SEP R7      ; call
+        SEP R7      ; call again: R7 was left at the entry
+        IDL
+EXIT:   SEP R0      ; return
+SUB:    INC R8
+        BR EXIT
+"#;
+        let output = assemble(source).unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&output.machine_code, 0).unwrap();
+        let mut stack = CallStack::default();
+        let mut calls = Vec::new();
+        while !cpu.halted {
+            stack.observe(&cpu);
+            if let Some(frame) = stack.frames().last()
+                && calls.last() != Some(frame)
+            {
+                calls.push(*frame);
+            }
+            cpu.step().unwrap();
+        }
+        assert!(stack.frames().is_empty());
+        assert_eq!(cpu.registers[8], 2);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            (calls[0].kind, calls[0].caller, calls[0].routine),
+            (CallKind::Sep, 0, 7)
+        );
+        assert_eq!(calls[1].return_address, calls[0].return_address + 1);
+    }
+}
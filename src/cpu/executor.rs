@@ -1,4 +1,10 @@
+use super::branch::{
+    execute_bdf, execute_bnf, execute_bnq, execute_bnz, execute_bq, execute_br, execute_bz,
+    execute_lbdf, execute_lbnf, execute_lbnq, execute_lbnz, execute_lbq, execute_lbr, execute_lbz,
+    execute_long_skip_if, execute_lskp, execute_skp,
+};
 use super::instruction::{Instruction, Opcode};
+use super::io::{execute_inp, execute_out, execute_req, execute_seq};
 use super::state::{Cpu, CpuError};
 
 /// Execute a single instruction on the CPU
@@ -59,22 +65,53 @@ pub fn execute_instruction(cpu: &mut Cpu, instruction: &Instruction) -> Result<(
         Opcode::LBQ => execute_lbq(cpu, instruction.address.unwrap_or(0)),
         Opcode::LBNQ => execute_lbnq(cpu, instruction.address.unwrap_or(0)),
         Opcode::LSKP => execute_lskp(cpu),
+        Opcode::LSNQ => execute_long_skip_if(cpu, !cpu.q),
+        Opcode::LSNZ => execute_long_skip_if(cpu, cpu.d != 0),
+        Opcode::LSNF => execute_long_skip_if(cpu, !cpu.df),
+        Opcode::LSIE => execute_long_skip_if(cpu, cpu.ie),
+        Opcode::LSQ => execute_long_skip_if(cpu, cpu.q),
+        Opcode::LSZ => execute_long_skip_if(cpu, cpu.d == 0),
+        Opcode::LSDF => execute_long_skip_if(cpu, cpu.df),
         Opcode::REQ => execute_req(cpu),
         Opcode::SEQ => execute_seq(cpu),
         Opcode::NOP => Ok(()),
+        Opcode::OUT => execute_out(cpu, instruction.register),
+        Opcode::INP => execute_inp(cpu, instruction.register),
         _ => {
             // Unimplemented instructions - just continue for now
             Ok(())
         }
     }?;
 
-    // Increment cycle and instruction counters
-    cpu.cycles += 1;
+    // Count the instruction and the machine cycles it takes
+    cpu.cycles += instruction.opcode.machine_cycles() as u64;
     cpu.instructions_executed += 1;
 
     Ok(())
 }
 
+impl Cpu {
+    /// Fetch, decode and execute the instruction at the program counter
+    ///
+    /// The PC is advanced past the instruction before it executes, as the
+    /// fetch cycle does on hardware, so branches simply overwrite it.
+    pub fn step(&mut self) -> Result<Instruction, CpuError> {
+        if self.is_halted() {
+            return Err(CpuError::Halted);
+        }
+
+        let pc = self.get_pc();
+        let bytes: Vec<u8> = (0..3)
+            .map(|i| self.memory[pc.wrapping_add(i) as usize])
+            .collect();
+        let instruction = Instruction::decode(&bytes).ok_or(CpuError::InvalidInstruction(pc))?;
+
+        self.set_pc(pc.wrapping_add(instruction.opcode.length() as u16));
+        execute_instruction(self, &instruction)?;
+        Ok(instruction)
+    }
+}
+
 // Memory Access Instructions
 
 /// IDL - Idle (halt until interrupt)
@@ -359,146 +396,6 @@ fn execute_shlc(cpu: &mut Cpu) -> Result<(), CpuError> {
     Ok(())
 }
 
-// Branch Instructions
-
-/// BR - Short branch: PC = (PC.high, offset)
-fn execute_br(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    let pc = cpu.get_pc();
-    let new_pc = (pc & 0xFF00) | (offset as u16);
-    cpu.set_pc(new_pc);
-    Ok(())
-}
-
-/// BZ - Branch if zero: if D==0 then branch
-fn execute_bz(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    if cpu.d == 0 {
-        execute_br(cpu, offset)?;
-    }
-    Ok(())
-}
-
-/// BNZ - Branch if not zero: if D!=0 then branch
-fn execute_bnz(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    if cpu.d != 0 {
-        execute_br(cpu, offset)?;
-    }
-    Ok(())
-}
-
-/// BDF - Branch if DF=1
-fn execute_bdf(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    if cpu.df {
-        execute_br(cpu, offset)?;
-    }
-    Ok(())
-}
-
-/// BNF - Branch if DF=0
-fn execute_bnf(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    if !cpu.df {
-        execute_br(cpu, offset)?;
-    }
-    Ok(())
-}
-
-/// BQ - Branch if Q=1
-fn execute_bq(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    if cpu.q {
-        execute_br(cpu, offset)?;
-    }
-    Ok(())
-}
-
-/// BNQ - Branch if Q=0
-fn execute_bnq(cpu: &mut Cpu, offset: u8) -> Result<(), CpuError> {
-    if !cpu.q {
-        execute_br(cpu, offset)?;
-    }
-    Ok(())
-}
-
-/// SKP - Skip - unconditionally skip next byte
-fn execute_skp(cpu: &mut Cpu) -> Result<(), CpuError> {
-    let pc = cpu.get_pc();
-    cpu.set_pc(pc.wrapping_add(1));
-    Ok(())
-}
-
-/// LBR - Long branch: PC = address
-fn execute_lbr(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    cpu.set_pc(address);
-    Ok(())
-}
-
-/// LBZ - Long branch if zero
-fn execute_lbz(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    if cpu.d == 0 {
-        cpu.set_pc(address);
-    }
-    Ok(())
-}
-
-/// LBNZ - Long branch if not zero
-fn execute_lbnz(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    if cpu.d != 0 {
-        cpu.set_pc(address);
-    }
-    Ok(())
-}
-
-/// LBDF - Long branch if DF=1
-fn execute_lbdf(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    if cpu.df {
-        cpu.set_pc(address);
-    }
-    Ok(())
-}
-
-/// LBNF - Long branch if DF=0
-fn execute_lbnf(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    if !cpu.df {
-        cpu.set_pc(address);
-    }
-    Ok(())
-}
-
-/// LBQ - Long branch if Q=1
-fn execute_lbq(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    if cpu.q {
-        cpu.set_pc(address);
-    }
-    Ok(())
-}
-
-/// LBNQ - Long branch if Q=0
-fn execute_lbnq(cpu: &mut Cpu, address: u16) -> Result<(), CpuError> {
-    if !cpu.q {
-        cpu.set_pc(address);
-    }
-    Ok(())
-}
-
-/// LSKP - Long skip
-fn execute_lskp(cpu: &mut Cpu) -> Result<(), CpuError> {
-    let pc = cpu.get_pc();
-    cpu.set_pc(pc.wrapping_add(2));
-    Ok(())
-}
-
-// I/O Instructions
-
-/// REQ - Reset Q: Q = 0
-fn execute_req(cpu: &mut Cpu) -> Result<(), CpuError> {
-    cpu.q = false;
-    Ok(())
-}
-
-/// SEQ - Set Q: Q = 1
-fn execute_seq(cpu: &mut Cpu) -> Result<(), CpuError> {
-    cpu.q = true;
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
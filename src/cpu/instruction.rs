@@ -1,3 +1,4 @@
+use super::isa::{self, INSTRUCTIONS, InstructionInfo, OperandKind};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -38,7 +39,7 @@ pub enum Opcode {
     B3,
     B4,
 
-    /// SKP (38) - Skip - unconditional skip 2 bytes
+    /// SKP (38) - Skip - unconditionally skip the next byte
     SKP,
 
     /// BNQ (39) - Branch if Q=0
@@ -62,13 +63,13 @@ pub enum Opcode {
     /// STR (5N) - Store via N - M(RN) = D
     STR,
 
-    /// IRX (60) - Increment register X
+    /// IRX (60) - Increment register X (68 is not an instruction)
     IRX,
 
     /// OUT (6N) - Output - output M(RX) to port N, RX++
     OUT,
 
-    /// INP (6N) - Input - input from port N to M(RX) and D
+    /// INP (6N) - Input - input from port N - 8 to M(RX) and D
     INP,
 
     /// RET (70) - Return - disable interrupts, return from interrupt
@@ -155,7 +156,7 @@ pub enum Opcode {
     /// LSNF (C7) - Long skip if DF=0
     LSNF,
 
-    /// LSKP (C8) - Long skip - unconditional skip 3 bytes
+    /// LSKP (C8) - Long skip - unconditionally skip the next 2 bytes
     LSKP,
 
     /// LBNQ (C9) - Long branch if Q=0
@@ -235,265 +236,31 @@ pub enum Opcode {
 }
 
 impl Opcode {
+    /// This instruction's entry in the [ISA table](super::isa)
+    pub fn info(&self) -> &'static InstructionInfo {
+        &INSTRUCTIONS[*self as usize]
+    }
+
     /// Decode an opcode from a byte
     pub fn from_byte(byte: u8) -> Option<Self> {
-        match byte {
-            0x00 => Some(Opcode::IDL),
-            0x01..=0x0F => Some(Opcode::LDN),
-            0x10..=0x1F => Some(Opcode::INC),
-            0x20..=0x2F => Some(Opcode::DEC),
-            0x30 => Some(Opcode::BR),
-            0x31 => Some(Opcode::BQ),
-            0x32 => Some(Opcode::BZ),
-            0x33 => Some(Opcode::BDF),
-            0x34 => Some(Opcode::B1),
-            0x35 => Some(Opcode::B2),
-            0x36 => Some(Opcode::B3),
-            0x37 => Some(Opcode::B4),
-            0x38 => Some(Opcode::SKP),
-            0x39 => Some(Opcode::BNQ),
-            0x3A => Some(Opcode::BNZ),
-            0x3B => Some(Opcode::BNF),
-            0x3C => Some(Opcode::BN1),
-            0x3D => Some(Opcode::BN2),
-            0x3E => Some(Opcode::BN3),
-            0x3F => Some(Opcode::BN4),
-            0x40..=0x4F => Some(Opcode::LDA),
-            0x50..=0x5F => Some(Opcode::STR),
-            0x60 => Some(Opcode::IRX),
-            0x61..=0x67 => Some(Opcode::OUT),
-            0x68 => Some(Opcode::IRX), // 68 is also IRX
-            0x69..=0x6F => Some(Opcode::INP),
-            0x70 => Some(Opcode::RET),
-            0x71 => Some(Opcode::DIS),
-            0x72 => Some(Opcode::LDXA),
-            0x73 => Some(Opcode::STXD),
-            0x74 => Some(Opcode::ADC),
-            0x75 => Some(Opcode::SDB),
-            0x76 => Some(Opcode::SHRC),
-            0x77 => Some(Opcode::SMB),
-            0x78 => Some(Opcode::SAV),
-            0x79 => Some(Opcode::MARK),
-            0x7A => Some(Opcode::REQ),
-            0x7B => Some(Opcode::SEQ),
-            0x7C => Some(Opcode::ADCI),
-            0x7D => Some(Opcode::SDBI),
-            0x7E => Some(Opcode::SHLC),
-            0x7F => Some(Opcode::SMBI),
-            0x80..=0x8F => Some(Opcode::GLO),
-            0x90..=0x9F => Some(Opcode::GHI),
-            0xA0..=0xAF => Some(Opcode::PLO),
-            0xB0..=0xBF => Some(Opcode::PHI),
-            0xC0 => Some(Opcode::LBR),
-            0xC1 => Some(Opcode::LBQ),
-            0xC2 => Some(Opcode::LBZ),
-            0xC3 => Some(Opcode::LBDF),
-            0xC4 => Some(Opcode::NOP),
-            0xC5 => Some(Opcode::LSNQ),
-            0xC6 => Some(Opcode::LSNZ),
-            0xC7 => Some(Opcode::LSNF),
-            0xC8 => Some(Opcode::LSKP),
-            0xC9 => Some(Opcode::LBNQ),
-            0xCA => Some(Opcode::LBNZ),
-            0xCB => Some(Opcode::LBNF),
-            0xCC => Some(Opcode::LSIE),
-            0xCD => Some(Opcode::LSQ),
-            0xCE => Some(Opcode::LSZ),
-            0xCF => Some(Opcode::LSDF),
-            0xD0..=0xDF => Some(Opcode::SEP),
-            0xE0..=0xEF => Some(Opcode::SEX),
-            0xF0 => Some(Opcode::LDX),
-            0xF1 => Some(Opcode::OR),
-            0xF2 => Some(Opcode::AND),
-            0xF3 => Some(Opcode::XOR),
-            0xF4 => Some(Opcode::ADD),
-            0xF5 => Some(Opcode::SD),
-            0xF6 => Some(Opcode::SHR),
-            0xF7 => Some(Opcode::SM),
-            0xF8 => Some(Opcode::LDI),
-            0xF9 => Some(Opcode::ORI),
-            0xFA => Some(Opcode::ANI),
-            0xFB => Some(Opcode::XRI),
-            0xFC => Some(Opcode::ADI),
-            0xFD => Some(Opcode::SDI),
-            0xFE => Some(Opcode::SHL),
-            0xFF => Some(Opcode::SMI),
-        }
+        isa::decode(byte).map(|info| info.opcode)
     }
 
     /// Get the mnemonic for this opcode
     pub fn mnemonic(&self) -> &'static str {
-        match self {
-            Opcode::IDL => "IDL",
-            Opcode::LDN => "LDN",
-            Opcode::INC => "INC",
-            Opcode::DEC => "DEC",
-            Opcode::BR => "BR",
-            Opcode::BQ => "BQ",
-            Opcode::BZ => "BZ",
-            Opcode::BDF => "BDF",
-            Opcode::B1 => "B1",
-            Opcode::B2 => "B2",
-            Opcode::B3 => "B3",
-            Opcode::B4 => "B4",
-            Opcode::SKP => "SKP",
-            Opcode::BNQ => "BNQ",
-            Opcode::BNZ => "BNZ",
-            Opcode::BNF => "BNF",
-            Opcode::BN1 => "BN1",
-            Opcode::BN2 => "BN2",
-            Opcode::BN3 => "BN3",
-            Opcode::BN4 => "BN4",
-            Opcode::LDA => "LDA",
-            Opcode::STR => "STR",
-            Opcode::IRX => "IRX",
-            Opcode::OUT => "OUT",
-            Opcode::INP => "INP",
-            Opcode::RET => "RET",
-            Opcode::DIS => "DIS",
-            Opcode::LDXA => "LDXA",
-            Opcode::STXD => "STXD",
-            Opcode::ADC => "ADC",
-            Opcode::SDB => "SDB",
-            Opcode::SHRC => "SHRC",
-            Opcode::SMB => "SMB",
-            Opcode::SAV => "SAV",
-            Opcode::MARK => "MARK",
-            Opcode::REQ => "REQ",
-            Opcode::SEQ => "SEQ",
-            Opcode::ADCI => "ADCI",
-            Opcode::SDBI => "SDBI",
-            Opcode::SHLC => "SHLC",
-            Opcode::SMBI => "SMBI",
-            Opcode::GLO => "GLO",
-            Opcode::GHI => "GHI",
-            Opcode::PLO => "PLO",
-            Opcode::PHI => "PHI",
-            Opcode::LBR => "LBR",
-            Opcode::LBQ => "LBQ",
-            Opcode::LBZ => "LBZ",
-            Opcode::LBDF => "LBDF",
-            Opcode::NOP => "NOP",
-            Opcode::LSNQ => "LSNQ",
-            Opcode::LSNZ => "LSNZ",
-            Opcode::LSNF => "LSNF",
-            Opcode::LSKP => "LSKP",
-            Opcode::LBNQ => "LBNQ",
-            Opcode::LBNZ => "LBNZ",
-            Opcode::LBNF => "LBNF",
-            Opcode::LSIE => "LSIE",
-            Opcode::LSQ => "LSQ",
-            Opcode::LSZ => "LSZ",
-            Opcode::LSDF => "LSDF",
-            Opcode::SEP => "SEP",
-            Opcode::SEX => "SEX",
-            Opcode::LDX => "LDX",
-            Opcode::OR => "OR",
-            Opcode::AND => "AND",
-            Opcode::XOR => "XOR",
-            Opcode::ADD => "ADD",
-            Opcode::SD => "SD",
-            Opcode::SHR => "SHR",
-            Opcode::SM => "SM",
-            Opcode::LDI => "LDI",
-            Opcode::ORI => "ORI",
-            Opcode::ANI => "ANI",
-            Opcode::XRI => "XRI",
-            Opcode::ADI => "ADI",
-            Opcode::SDI => "SDI",
-            Opcode::SHL => "SHL",
-            Opcode::SMI => "SMI",
-        }
+        self.info().mnemonic
     }
 
     /// Get the length of the instruction in bytes
     pub fn length(&self) -> u8 {
-        match self {
-            // Most instructions are 1 byte
-            Opcode::IDL
-            | Opcode::LDN
-            | Opcode::INC
-            | Opcode::DEC
-            | Opcode::LDA
-            | Opcode::STR
-            | Opcode::IRX
-            | Opcode::OUT
-            | Opcode::INP
-            | Opcode::RET
-            | Opcode::DIS
-            | Opcode::LDXA
-            | Opcode::STXD
-            | Opcode::ADC
-            | Opcode::SDB
-            | Opcode::SHRC
-            | Opcode::SMB
-            | Opcode::SAV
-            | Opcode::MARK
-            | Opcode::REQ
-            | Opcode::SEQ
-            | Opcode::GLO
-            | Opcode::GHI
-            | Opcode::PLO
-            | Opcode::PHI
-            | Opcode::NOP
-            | Opcode::SEP
-            | Opcode::SEX
-            | Opcode::LDX
-            | Opcode::OR
-            | Opcode::AND
-            | Opcode::XOR
-            | Opcode::ADD
-            | Opcode::SD
-            | Opcode::SHR
-            | Opcode::SM
-            | Opcode::SHL => 1,
-
-            // Short branches and immediate operations are 2 bytes
-            Opcode::BR
-            | Opcode::BQ
-            | Opcode::BZ
-            | Opcode::BDF
-            | Opcode::B1
-            | Opcode::B2
-            | Opcode::B3
-            | Opcode::B4
-            | Opcode::SKP
-            | Opcode::BNQ
-            | Opcode::BNZ
-            | Opcode::BNF
-            | Opcode::BN1
-            | Opcode::BN2
-            | Opcode::BN3
-            | Opcode::BN4
-            | Opcode::ADCI
-            | Opcode::SDBI
-            | Opcode::SHLC
-            | Opcode::SMBI
-            | Opcode::LDI
-            | Opcode::ORI
-            | Opcode::ANI
-            | Opcode::XRI
-            | Opcode::ADI
-            | Opcode::SDI
-            | Opcode::SMI => 2,
-
-            // Long branches and skips are 3 bytes
-            Opcode::LBR
-            | Opcode::LBQ
-            | Opcode::LBZ
-            | Opcode::LBDF
-            | Opcode::LSNQ
-            | Opcode::LSNZ
-            | Opcode::LSNF
-            | Opcode::LSKP
-            | Opcode::LBNQ
-            | Opcode::LBNZ
-            | Opcode::LBNF
-            | Opcode::LSIE
-            | Opcode::LSQ
-            | Opcode::LSZ
-            | Opcode::LSDF => 3,
-        }
+        self.info().length
+    }
+
+    /// Machine cycles (8 clocks each) the instruction takes on hardware
+    ///
+    /// Long branches, long skips and NOP take 3; everything else takes 2.
+    pub fn machine_cycles(&self) -> u8 {
+        self.info().cycles
     }
 }
 
@@ -568,7 +335,8 @@ impl Instruction {
 
     /// Encode instruction to bytes
     pub fn encode(&self) -> Vec<u8> {
-        let mut bytes = vec![self.get_base_opcode()];
+        let info = self.opcode.info();
+        let mut bytes = vec![info.code | (self.register & info.operand.field_mask())];
 
         if let Some(imm) = self.immediate {
             bytes.push(imm);
@@ -581,44 +349,18 @@ impl Instruction {
 
         bytes
     }
-
-    /// Get the base opcode byte (with register field)
-    fn get_base_opcode(&self) -> u8 {
-        // This is a simplified version - would need full opcode table
-        match self.opcode {
-            Opcode::IDL => 0x00,
-            Opcode::LDN => self.register & 0x0F,
-            Opcode::INC => 0x10 | (self.register & 0x0F),
-            Opcode::DEC => 0x20 | (self.register & 0x0F),
-            Opcode::LDI => 0xF8,
-            Opcode::NOP => 0xC4,
-            // Add remaining opcodes as needed
-            _ => 0x00, // Placeholder
-        }
-    }
 }
 
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.opcode.mnemonic())?;
 
-        // Add register for applicable instructions
-        match self.opcode {
-            Opcode::LDN
-            | Opcode::INC
-            | Opcode::DEC
-            | Opcode::LDA
-            | Opcode::STR
-            | Opcode::GLO
-            | Opcode::GHI
-            | Opcode::PLO
-            | Opcode::PHI
-            | Opcode::SEP
-            | Opcode::SEX
-            | Opcode::OUT
-            | Opcode::INP => {
+        // Add register or port for applicable instructions
+        match self.opcode.info().operand {
+            OperandKind::Register | OperandKind::RegisterNonZero => {
                 write!(f, " R{:X}", self.register)?;
             }
+            OperandKind::Port => write!(f, " {}", self.register & 0x07)?,
             _ => {}
         }
 
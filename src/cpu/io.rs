@@ -0,0 +1,56 @@
+use super::state::{Cpu, CpuError};
+
+/// REQ - Reset Q: Q = 0
+pub(super) fn execute_req(cpu: &mut Cpu) -> Result<(), CpuError> {
+    cpu.q = false;
+    Ok(())
+}
+
+/// SEQ - Set Q: Q = 1
+pub(super) fn execute_seq(cpu: &mut Cpu) -> Result<(), CpuError> {
+    cpu.q = true;
+    Ok(())
+}
+
+/// OUT - Output: bus = M(RX) to port N, RX++
+pub(super) fn execute_out(cpu: &mut Cpu, port: u8) -> Result<(), CpuError> {
+    let addr = cpu.get_x_register();
+    let byte = cpu.read_byte(addr)?;
+    cpu.ports.write(port & 0x07, byte);
+    cpu.set_x_register(addr.wrapping_add(1));
+    Ok(())
+}
+
+/// INP - Input: M(RX) = D = bus from port N - 8
+pub(super) fn execute_inp(cpu: &mut Cpu, port: u8) -> Result<(), CpuError> {
+    let byte = cpu.ports.read(port & 0x07);
+    cpu.write_byte(cpu.get_x_register(), byte)?;
+    cpu.d = byte;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::executor::execute_instruction;
+    use super::super::instruction::{Instruction, Opcode};
+    use super::super::state::Cpu;
+
+    #[test]
+    fn test_out_and_inp() {
+        let mut cpu = Cpu::new();
+        cpu.x = 2;
+        cpu.set_register(2, 0x0100).unwrap();
+        cpu.write_byte(0x0100, 0x5A).unwrap();
+        cpu.ports.feed(1, &[0x11, 0x22]);
+
+        execute_instruction(&mut cpu, &Instruction::new(Opcode::OUT, 4)).unwrap();
+        assert_eq!(cpu.ports.output_on(4), vec![0x5A]);
+        assert_eq!(cpu.get_register(2).unwrap(), 0x0101);
+
+        execute_instruction(&mut cpu, &Instruction::new(Opcode::INP, 9)).unwrap();
+        assert_eq!((cpu.d, cpu.read_byte(0x0101).unwrap()), (0x11, 0x11));
+        execute_instruction(&mut cpu, &Instruction::new(Opcode::INP, 9)).unwrap();
+        execute_instruction(&mut cpu, &Instruction::new(Opcode::INP, 9)).unwrap();
+        assert_eq!(cpu.d, 0);
+    }
+}
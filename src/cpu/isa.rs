@@ -0,0 +1,368 @@
+//! The 1802 instruction set as one table
+//!
+//! Each entry gives an instruction's encoding, operand, length, timing,
+//! the flags it changes and what it does. Decoding ([`Opcode::from_byte`]),
+//! encoding ([`Instruction::encode`](super::Instruction::encode)), the
+//! assembler, the disassembler and the reference in `docs/isa.md` (see
+//! [`reference`]) are all driven by it.
+//!
+//! SKP and the long skips take no operand: they are one byte and step over
+//! the bytes after them. 68 is not an 1802 instruction.
+
+use super::instruction::Opcode;
+use Flag::{DF, IE, Q};
+use OperandKind::{Immediate, Implied, LongBranch, Port, Register, RegisterNonZero, ShortBranch};
+use std::fmt::Write;
+
+/// What follows an instruction's opcode, or goes in its low bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// No operand
+    Implied,
+    /// Register R0-RF in the N field
+    Register,
+    /// Register R1-RF in the N field (N = 0 would be IDL)
+    RegisterNonZero,
+    /// I/O port 1-7 in the low three bits
+    Port,
+    /// A byte after the opcode
+    Immediate,
+    /// Low byte of a target on the operand byte's page
+    ShortBranch,
+    /// Big-endian 16-bit target after the opcode
+    LongBranch,
+}
+
+impl OperandKind {
+    /// Bytes the instruction takes, opcode included
+    pub const fn length(self) -> u8 {
+        match self {
+            OperandKind::Immediate | OperandKind::ShortBranch => 2,
+            OperandKind::LongBranch => 3,
+            _ => 1,
+        }
+    }
+
+    /// Mask of the opcode bits that hold the operand
+    pub const fn field_mask(self) -> u8 {
+        match self {
+            OperandKind::Register | OperandKind::RegisterNonZero => 0x0F,
+            OperandKind::Port => 0x07,
+            _ => 0,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            OperandKind::Implied => "",
+            OperandKind::Register => "Rn",
+            OperandKind::RegisterNonZero => "R1-RF",
+            OperandKind::Port => "port 1-7",
+            OperandKind::Immediate => "byte",
+            OperandKind::ShortBranch => "page address",
+            OperandKind::LongBranch => "address",
+        }
+    }
+}
+
+/// Status flags an instruction can change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    /// Data flag (carry/borrow and shifted-out bit)
+    DF,
+    /// Q output line
+    Q,
+    /// Interrupt enable
+    IE,
+}
+
+/// Everything known about one instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionInfo {
+    pub opcode: Opcode,
+    pub mnemonic: &'static str,
+    /// Opcode byte, with the operand bits clear for register and port forms
+    pub code: u8,
+    pub operand: OperandKind,
+    /// Bytes, opcode included
+    pub length: u8,
+    /// Machine cycles (8 clocks each)
+    pub cycles: u8,
+    pub flags: &'static [Flag],
+    pub description: &'static str,
+}
+
+impl InstructionInfo {
+    const fn new(
+        opcode: Opcode,
+        mnemonic: &'static str,
+        code: u8,
+        operand: OperandKind,
+        cycles: u8,
+        flags: &'static [Flag],
+        description: &'static str,
+    ) -> Self {
+        Self {
+            opcode,
+            mnemonic,
+            code,
+            operand,
+            length: operand.length(),
+            cycles,
+            flags,
+            description,
+        }
+    }
+
+    /// Does `byte` decode as this instruction?
+    pub const fn matches(&self, byte: u8) -> bool {
+        let field = byte & self.operand.field_mask();
+        match self.operand {
+            OperandKind::Register => byte & 0xF0 == self.code,
+            OperandKind::RegisterNonZero | OperandKind::Port => {
+                byte & !self.operand.field_mask() == self.code && field != 0
+            }
+            _ => byte == self.code,
+        }
+    }
+
+    /// Opcode as written in the RCA manuals: `F8`, or `1N` for register forms
+    pub fn encoding(&self) -> String {
+        match self.operand.field_mask() {
+            0 => format!("{:02X}", self.code),
+            _ => format!("{:X}N", self.code >> 4),
+        }
+    }
+}
+
+/// The instruction set, in [`Opcode`] order
+#[rustfmt::skip]
+pub const INSTRUCTIONS: &[InstructionInfo] = &[
+    InstructionInfo::new(Opcode::IDL, "IDL", 0x00, Implied, 2, &[], "Idle: wait for an interrupt or DMA"),
+    InstructionInfo::new(Opcode::LDN, "LDN", 0x00, RegisterNonZero, 2, &[], "Load via N: D = M(R(N))"),
+    InstructionInfo::new(Opcode::INC, "INC", 0x10, Register, 2, &[], "Increment R(N)"),
+    InstructionInfo::new(Opcode::DEC, "DEC", 0x20, Register, 2, &[], "Decrement R(N)"),
+    InstructionInfo::new(Opcode::BR, "BR", 0x30, ShortBranch, 2, &[], "Short branch: R(P).0 = M(R(P))"),
+    InstructionInfo::new(Opcode::BQ, "BQ", 0x31, ShortBranch, 2, &[], "Short branch if Q = 1"),
+    InstructionInfo::new(Opcode::BZ, "BZ", 0x32, ShortBranch, 2, &[], "Short branch if D = 0"),
+    InstructionInfo::new(Opcode::BDF, "BDF", 0x33, ShortBranch, 2, &[], "Short branch if DF = 1"),
+    InstructionInfo::new(Opcode::B1, "B1", 0x34, ShortBranch, 2, &[], "Short branch if EF1 = 1"),
+    InstructionInfo::new(Opcode::B2, "B2", 0x35, ShortBranch, 2, &[], "Short branch if EF2 = 1"),
+    InstructionInfo::new(Opcode::B3, "B3", 0x36, ShortBranch, 2, &[], "Short branch if EF3 = 1"),
+    InstructionInfo::new(Opcode::B4, "B4", 0x37, ShortBranch, 2, &[], "Short branch if EF4 = 1"),
+    InstructionInfo::new(Opcode::SKP, "SKP", 0x38, Implied, 2, &[], "Short skip: R(P) += 1"),
+    InstructionInfo::new(Opcode::BNQ, "BNQ", 0x39, ShortBranch, 2, &[], "Short branch if Q = 0"),
+    InstructionInfo::new(Opcode::BNZ, "BNZ", 0x3A, ShortBranch, 2, &[], "Short branch if D != 0"),
+    InstructionInfo::new(Opcode::BNF, "BNF", 0x3B, ShortBranch, 2, &[], "Short branch if DF = 0"),
+    InstructionInfo::new(Opcode::BN1, "BN1", 0x3C, ShortBranch, 2, &[], "Short branch if EF1 = 0"),
+    InstructionInfo::new(Opcode::BN2, "BN2", 0x3D, ShortBranch, 2, &[], "Short branch if EF2 = 0"),
+    InstructionInfo::new(Opcode::BN3, "BN3", 0x3E, ShortBranch, 2, &[], "Short branch if EF3 = 0"),
+    InstructionInfo::new(Opcode::BN4, "BN4", 0x3F, ShortBranch, 2, &[], "Short branch if EF4 = 0"),
+    InstructionInfo::new(Opcode::LDA, "LDA", 0x40, Register, 2, &[], "Load advance: D = M(R(N)); R(N) += 1"),
+    InstructionInfo::new(Opcode::STR, "STR", 0x50, Register, 2, &[], "Store via N: M(R(N)) = D"),
+    InstructionInfo::new(Opcode::IRX, "IRX", 0x60, Implied, 2, &[], "Increment R(X)"),
+    InstructionInfo::new(Opcode::OUT, "OUT", 0x60, Port, 2, &[], "Output: bus = M(R(X)); R(X) += 1"),
+    InstructionInfo::new(Opcode::INP, "INP", 0x68, Port, 2, &[], "Input: M(R(X)) = D = bus"),
+    InstructionInfo::new(Opcode::RET, "RET", 0x70, Implied, 2, &[IE], "Return: (X, P) = M(R(X)); R(X) += 1; IE = 1"),
+    InstructionInfo::new(Opcode::DIS, "DIS", 0x71, Implied, 2, &[IE], "Disable: (X, P) = M(R(X)); R(X) += 1; IE = 0"),
+    InstructionInfo::new(Opcode::LDXA, "LDXA", 0x72, Implied, 2, &[], "Load via X and advance: D = M(R(X)); R(X) += 1"),
+    InstructionInfo::new(Opcode::STXD, "STXD", 0x73, Implied, 2, &[], "Store via X and decrement: M(R(X)) = D; R(X) -= 1"),
+    InstructionInfo::new(Opcode::ADC, "ADC", 0x74, Implied, 2, &[DF], "Add with carry: DF, D = M(R(X)) + D + DF"),
+    InstructionInfo::new(Opcode::SDB, "SDB", 0x75, Implied, 2, &[DF], "Subtract D with borrow: DF, D = M(R(X)) - D - !DF"),
+    InstructionInfo::new(Opcode::SHRC, "SHRC", 0x76, Implied, 2, &[DF], "Shift right with carry: DF -> D7, D0 -> DF"),
+    InstructionInfo::new(Opcode::SMB, "SMB", 0x77, Implied, 2, &[DF], "Subtract memory with borrow: DF, D = D - M(R(X)) - !DF"),
+    InstructionInfo::new(Opcode::SAV, "SAV", 0x78, Implied, 2, &[], "Save: M(R(X)) = T"),
+    InstructionInfo::new(Opcode::MARK, "MARK", 0x79, Implied, 2, &[], "Push X, P to stack: T = X, P; M(R(2)) = T; X = P; R(2) -= 1"),
+    InstructionInfo::new(Opcode::REQ, "REQ", 0x7A, Implied, 2, &[Q], "Reset Q: Q = 0"),
+    InstructionInfo::new(Opcode::SEQ, "SEQ", 0x7B, Implied, 2, &[Q], "Set Q: Q = 1"),
+    InstructionInfo::new(Opcode::ADCI, "ADCI", 0x7C, Immediate, 2, &[DF], "Add with carry immediate: DF, D = M(R(P)) + D + DF"),
+    InstructionInfo::new(Opcode::SDBI, "SDBI", 0x7D, Immediate, 2, &[DF], "Subtract D with borrow immediate: DF, D = M(R(P)) - D - !DF"),
+    InstructionInfo::new(Opcode::SHLC, "SHLC", 0x7E, Implied, 2, &[DF], "Shift left with carry: DF -> D0, D7 -> DF"),
+    InstructionInfo::new(Opcode::SMBI, "SMBI", 0x7F, Immediate, 2, &[DF], "Subtract memory with borrow immediate: DF, D = D - M(R(P)) - !DF"),
+    InstructionInfo::new(Opcode::GLO, "GLO", 0x80, Register, 2, &[], "Get low: D = R(N).0"),
+    InstructionInfo::new(Opcode::GHI, "GHI", 0x90, Register, 2, &[], "Get high: D = R(N).1"),
+    InstructionInfo::new(Opcode::PLO, "PLO", 0xA0, Register, 2, &[], "Put low: R(N).0 = D"),
+    InstructionInfo::new(Opcode::PHI, "PHI", 0xB0, Register, 2, &[], "Put high: R(N).1 = D"),
+    InstructionInfo::new(Opcode::LBR, "LBR", 0xC0, LongBranch, 3, &[], "Long branch: R(P) = M(R(P)), M(R(P) + 1)"),
+    InstructionInfo::new(Opcode::LBQ, "LBQ", 0xC1, LongBranch, 3, &[], "Long branch if Q = 1"),
+    InstructionInfo::new(Opcode::LBZ, "LBZ", 0xC2, LongBranch, 3, &[], "Long branch if D = 0"),
+    InstructionInfo::new(Opcode::LBDF, "LBDF", 0xC3, LongBranch, 3, &[], "Long branch if DF = 1"),
+    InstructionInfo::new(Opcode::NOP, "NOP", 0xC4, Implied, 3, &[], "No operation"),
+    InstructionInfo::new(Opcode::LSNQ, "LSNQ", 0xC5, Implied, 3, &[], "Long skip if Q = 0: R(P) += 2"),
+    InstructionInfo::new(Opcode::LSNZ, "LSNZ", 0xC6, Implied, 3, &[], "Long skip if D != 0: R(P) += 2"),
+    InstructionInfo::new(Opcode::LSNF, "LSNF", 0xC7, Implied, 3, &[], "Long skip if DF = 0: R(P) += 2"),
+    InstructionInfo::new(Opcode::LSKP, "LSKP", 0xC8, Implied, 3, &[], "Long skip: R(P) += 2"),
+    InstructionInfo::new(Opcode::LBNQ, "LBNQ", 0xC9, LongBranch, 3, &[], "Long branch if Q = 0"),
+    InstructionInfo::new(Opcode::LBNZ, "LBNZ", 0xCA, LongBranch, 3, &[], "Long branch if D != 0"),
+    InstructionInfo::new(Opcode::LBNF, "LBNF", 0xCB, LongBranch, 3, &[], "Long branch if DF = 0"),
+    InstructionInfo::new(Opcode::LSIE, "LSIE", 0xCC, Implied, 3, &[], "Long skip if IE = 1: R(P) += 2"),
+    InstructionInfo::new(Opcode::LSQ, "LSQ", 0xCD, Implied, 3, &[], "Long skip if Q = 1: R(P) += 2"),
+    InstructionInfo::new(Opcode::LSZ, "LSZ", 0xCE, Implied, 3, &[], "Long skip if D = 0: R(P) += 2"),
+    InstructionInfo::new(Opcode::LSDF, "LSDF", 0xCF, Implied, 3, &[], "Long skip if DF = 1: R(P) += 2"),
+    InstructionInfo::new(Opcode::SEP, "SEP", 0xD0, Register, 2, &[], "Set P: P = N (R(N) becomes the program counter)"),
+    InstructionInfo::new(Opcode::SEX, "SEX", 0xE0, Register, 2, &[], "Set X: X = N (R(N) becomes the index register)"),
+    InstructionInfo::new(Opcode::LDX, "LDX", 0xF0, Implied, 2, &[], "Load via X: D = M(R(X))"),
+    InstructionInfo::new(Opcode::OR, "OR", 0xF1, Implied, 2, &[], "Or: D = M(R(X)) | D"),
+    InstructionInfo::new(Opcode::AND, "AND", 0xF2, Implied, 2, &[], "And: D = M(R(X)) & D"),
+    InstructionInfo::new(Opcode::XOR, "XOR", 0xF3, Implied, 2, &[], "Exclusive or: D = M(R(X)) ^ D"),
+    InstructionInfo::new(Opcode::ADD, "ADD", 0xF4, Implied, 2, &[DF], "Add: DF, D = M(R(X)) + D"),
+    InstructionInfo::new(Opcode::SD, "SD", 0xF5, Implied, 2, &[DF], "Subtract D: DF, D = M(R(X)) - D"),
+    InstructionInfo::new(Opcode::SHR, "SHR", 0xF6, Implied, 2, &[DF], "Shift right: D0 -> DF, 0 -> D7"),
+    InstructionInfo::new(Opcode::SM, "SM", 0xF7, Implied, 2, &[DF], "Subtract memory: DF, D = D - M(R(X))"),
+    InstructionInfo::new(Opcode::LDI, "LDI", 0xF8, Immediate, 2, &[], "Load immediate: D = M(R(P)); R(P) += 1"),
+    InstructionInfo::new(Opcode::ORI, "ORI", 0xF9, Immediate, 2, &[], "Or immediate: D = M(R(P)) | D"),
+    InstructionInfo::new(Opcode::ANI, "ANI", 0xFA, Immediate, 2, &[], "And immediate: D = M(R(P)) & D"),
+    InstructionInfo::new(Opcode::XRI, "XRI", 0xFB, Immediate, 2, &[], "Exclusive or immediate: D = M(R(P)) ^ D"),
+    InstructionInfo::new(Opcode::ADI, "ADI", 0xFC, Immediate, 2, &[DF], "Add immediate: DF, D = M(R(P)) + D"),
+    InstructionInfo::new(Opcode::SDI, "SDI", 0xFD, Immediate, 2, &[DF], "Subtract D immediate: DF, D = M(R(P)) - D"),
+    InstructionInfo::new(Opcode::SHL, "SHL", 0xFE, Implied, 2, &[DF], "Shift left: D7 -> DF, 0 -> D0"),
+    InstructionInfo::new(Opcode::SMI, "SMI", 0xFF, Immediate, 2, &[DF], "Subtract memory immediate: DF, D = D - M(R(P))"),
+];
+
+/// Index + 1 into [`INSTRUCTIONS`] of the instruction each byte decodes
+/// as, or 0; built at compile time, which also rejects overlapping entries
+const DECODE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < INSTRUCTIONS.len() {
+        let mut byte = 0;
+        while byte < 256 {
+            if INSTRUCTIONS[i].matches(byte as u8) {
+                assert!(table[byte] == 0, "two instructions share an opcode");
+                table[byte] = i as u8 + 1;
+            }
+            byte += 1;
+        }
+        i += 1;
+    }
+    table
+};
+
+/// The instruction `byte` starts, or `None` if it is not an opcode
+pub fn decode(byte: u8) -> Option<&'static InstructionInfo> {
+    match DECODE[byte as usize] {
+        0 => None,
+        index => Some(&INSTRUCTIONS[index as usize - 1]),
+    }
+}
+
+/// The instruction with this mnemonic, ignoring case
+pub fn lookup(mnemonic: &str) -> Option<&'static InstructionInfo> {
+    INSTRUCTIONS
+        .iter()
+        .find(|i| i.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+/// Markdown reference for the whole instruction set, as kept in
+/// `docs/isa.md`
+pub fn reference() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# RCA 1802 instruction set\n");
+    let _ = writeln!(
+        out,
+        "Generated from `src/cpu/isa.rs`. N is a register number; `port` \
+         is an I/O port 1-7. A cycle is 8 clocks.\n"
+    );
+    let _ = writeln!(
+        out,
+        "| Code | Mnemonic | Operand | Bytes | Cycles | Flags | Description |"
+    );
+    let _ = writeln!(out, "|---|---|---|---|---|---|---|");
+    let mut rows: Vec<&InstructionInfo> = INSTRUCTIONS.iter().collect();
+    rows.sort_by_key(|i| (i.code, i.operand.field_mask() != 0));
+    for info in rows {
+        let flags: Vec<String> = info.flags.iter().map(|f| format!("{:?}", f)).collect();
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            info.encoding(),
+            info.mnemonic,
+            info.operand.name(),
+            info.length,
+            info.cycles,
+            flags.join(", "),
+            info.description.replace('|', "\\|"),
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::cpu::Instruction;
+    use crate::disassembler::disassemble;
+    use crate::formats::Segment;
+
+    #[test]
+    fn test_table_follows_opcode_order() {
+        for (i, info) in INSTRUCTIONS.iter().enumerate() {
+            assert_eq!(info.opcode as usize, i, "{}", info.mnemonic);
+            assert_eq!(format!("{:?}", info.opcode), info.mnemonic);
+        }
+    }
+
+    #[test]
+    fn test_every_byte_decodes_and_encodes() {
+        let undefined: Vec<u8> = (0..=0xFF).filter(|&b| decode(b).is_none()).collect();
+        assert_eq!(undefined, vec![0x68]);
+
+        for byte in (0..=0xFF).filter(|&b| b != 0x68) {
+            let info = decode(byte).unwrap();
+            let bytes = [byte, 0x12, 0x34];
+            let instruction = Instruction::decode(&bytes).unwrap();
+            assert_eq!(instruction.opcode, info.opcode);
+            assert_eq!(
+                instruction.encode(),
+                bytes[..info.length as usize],
+                "{:02X}",
+                byte
+            );
+        }
+        assert_eq!(lookup("shlc").unwrap().length, 1);
+        assert_eq!(lookup("LSKP").unwrap().length, 1);
+    }
+
+    /// Source line for the instruction `byte` starts, with the operand
+    /// bytes 12 34
+    fn source_for(byte: u8) -> String {
+        let info = decode(byte).unwrap();
+        match info.operand {
+            Implied => info.mnemonic.to_string(),
+            Register | RegisterNonZero => format!("{} R{:X}", info.mnemonic, byte & 0x0F),
+            Port => format!("{} {}", info.mnemonic, byte & 0x07),
+            Immediate | ShortBranch => format!("{} 0x12", info.mnemonic),
+            LongBranch => format!("{} 0x1234", info.mnemonic),
+        }
+    }
+
+    #[test]
+    fn test_assembler_and_disassembler_agree_with_table() {
+        for byte in (0..=0xFF).filter(|&b| b != 0x68) {
+            let info = decode(byte).unwrap();
+            let source = source_for(byte);
+            let output = assemble(&source).unwrap();
+            let expected = [byte, 0x12, 0x34];
+            assert_eq!(
+                output.machine_code,
+                expected[..info.length as usize],
+                "{}",
+                source
+            );
+
+            let image = [Segment::new(0, output.machine_code.clone())];
+            let lines = &disassemble(&image, &[0]).segments[0].1;
+            assert!(lines[0].code, "{}", source);
+            assert_eq!(lines[0].text.replace("0x0012", "0x12"), source);
+        }
+    }
+
+    #[test]
+    fn test_reference_is_up_to_date() {
+        let reference = reference();
+        assert!(reference.contains("| 7E | SHLC |  | 1 | 2 | DF |"));
+        assert!(reference.contains("| 6N | OUT | port 1-7 | 1 | 2 |  |"));
+        assert_eq!(include_str!("../../docs/isa.md"), reference);
+    }
+}
@@ -0,0 +1,58 @@
+use super::state::Cpu;
+use crate::formats::{FormatError, Segment, from_binary, from_ihex, from_srec};
+
+impl Cpu {
+    /// Copy each segment into memory at its load address
+    pub fn load_segments(&mut self, segments: &[Segment]) -> Result<(), FormatError> {
+        if let Some(segment) = segments.iter().find(|s| s.end() > Self::MEMORY_SIZE as u32) {
+            return Err(FormatError::AddressOutOfRange(segment.end()));
+        }
+        for segment in segments {
+            let start = segment.address as usize;
+            self.memory[start..start + segment.data.len()].copy_from_slice(&segment.data);
+        }
+        Ok(())
+    }
+
+    /// Load an Intel HEX image
+    pub fn load_ihex(&mut self, text: &str) -> Result<(), FormatError> {
+        self.load_segments(&from_ihex(text)?)
+    }
+
+    /// Load a Motorola S-record image
+    pub fn load_srec(&mut self, text: &str) -> Result<(), FormatError> {
+        self.load_segments(&from_srec(text)?)
+    }
+
+    /// Load a raw binary image at `base`
+    pub fn load_binary(&mut self, bytes: &[u8], base: u16) -> Result<(), FormatError> {
+        self.load_segments(&from_binary(bytes, base)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{to_ihex, to_srec};
+
+    #[test]
+    fn test_load_formats() {
+        let segments = vec![
+            Segment::new(0x0000, vec![0xC0, 0x80, 0x00]),
+            Segment::new(0x8000, vec![0xF8, 0x42, 0x00]),
+        ];
+
+        let mut cpu = Cpu::new();
+        cpu.load_ihex(&to_ihex(&segments)).unwrap();
+        assert_eq!(&cpu.memory[0x8000..0x8003], &[0xF8, 0x42, 0x00]);
+
+        let mut cpu = Cpu::new();
+        cpu.load_srec(&to_srec(&segments, "TEST", 0)).unwrap();
+        assert_eq!(&cpu.memory[0x0000..0x0003], &[0xC0, 0x80, 0x00]);
+
+        let mut cpu = Cpu::new();
+        cpu.load_binary(&[0x7B, 0x00], 0x0100).unwrap();
+        assert_eq!(cpu.memory[0x0100], 0x7B);
+        assert!(cpu.load_binary(&[0; 4], 0xFFFE).is_err());
+    }
+}
@@ -1,7 +1,18 @@
+mod branch;
+mod call_stack;
 pub mod executor;
 pub mod instruction;
+mod io;
+pub mod isa;
+mod loader;
+mod ports;
 pub mod state;
+mod stepping;
 
+pub use call_stack::{CallKind, CallStack, Frame};
 pub use executor::execute_instruction;
 pub use instruction::{Instruction, Opcode};
+pub use isa::{Flag, InstructionInfo, OperandKind};
+pub use ports::Ports;
 pub use state::{Cpu, CpuError};
+pub use stepping::StepStop;
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The data bus as seen by `OUT` and `INP` on ports 1-7
+///
+/// Input is fed ahead of time, a queue per port; reading an empty port
+/// gives 0, as a floating bus would. Output is logged in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ports {
+    /// Bytes waiting on each port, indexed by port number (0 is unused)
+    input: [VecDeque<u8>; 8],
+    /// Every `OUT` as (port, byte)
+    output: Vec<(u8, u8)>,
+}
+
+impl Ports {
+    /// Queue bytes for `INP` on `port`
+    pub fn feed(&mut self, port: u8, bytes: &[u8]) {
+        self.input[(port & 0x07) as usize].extend(bytes);
+    }
+
+    /// Take the next byte waiting on `port`
+    pub fn read(&mut self, port: u8) -> u8 {
+        self.input[(port & 0x07) as usize].pop_front().unwrap_or(0)
+    }
+
+    /// Log a byte written to `port`
+    pub fn write(&mut self, port: u8, byte: u8) {
+        self.output.push((port & 0x07, byte));
+    }
+
+    /// Everything written so far, as (port, byte)
+    pub fn output(&self) -> &[(u8, u8)] {
+        &self.output
+    }
+
+    /// The bytes written to one port
+    pub fn output_on(&self, port: u8) -> Vec<u8> {
+        self.output
+            .iter()
+            .filter(|(p, _)| *p == port)
+            .map(|(_, byte)| *byte)
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
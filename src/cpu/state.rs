@@ -1,3 +1,4 @@
+use super::ports::Ports;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -55,11 +56,15 @@ pub struct Cpu {
     /// CPU halted flag
     pub halted: bool,
 
-    /// Cycle counter
+    /// Machine cycles executed
     pub cycles: u64,
 
     /// Instructions executed
     pub instructions_executed: u64,
+
+    /// I/O ports 1-7 used by OUT and INP
+    #[serde(default)]
+    pub ports: Ports,
 }
 
 impl Cpu {
@@ -93,6 +98,7 @@ impl Cpu {
             halted: false,
             cycles: 0,
             instructions_executed: 0,
+            ports: Ports::default(),
         }
     }
 
@@ -109,6 +115,7 @@ impl Cpu {
         self.halted = false;
         self.cycles = 0;
         self.instructions_executed = 0;
+        self.ports.clear();
     }
 
     /// Get program counter value (value of register selected by P)
@@ -0,0 +1,214 @@
+use super::call_stack::{CallStack, Frame};
+use super::instruction::Instruction;
+use super::state::{Cpu, CpuError};
+
+/// Why a step over or step out stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStop {
+    /// Control is back with the caller (or the instruction made no call)
+    Returned,
+    /// The CPU executed IDL
+    Halted,
+    /// `max_cycles` machine cycles ran out first
+    CycleLimit,
+}
+
+impl CallStack {
+    /// Execute one instruction, recording the call or return it makes
+    pub fn step(&mut self, cpu: &mut Cpu) -> Result<Instruction, CpuError> {
+        self.observe(cpu);
+        cpu.step()
+    }
+
+    /// Execute one instruction; if it calls a subroutine, keep running
+    /// until control is back at the caller's P register and return address
+    ///
+    /// `max_cycles` counts machine cycles, the call included.
+    pub fn step_over(&mut self, cpu: &mut Cpu, max_cycles: u64) -> Result<StepStop, CpuError> {
+        let depth = self.frames().len();
+        let start = cpu.cycles;
+        self.step(cpu)?;
+        match self.frames().get(depth) {
+            Some(&frame) => self.run_until_return(cpu, depth, frame, start + max_cycles),
+            None if cpu.halted => Ok(StepStop::Halted),
+            None => Ok(StepStop::Returned),
+        }
+    }
+
+    /// Run until the current subroutine returns to its caller; with no
+    /// call active, run until halted
+    ///
+    /// `max_cycles` counts machine cycles.
+    pub fn step_out(&mut self, cpu: &mut Cpu, max_cycles: u64) -> Result<StepStop, CpuError> {
+        match self.frames().last() {
+            Some(&frame) => {
+                let depth = self.frames().len() - 1;
+                self.run_until_return(cpu, depth, frame, cpu.cycles + max_cycles)
+            }
+            None => {
+                let end = cpu.cycles + max_cycles;
+                while !cpu.halted {
+                    if cpu.cycles >= end {
+                        return Ok(StepStop::CycleLimit);
+                    }
+                    self.step(cpu)?;
+                }
+                Ok(StepStop::Halted)
+            }
+        }
+    }
+
+    /// Step until `frame` has been popped back to `depth` and the CPU is at
+    /// its return address with the caller's P, or the CPU's cycle count
+    /// reaches `end`
+    fn run_until_return(
+        &mut self,
+        cpu: &mut Cpu,
+        depth: usize,
+        frame: Frame,
+        end: u64,
+    ) -> Result<StepStop, CpuError> {
+        loop {
+            if cpu.halted {
+                return Ok(StepStop::Halted);
+            }
+            if self.frames().len() <= depth
+                && cpu.p == frame.caller
+                && cpu.get_pc() == frame.return_address
+            {
+                return Ok(StepStop::Returned);
+            }
+            if cpu.cycles >= end {
+                return Ok(StepStop::CycleLimit);
+            }
+            self.step(cpu)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    fn load(source: &str) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&assemble(source).unwrap().machine_code, 0)
+            .unwrap();
+        cpu
+    }
+
+    /// A SEP-style subroutine in R7 that counts R8 and loops a while
+    const SEP_CALL: &str = r#"
+        LDI HIGH(SUB)
+        PHI R7
+        LDI LOW(SUB)
+        PLO R7
+        SEP R7
+        SEP R7
+        IDL
+EXIT:   SEP R0
+SUB:    INC R8
+        LDI 0x10
+WAIT:   SMI 1
+        BNZ WAIT
+        BR EXIT
+"#;
+
+    #[test]
+    fn test_step_over_sep_call() {
+        let mut cpu = load(SEP_CALL);
+        let mut stack = CallStack::default();
+        for _ in 0..4 {
+            assert_eq!(stack.step_over(&mut cpu, 1000).unwrap(), StepStop::Returned);
+        }
+        assert_eq!(cpu.get_pc(), 6);
+
+        // Each SEP R7 runs the whole subroutine
+        assert_eq!(stack.step_over(&mut cpu, 1000).unwrap(), StepStop::Returned);
+        assert_eq!((cpu.p, cpu.get_pc(), cpu.registers[8]), (0, 7, 1));
+        assert!(stack.frames().is_empty());
+        assert_eq!(stack.step_over(&mut cpu, 1000).unwrap(), StepStop::Returned);
+        assert_eq!((cpu.get_pc(), cpu.registers[8]), (8, 2));
+        assert_eq!(stack.step_over(&mut cpu, 1000).unwrap(), StepStop::Halted);
+
+        let mut cpu = load(SEP_CALL);
+        let mut stack = CallStack::default();
+        for _ in 0..4 {
+            stack.step(&mut cpu).unwrap();
+        }
+        assert_eq!(stack.step_over(&mut cpu, 10).unwrap(), StepStop::CycleLimit);
+        assert_eq!(cpu.p, 7);
+    }
+
+    #[test]
+    fn test_limit_counts_machine_cycles() {
+        // The call and subroutine take 37 instructions and 74 machine cycles
+        let run = |max_cycles| {
+            let mut cpu = load(SEP_CALL);
+            let mut stack = CallStack::default();
+            for _ in 0..4 {
+                stack.step(&mut cpu).unwrap();
+            }
+            let start = (cpu.cycles, cpu.instructions_executed);
+            let stop = stack.step_over(&mut cpu, max_cycles).unwrap();
+            (
+                stop,
+                cpu.cycles - start.0,
+                cpu.instructions_executed - start.1,
+            )
+        };
+        assert_eq!(run(74), (StepStop::Returned, 74, 37));
+        assert_eq!(run(50), (StepStop::CycleLimit, 50, 25));
+    }
+
+    #[test]
+    fn test_step_out() {
+        let mut cpu = load(SEP_CALL);
+        let mut stack = CallStack::default();
+        for _ in 0..6 {
+            stack.step(&mut cpu).unwrap();
+        }
+        assert_eq!(cpu.p, 7);
+        assert_eq!(stack.step_out(&mut cpu, 1000).unwrap(), StepStop::Returned);
+        assert_eq!((cpu.p, cpu.get_pc()), (0, 7));
+
+        // Nothing left to return from: run to the end
+        assert_eq!(stack.step_out(&mut cpu, 1000).unwrap(), StepStop::Halted);
+        assert_eq!(cpu.registers[8], 2);
+    }
+
+    #[test]
+    fn test_step_over_scrt_call() {
+        let source = r#"
+        CALL SUB
+        CALL SUB
+        IDL
+SUB:    INC R8
+        CALL LEAF
+        RETN
+LEAF:   INC R9
+        RETN
+"#;
+        let mut cpu = load(source);
+        let mut stack = CallStack::default();
+        while !(cpu.p == 3 && cpu.memory[cpu.get_pc() as usize] == 0xD4) {
+            stack.step(&mut cpu).unwrap();
+        }
+        let call = cpu.get_pc();
+        let depth = stack.frames().len();
+        assert_eq!(stack.step_over(&mut cpu, 1000).unwrap(), StepStop::Returned);
+        assert_eq!((cpu.p, cpu.get_pc()), (3, call + 3));
+        assert_eq!((cpu.registers[8], cpu.registers[9]), (1, 1));
+        assert_eq!(stack.frames().len(), depth);
+
+        // Into the second call, then out of it
+        stack.step(&mut cpu).unwrap();
+        while cpu.p != 3 {
+            stack.step(&mut cpu).unwrap();
+        }
+        assert_eq!(stack.step_out(&mut cpu, 1000).unwrap(), StepStop::Returned);
+        assert_eq!((cpu.p, cpu.get_pc()), (3, call + 6));
+        assert_eq!((cpu.registers[8], cpu.registers[9]), (2, 2));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::cpu::isa::{self, OperandKind};
+use crate::formats::Segment;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Sparse view of a memory image: `None` where no segment supplies a byte
+pub(super) struct Image {
+    bytes: Vec<Option<u8>>,
+}
+
+impl Image {
+    pub fn new(segments: &[Segment]) -> Self {
+        let mut bytes = vec![None; 0x10000];
+        for segment in segments {
+            for (i, &byte) in segment.data.iter().enumerate() {
+                if let Some(slot) = bytes.get_mut(segment.address as usize + i) {
+                    *slot = Some(byte);
+                }
+            }
+        }
+        Self { bytes }
+    }
+
+    /// The `length` bytes at `address`, if all of them are present
+    pub fn read(&self, address: u16, length: u16) -> Option<Vec<u8>> {
+        let start = address as usize;
+        self.bytes
+            .get(start..start + length as usize)?
+            .iter()
+            .copied()
+            .collect()
+    }
+}
+
+/// Which bytes of an image were reached as code
+#[derive(Debug, Default)]
+pub(super) struct Flow {
+    /// Start address and length of every instruction found
+    pub code: BTreeMap<u16, u16>,
+    /// Entry points plus every branch and call target
+    pub targets: BTreeSet<u16>,
+}
+
+/// Instruction length, or `None` for bytes that are not valid 1802
+/// opcodes
+pub(super) fn length(opcode: u8) -> Option<u16> {
+    isa::decode(opcode).map(|info| info.length as u16)
+}
+
+/// Is this a long skip (C5-C8, CC-CF)?
+pub(super) fn is_long_skip(opcode: u8) -> bool {
+    matches!(opcode, 0xC5..=0xC8 | 0xCC..=0xCF)
+}
+
+/// Is this a short branch (30-3F except SKP)?
+pub(super) fn is_short_branch(opcode: u8) -> bool {
+    isa::decode(opcode).is_some_and(|info| info.operand == OperandKind::ShortBranch)
+}
+
+/// Is this a long branch (C0-C3, C9-CB)?
+pub(super) fn is_long_branch(opcode: u8) -> bool {
+    isa::decode(opcode).is_some_and(|info| info.operand == OperandKind::LongBranch)
+}
+
+/// Target of a short branch at `address`: the operand byte's page
+pub(super) fn short_target(address: u16, operand: u8) -> u16 {
+    (address.wrapping_add(1) & 0xFF00) | operand as u16
+}
+
+/// Constant values known to be in D and the registers along one path
+///
+/// Only as much as needed to spot the `LDI hi / PHI Rn / LDI lo / PLO Rn /
+/// SEP Rn` call idiom.
+#[derive(Debug, Clone, Copy, Default)]
+struct Known {
+    d: Option<u8>,
+    x: Option<u8>,
+    high: [Option<u8>; 16],
+    low: [Option<u8>; 16],
+}
+
+impl Known {
+    fn register(&self, n: usize) -> Option<u16> {
+        Some(u16::from_be_bytes([self.high[n]?, self.low[n]?]))
+    }
+
+    fn forget(&mut self, n: usize) {
+        self.high[n] = None;
+        self.low[n] = None;
+    }
+
+    /// Forget R(X), or every register if X is not known
+    fn forget_x(&mut self) {
+        match self.x {
+            Some(x) => self.forget(x as usize),
+            None => {
+                *self = Known {
+                    d: self.d,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        let n = (bytes[0] & 0x0F) as usize;
+        match bytes[0] {
+            0xF8 => self.d = Some(bytes[1]),
+            0x80..=0x8F => self.d = self.low[n],
+            0x90..=0x9F => self.d = self.high[n],
+            0xA0..=0xAF => self.low[n] = self.d,
+            0xB0..=0xBF => self.high[n] = self.d,
+            0xE0..=0xEF => self.x = Some(n as u8),
+            0x10..=0x2F => self.forget(n),
+            0x40..=0x4F => {
+                self.forget(n);
+                self.d = None;
+            }
+            0x60..=0x67 | 0x72 | 0x73 => {
+                self.forget_x();
+                self.d = None;
+            }
+            0x79 => {
+                self.forget(2);
+                self.x = None;
+                self.d = None;
+            }
+            0x50..=0x5F | 0x7A | 0x7B | 0xC4 => {}
+            _ => self.d = None,
+        }
+    }
+}
+
+/// Trace execution from `entry_points`, recording each instruction reached
+///
+/// Branch targets are followed along with the fall-through path. `SEP Rn`
+/// is treated as a call: execution is assumed to resume after it (the
+/// usual way an 1802 subroutine returns), and when Rn was loaded with a
+/// constant just before, that address is traced as well.
+pub(super) fn trace(image: &Image, entry_points: &[u16]) -> Flow {
+    let mut flow = Flow::default();
+    let mut claimed = vec![false; 0x10000];
+    let mut pending: Vec<(u16, Known)> = entry_points
+        .iter()
+        .map(|&address| (address, Known::default()))
+        .collect();
+    flow.targets.extend(entry_points);
+
+    while let Some((mut address, mut known)) = pending.pop() {
+        loop {
+            if flow.code.contains_key(&address) {
+                break;
+            }
+            let Some(opcode) = image.read(address, 1).map(|b| b[0]) else {
+                break;
+            };
+            let Some(len) = length(opcode) else {
+                break;
+            };
+            let end = address as usize + len as usize;
+            if end > claimed.len() || claimed[address as usize..end].iter().any(|&c| c) {
+                break;
+            }
+            let Some(bytes) = image.read(address, len) else {
+                break;
+            };
+
+            claimed[address as usize..end].fill(true);
+            flow.code.insert(address, len);
+            let next = address.wrapping_add(len);
+
+            let mut target = None;
+            let mut falls_through = true;
+            match opcode {
+                // IDL halts; RET and DIS return from an interrupt
+                0x00 | 0x70 | 0x71 => falls_through = false,
+                0x30 => {
+                    target = Some(short_target(address, bytes[1]));
+                    falls_through = false;
+                }
+                0xC0 => {
+                    target = Some(u16::from_be_bytes([bytes[1], bytes[2]]));
+                    falls_through = false;
+                }
+                op if is_short_branch(op) => target = Some(short_target(address, bytes[1])),
+                op if is_long_branch(op) => target = Some(u16::from_be_bytes([bytes[1], bytes[2]])),
+                0x38 => {
+                    pending.push((address.wrapping_add(2), known));
+                    falls_through = false;
+                }
+                0xC8 => {
+                    pending.push((address.wrapping_add(3), known));
+                    falls_through = false;
+                }
+                op if is_long_skip(op) => pending.push((address.wrapping_add(3), known)),
+                0xD0..=0xDF => {
+                    if let Some(callee) = known.register((opcode & 0x0F) as usize) {
+                        flow.targets.insert(callee);
+                        pending.push((callee, Known::default()));
+                    }
+                    known = Known::default();
+                }
+                _ => known.update(&bytes),
+            }
+
+            if let Some(target) = target {
+                flow.targets.insert(target);
+                pending.push((target, Known::default()));
+            }
+            if !falls_through {
+                break;
+            }
+            address = next;
+        }
+    }
+
+    flow
+}
@@ -0,0 +1,306 @@
+//! RCA 1802 (COSMAC) disassembler
+//!
+//! Execution is traced from one or more entry points so that only bytes
+//! actually reached as instructions are shown as code; everything else is
+//! emitted as `DB` data. Branch and call targets get invented labels and
+//! the result is source the assembler turns back into the same bytes.
+
+mod flow;
+
+use crate::cpu::isa::{self, OperandKind};
+use crate::formats::{Segment, merge_segments};
+use flow::{Image, short_target, trace};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Most bytes shown in a single `DB` line of hex values
+const BYTES_PER_DATA_LINE: usize = 8;
+
+/// Shortest run of printable characters emitted as a string
+const MIN_STRING: usize = 4;
+
+/// One line of disassembled output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisassembledLine {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    /// Label defined on this line, if anything branches or calls here
+    pub label: Option<String>,
+    /// Assembler source for the bytes (an instruction or a `DB`)
+    pub text: String,
+    /// Whether the bytes were reached as an instruction
+    pub code: bool,
+}
+
+/// A disassembled image
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disassembly {
+    /// Segments in address order; each starts with its load address
+    pub segments: Vec<(u16, Vec<DisassembledLine>)>,
+    /// Invented labels by address
+    pub labels: BTreeMap<u16, String>,
+}
+
+impl Disassembly {
+    /// Render reassemblable source with an `ORG` per segment
+    ///
+    /// Each line carries a comment with its address and bytes.
+    pub fn source(&self) -> String {
+        let mut out = String::new();
+        for (address, lines) in &self.segments {
+            let _ = writeln!(out, "        ORG 0x{:04X}", address);
+            for line in lines {
+                let label = line
+                    .label
+                    .as_ref()
+                    .map_or(String::new(), |l| format!("{}:", l));
+                let code = format!("{:<7} {}", label, line.text);
+                let _ = writeln!(
+                    out,
+                    "{:<32}; {:04X}  {}",
+                    code,
+                    line.address,
+                    hex_bytes(&line.bytes)
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Disassemble `segments`, tracing code from `entry_points`
+///
+/// ```
+/// use rca_1802_emulator::disassembler::disassemble;
+/// use rca_1802_emulator::formats::Segment;
+///
+/// let image = [Segment::new(0, vec![0xF8, 0x05, 0x30, 0x00])];
+/// let disassembly = disassemble(&image, &[0]);
+/// assert!(disassembly.source().contains("L0000:  LDI 0x05"));
+/// assert!(disassembly.source().contains("BR L0000"));
+/// ```
+pub fn disassemble(segments: &[Segment], entry_points: &[u16]) -> Disassembly {
+    let segments = merge_segments(segments.to_vec());
+    let image = Image::new(&segments);
+    let flow = trace(&image, entry_points);
+
+    let labels: BTreeMap<u16, String> = flow
+        .targets
+        .iter()
+        .filter(|address| flow.code.contains_key(address))
+        .map(|&address| (address, format!("L{:04X}", address)))
+        .collect();
+
+    let mut disassembly = Disassembly {
+        segments: Vec::new(),
+        labels,
+    };
+
+    for segment in &segments {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        while offset < segment.data.len() {
+            let address = segment.address.wrapping_add(offset as u16);
+            let rest = &segment.data[offset..];
+
+            let line = match flow.code.get(&address) {
+                Some(&length) if length as usize <= rest.len() => {
+                    let bytes = rest[..length as usize].to_vec();
+                    DisassembledLine {
+                        address,
+                        text: instruction_text(&bytes, address, &disassembly.labels),
+                        label: disassembly.labels.get(&address).cloned(),
+                        bytes,
+                        code: true,
+                    }
+                }
+                _ => {
+                    // Data runs up to the next instruction
+                    let run = (1..rest.len())
+                        .find(|&i| flow.code.contains_key(&address.wrapping_add(i as u16)))
+                        .unwrap_or(rest.len());
+                    let bytes = data_line(&rest[..run]).to_vec();
+                    DisassembledLine {
+                        address,
+                        text: data_text(&bytes),
+                        label: None,
+                        bytes,
+                        code: false,
+                    }
+                }
+            };
+            offset += line.bytes.len();
+            lines.push(line);
+        }
+        disassembly.segments.push((segment.address, lines));
+    }
+
+    disassembly
+}
+
+/// Assembler text for one decoded instruction
+fn instruction_text(bytes: &[u8], address: u16, labels: &BTreeMap<u16, String>) -> String {
+    let opcode = bytes[0];
+    let name = |target: u16| {
+        labels
+            .get(&target)
+            .cloned()
+            .unwrap_or_else(|| format!("0x{:04X}", target))
+    };
+
+    let Some(info) = isa::decode(opcode) else {
+        return data_text(bytes);
+    };
+    let mnemonic = info.mnemonic;
+    match info.operand {
+        OperandKind::Implied => mnemonic.to_string(),
+        OperandKind::Register | OperandKind::RegisterNonZero => {
+            format!("{} R{:X}", mnemonic, opcode & 0x0F)
+        }
+        OperandKind::Port => format!("{} {}", mnemonic, opcode & 0x07),
+        OperandKind::Immediate => format!("{} 0x{:02X}", mnemonic, bytes[1]),
+        OperandKind::ShortBranch => {
+            format!("{} {}", mnemonic, name(short_target(address, bytes[1])))
+        }
+        OperandKind::LongBranch => {
+            format!(
+                "{} {}",
+                mnemonic,
+                name(u16::from_be_bytes([bytes[1], bytes[2]]))
+            )
+        }
+    }
+}
+
+/// The leading bytes of a data run that fit on one `DB` line
+///
+/// Printable text becomes a string; other bytes are grouped in eights,
+/// stopping early where a string begins.
+fn data_line(run: &[u8]) -> &[u8] {
+    let text = run.iter().take_while(|&&b| is_text(b)).count();
+    if text >= MIN_STRING {
+        return &run[..text];
+    }
+
+    let mut end = 1;
+    while end < run.len().min(BYTES_PER_DATA_LINE) {
+        let ahead = run[end..].iter().take_while(|&&b| is_text(b)).count();
+        if ahead >= MIN_STRING {
+            break;
+        }
+        end += 1;
+    }
+    &run[..end]
+}
+
+/// `DB` source for bytes chosen by [`data_line`]
+fn data_text(bytes: &[u8]) -> String {
+    if bytes.len() >= MIN_STRING && bytes.iter().all(|&b| is_text(b)) {
+        return format!("DB \"{}\"", String::from_utf8_lossy(bytes));
+    }
+    let values: Vec<String> = bytes.iter().map(|b| format!("0x{:02X}", b)).collect();
+    format!("DB {}", values.join(", "))
+}
+
+/// Printable ASCII that can sit inside a "..." literal
+fn is_text(byte: u8) -> bool {
+    (0x20..0x7F).contains(&byte) && byte != b'"'
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    fn round_trip(source: &str, entry_points: &[u16]) -> Disassembly {
+        let original = assemble(source).unwrap();
+        let disassembly = disassemble(&original.segments(), entry_points);
+        let reassembled = assemble(&disassembly.source()).unwrap();
+        assert_eq!(reassembled.machine_code, original.machine_code);
+        disassembly
+    }
+
+    #[test]
+    fn test_round_trip_separates_code_and_data() {
+        let source = r#"
+START: LDI 0x03
+       PLO R7
+LOOP:  DEC R7
+       GLO R7
+       BNZ LOOP
+       LBR DONE
+MSG:   DB "HELLO, WORLD", 0x0D, 0x0A, 0
+TABLE: DW 0x1234, 0xFFFF
+DONE:  SEQ
+       IDL
+"#;
+        let disassembly = round_trip(source, &[0]);
+        let text = disassembly.source();
+
+        assert!(text.contains("L0003:  DEC R7"));
+        assert!(text.contains("BNZ L0003"));
+        assert!(text.contains("LBR L001D"));
+        assert!(text.contains("DB \"HELLO, WORLD\""));
+        assert!(text.contains("DB 0x0D, 0x0A, 0x00, 0x12, 0x34, 0xFF, 0xFF"));
+        assert!(text.contains("L001D:  SEQ"));
+    }
+
+    #[test]
+    fn test_follows_sep_calls_and_skips() {
+        let source = r#"
+       LDI 0x01
+       PHI R4
+       LDI 0x00
+       PLO R4
+       SEP R4
+       OUT 3
+       DB 0xCE
+       REQ
+       SEQ
+       IDL
+       DB 0x99
+       ORG 0x100
+SUB:   INP 2
+       SEP R0
+"#;
+        let disassembly = round_trip(source, &[0]);
+        let text = disassembly.source();
+
+        assert!(text.contains("L0100:  INP 2"));
+        assert!(text.contains("OUT 3"));
+        let skip = &disassembly.segments[0].1[6];
+        assert_eq!(
+            (skip.address, skip.text.as_str(), skip.code),
+            (8, "LSZ", true)
+        );
+        assert!(text.contains("REQ"));
+        assert!(text.contains("DB 0x99"));
+        assert!(!disassembly.segments[0].1.last().unwrap().code);
+    }
+
+    #[test]
+    fn test_unreached_bytes_are_data() {
+        let disassembly = round_trip("IDL\nLDI 5\nBR 0x03", &[0]);
+        let lines = &disassembly.segments[0].1;
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "IDL");
+        assert_eq!(lines[1].text, "DB 0xF8, 0x05, 0x30, 0x03");
+    }
+
+    #[test]
+    fn test_branch_into_instruction_uses_address() {
+        // The branch lands on the operand of LDI, which is already code
+        let disassembly = round_trip("LDI 0x30\nBR 0x01", &[0]);
+        assert!(disassembly.source().contains("BR 0x0001"));
+    }
+}
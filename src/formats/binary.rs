@@ -0,0 +1,66 @@
+use super::{FormatError, Segment};
+
+/// Flatten segments into a raw binary image starting at `base`
+///
+/// Gaps between segments are filled with zeros. Every segment must lie at
+/// or above `base`.
+pub fn to_binary(segments: &[Segment], base: u16) -> Result<Vec<u8>, FormatError> {
+    let mut image = Vec::new();
+
+    for segment in segments {
+        if segment.address < base {
+            return Err(FormatError::BelowBase {
+                address: segment.address,
+                base,
+            });
+        }
+        let offset = (segment.address - base) as usize;
+        let end = offset + segment.data.len();
+        if end > image.len() {
+            image.resize(end, 0);
+        }
+        image[offset..end].copy_from_slice(&segment.data);
+    }
+
+    Ok(image)
+}
+
+/// Treat a raw binary image as a single segment loaded at `base`
+pub fn from_binary(bytes: &[u8], base: u16) -> Result<Vec<Segment>, FormatError> {
+    let end = base as u32 + bytes.len() as u32;
+    if end > 0x10000 {
+        return Err(FormatError::AddressOutOfRange(end));
+    }
+    Ok(vec![Segment::new(base, bytes.to_vec())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_fills_gaps() {
+        let segments = vec![
+            Segment::new(0x0100, vec![1, 2]),
+            Segment::new(0x0104, vec![3]),
+        ];
+
+        assert_eq!(to_binary(&segments, 0x0100).unwrap(), vec![1, 2, 0, 0, 3]);
+        assert_eq!(
+            to_binary(&segments, 0x0101),
+            Err(FormatError::BelowBase {
+                address: 0x0100,
+                base: 0x0101
+            })
+        );
+    }
+
+    #[test]
+    fn test_binary_reader() {
+        assert_eq!(
+            from_binary(&[1, 2, 3], 0x8000).unwrap(),
+            vec![Segment::new(0x8000, vec![1, 2, 3])]
+        );
+        assert!(from_binary(&[0; 2], 0xFFFF).is_err());
+    }
+}
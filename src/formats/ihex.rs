@@ -0,0 +1,153 @@
+use super::{FormatError, Segment, merge_segments, parse_hex_bytes, records};
+
+/// Intel HEX record types
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+/// Write segments as Intel HEX (16 data bytes per record)
+///
+/// ```
+/// use rca_1802_emulator::formats::{Segment, to_ihex};
+///
+/// let hex = to_ihex(&[Segment::new(0x0000, vec![0xF8, 0x42, 0x00])]);
+/// assert_eq!(hex, ":03000000F84200C3\n:00000001FF\n");
+/// ```
+pub fn to_ihex(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for (address, chunk) in records(segments) {
+        out.push_str(&record(DATA, address, chunk));
+    }
+    out.push_str(&record(END_OF_FILE, 0, &[]));
+    out
+}
+
+/// Read Intel HEX into merged segments
+///
+/// Extended address records are accepted as long as every byte still lands
+/// in the 1802's 64KB address space. Start address records are ignored.
+pub fn from_ihex(text: &str) -> Result<Vec<Segment>, FormatError> {
+    let mut segments = Vec::new();
+    let mut base: u32 = 0;
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+
+        let invalid = |message: &str| FormatError::InvalidRecord {
+            line,
+            message: message.to_string(),
+        };
+
+        let hex = raw
+            .strip_prefix(':')
+            .ok_or_else(|| invalid("record must start with ':'"))?;
+        let bytes = parse_hex_bytes(hex, line)?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(invalid("record length does not match byte count"));
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 1);
+        let expected = checksum_of(body);
+        if expected != checksum[0] {
+            return Err(FormatError::Checksum {
+                line,
+                expected,
+                found: checksum[0],
+            });
+        }
+
+        let offset = u16::from_be_bytes([body[1], body[2]]) as u32;
+        let data = &body[4..];
+        match body[3] {
+            DATA => {
+                let address = base + offset;
+                if address + data.len() as u32 > 0x10000 {
+                    return Err(FormatError::AddressOutOfRange(address + data.len() as u32));
+                }
+                segments.push(Segment::new(address as u16, data.to_vec()));
+            }
+            END_OF_FILE => return Ok(merge_segments(segments)),
+            EXTENDED_SEGMENT_ADDRESS if data.len() == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4;
+            }
+            EXTENDED_LINEAR_ADDRESS if data.len() == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16;
+            }
+            START_SEGMENT_ADDRESS | START_LINEAR_ADDRESS => {}
+            kind => return Err(invalid(&format!("unsupported record type {:02X}", kind))),
+        }
+    }
+
+    Err(FormatError::MissingEnd)
+}
+
+/// Format one record: `:LLAAAATT<data>CC`
+fn record(kind: u8, address: u16, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend(address.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+    bytes.push(checksum_of(&bytes));
+
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!(":{}\n", hex)
+}
+
+/// Two's complement of the sum of all bytes
+fn checksum_of(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |sum, b| sum.wrapping_add(*b))
+        .wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ihex_round_trip_multi_segment() {
+        let segments = vec![
+            Segment::new(0x0000, (0..20).collect()),
+            Segment::new(0x8000, vec![0xC0, 0x00, 0x00]),
+        ];
+        let hex = to_ihex(&segments);
+
+        assert_eq!(hex.lines().count(), 4);
+        assert!(hex.starts_with(":10000000000102030405060708090A0B0C0D0E0F78\n"));
+        assert_eq!(from_ihex(&hex).unwrap(), segments);
+    }
+
+    #[test]
+    fn test_ihex_errors() {
+        assert!(matches!(
+            from_ihex(":03000000F8420004\n:00000001FF"),
+            Err(FormatError::Checksum { line: 1, .. })
+        ));
+        assert_eq!(from_ihex(":03000000F84200C3"), Err(FormatError::MissingEnd));
+        assert!(matches!(
+            from_ihex("03000000F8420003"),
+            Err(FormatError::InvalidRecord { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_ihex_extended_address() {
+        // Extended linear address 0x0001 puts data above 64KB
+        let hex = ":020000040001F9\n:0100000042BD\n:00000001FF\n";
+        assert_eq!(from_ihex(hex), Err(FormatError::AddressOutOfRange(0x10001)));
+
+        let hex = ":020000040000FA\n:0110000042AD\n:00000001FF\n";
+        assert_eq!(
+            from_ihex(hex).unwrap(),
+            vec![Segment::new(0x1000, vec![0x42])]
+        );
+    }
+}
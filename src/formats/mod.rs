@@ -0,0 +1,137 @@
+//! Program image file formats
+//!
+//! Programs are exchanged as lists of [`Segment`]s: contiguous runs of bytes
+//! at a load address. Each format has a writer producing text (or bytes)
+//! from segments and a reader turning it back into segments.
+
+mod binary;
+mod ihex;
+mod srec;
+
+pub use binary::{from_binary, to_binary};
+pub use ihex::{from_ihex, to_ihex};
+pub use srec::{from_srec, to_srec};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Data bytes written per Intel HEX or S-record line
+const BYTES_PER_RECORD: usize = 16;
+
+/// Errors reading or writing program images
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    #[error("Line {line}: {message}")]
+    InvalidRecord { line: usize, message: String },
+
+    #[error("Line {line}: checksum mismatch (expected {expected:#04X}, found {found:#04X})")]
+    Checksum {
+        line: usize,
+        expected: u8,
+        found: u8,
+    },
+
+    #[error("Address {0:#X} is outside the 64KB address space")]
+    AddressOutOfRange(u32),
+
+    #[error("Segment at {address:#06X} lies below the base address {base:#06X}")]
+    BelowBase { address: u16, base: u16 },
+
+    #[error("Missing end-of-file record")]
+    MissingEnd,
+}
+
+/// A contiguous run of bytes at a load address
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    pub address: u16,
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    pub fn new(address: u16, data: Vec<u8>) -> Self {
+        Self { address, data }
+    }
+
+    /// One past the last address covered (may be 0x10000)
+    pub fn end(&self) -> u32 {
+        self.address as u32 + self.data.len() as u32
+    }
+}
+
+/// Sort segments by address and merge those that touch or overlap
+///
+/// Where segments overlap, the one starting at the higher address wins.
+pub fn merge_segments(mut segments: Vec<Segment>) -> Vec<Segment> {
+    segments.sort_by_key(|s| s.address);
+    let mut merged: Vec<Segment> = Vec::new();
+
+    for segment in segments.into_iter().filter(|s| !s.data.is_empty()) {
+        match merged.last_mut() {
+            Some(last) if segment.address as u32 <= last.end() => {
+                let offset = (segment.address - last.address) as usize;
+                let end = offset + segment.data.len();
+                if end > last.data.len() {
+                    last.data.resize(end, 0);
+                }
+                last.data[offset..end].copy_from_slice(&segment.data);
+            }
+            _ => merged.push(segment),
+        }
+    }
+
+    merged
+}
+
+/// Split segments into record-sized chunks of (address, bytes)
+fn records(segments: &[Segment]) -> impl Iterator<Item = (u16, &[u8])> {
+    segments.iter().flat_map(|segment| {
+        segment
+            .data
+            .chunks(BYTES_PER_RECORD)
+            .enumerate()
+            .map(move |(i, chunk)| {
+                let address = segment.address.wrapping_add((i * BYTES_PER_RECORD) as u16);
+                (address, chunk)
+            })
+    })
+}
+
+/// Decode a string of hex digit pairs
+fn parse_hex_bytes(text: &str, line: usize) -> Result<Vec<u8>, FormatError> {
+    let invalid = |message: &str| FormatError::InvalidRecord {
+        line,
+        message: message.to_string(),
+    };
+
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return Err(invalid("odd number of hex digits"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| invalid("invalid hex digit")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_segments() {
+        let merged = merge_segments(vec![
+            Segment::new(0x0104, vec![5, 6]),
+            Segment::new(0x0100, vec![1, 2, 3, 4]),
+            Segment::new(0x0200, vec![9]),
+            Segment::new(0x0101, vec![7]),
+        ]);
+
+        assert_eq!(
+            merged,
+            vec![
+                Segment::new(0x0100, vec![1, 7, 3, 4, 5, 6]),
+                Segment::new(0x0200, vec![9]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use super::{FormatError, Segment, merge_segments, parse_hex_bytes, records};
+
+/// Write segments as Motorola S-records
+///
+/// Output is an S0 header carrying `header`, S1 data records with 16-bit
+/// addresses, an S5 record count and an S9 terminator with `entry`.
+pub fn to_srec(segments: &[Segment], header: &str, entry: u16) -> String {
+    let mut out = record('0', &[0, 0], header.as_bytes());
+    let mut count = 0u16;
+    for (address, chunk) in records(segments) {
+        out.push_str(&record('1', &address.to_be_bytes(), chunk));
+        count = count.wrapping_add(1);
+    }
+    out.push_str(&record('5', &count.to_be_bytes(), &[]));
+    out.push_str(&record('9', &entry.to_be_bytes(), &[]));
+    out
+}
+
+/// Read Motorola S-records into merged segments
+///
+/// S1, S2 and S3 data records are accepted as long as every byte lands in
+/// the 64KB address space. Header and count records are checked only for
+/// well-formedness.
+pub fn from_srec(text: &str) -> Result<Vec<Segment>, FormatError> {
+    let mut segments = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+
+        let invalid = |message: &str| FormatError::InvalidRecord {
+            line,
+            message: message.to_string(),
+        };
+
+        let mut chars = raw.chars();
+        if chars.next() != Some('S') {
+            return Err(invalid("record must start with 'S'"));
+        }
+        let kind = chars.next().ok_or_else(|| invalid("missing record type"))?;
+        let bytes = parse_hex_bytes(chars.as_str(), line)?;
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(invalid("record length does not match byte count"));
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 1);
+        let expected = checksum_of(body);
+        if expected != checksum[0] {
+            return Err(FormatError::Checksum {
+                line,
+                expected,
+                found: checksum[0],
+            });
+        }
+
+        let address_len = match kind {
+            '0' | '1' | '5' | '9' => 2,
+            '2' | '6' | '8' => 3,
+            '3' | '7' => 4,
+            _ => return Err(invalid(&format!("unsupported record type S{}", kind))),
+        };
+        if body.len() < 1 + address_len {
+            return Err(invalid("record too short for its address"));
+        }
+
+        let address = body[1..1 + address_len]
+            .iter()
+            .fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        let data = &body[1 + address_len..];
+
+        match kind {
+            '1' | '2' | '3' => {
+                let end = address + data.len() as u32;
+                if end > 0x10000 {
+                    return Err(FormatError::AddressOutOfRange(end));
+                }
+                segments.push(Segment::new(address as u16, data.to_vec()));
+            }
+            '7' | '8' | '9' => return Ok(merge_segments(segments)),
+            _ => {}
+        }
+    }
+
+    Err(FormatError::MissingEnd)
+}
+
+/// Format one record: `S<type><count><address><data><checksum>`
+fn record(kind: char, address: &[u8], data: &[u8]) -> String {
+    let mut bytes = vec![(address.len() + data.len() + 1) as u8];
+    bytes.extend_from_slice(address);
+    bytes.extend_from_slice(data);
+    bytes.push(checksum_of(&bytes));
+
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("S{}{}\n", kind, hex)
+}
+
+/// Ones' complement of the low byte of the sum of all bytes
+fn checksum_of(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srec_format() {
+        let srec = to_srec(&[Segment::new(0x0000, vec![0xF8, 0x42, 0x00])], "HI", 0);
+        let lines: Vec<&str> = srec.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "S0050000484969",
+                "S1060000F84200BF",
+                "S5030001FB",
+                "S9030000FC"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_srec_round_trip_multi_segment() {
+        let segments = vec![
+            Segment::new(0x0010, (0..40).collect()),
+            Segment::new(0xFFF0, vec![0xAA; 16]),
+        ];
+        let srec = to_srec(&segments, "", 0x0010);
+        assert_eq!(from_srec(&srec).unwrap(), segments);
+    }
+
+    #[test]
+    fn test_srec_errors() {
+        assert!(matches!(
+            from_srec("S1060000F84200BE\nS9030000FC"),
+            Err(FormatError::Checksum { line: 1, .. })
+        ));
+        assert_eq!(from_srec("S1060000F84200BF"), Err(FormatError::MissingEnd));
+        assert_eq!(
+            from_srec("S2060100000000F8\nS9030000FC"),
+            Err(FormatError::AddressOutOfRange(0x10002))
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! GDB remote serial protocol stub
+//!
+//! Serves one debugger connection over TCP, so GDB and front ends that
+//! speak its remote protocol can drive the emulator. The target
+//! description lists R0-RF, D, DF, P, X, Q and IE, plus `pc` standing for
+//! R(P); 16-bit registers are sent big-endian. Supported are register and
+//! memory reads and writes, software breakpoints (`Z0`/`Z1`), write, read
+//! and access watchpoints (`Z2`-`Z4`), single-step, continue and Ctrl-C.
+//!
+//! IDL stops the target with `S05` at the instruction after it, like a
+//! breakpoint; continuing again reports the same stop.
+
+mod packet;
+mod stub;
+mod target;
+
+use crate::cpu::Cpu;
+use packet::{Incoming, read_incoming, write_packet};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use stub::{Action, Stub};
+
+/// Debug `cpu` for one connection on `stream` until the debugger detaches,
+/// kills the target or hangs up, returning the CPU as it was left
+pub fn serve(stream: TcpStream, cpu: Cpu) -> io::Result<Cpu> {
+    // Packets are small and each waits for an answer
+    stream.set_nodelay(true)?;
+    let mut stub = Stub::new(cpu);
+    let mut reader = stream.try_clone()?;
+    let mut writer = stream.try_clone()?;
+    let mut last_reply = Vec::new();
+
+    while let Some(incoming) = read_incoming(&mut reader)? {
+        let packet = match incoming {
+            Incoming::Packet(data, true) => data,
+            Incoming::Packet(_, false) => {
+                if !stub.no_ack {
+                    writer.write_all(b"-")?;
+                }
+                continue;
+            }
+            Incoming::Nak => {
+                write_packet(&mut writer, &last_reply)?;
+                continue;
+            }
+            Incoming::Ack | Incoming::Interrupt => continue,
+        };
+        if !stub.no_ack {
+            writer.write_all(b"+")?;
+        }
+        let reply = match stub.handle(&packet) {
+            Action::Reply(reply) => reply,
+            Action::Step => stub.resume(true, &mut || false),
+            Action::Continue => stub.resume(false, &mut || interrupted(&stream)),
+            Action::Detach => {
+                write_packet(&mut writer, b"OK")?;
+                break;
+            }
+            Action::Kill => break,
+        };
+        write_packet(&mut writer, &reply)?;
+        last_reply = reply;
+    }
+    Ok(stub.cpu)
+}
+
+/// Whether Ctrl-C has arrived, without waiting for it
+fn interrupted(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let mut byte = [0u8];
+    let interrupt = matches!(stream.peek(&mut byte), Ok(1) if byte[0] == 0x03);
+    let _ = stream.set_nonblocking(false);
+    if interrupt {
+        let _ = (&*stream).read_exact(&mut byte);
+    }
+    interrupt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// A scripted debugger connected to a stub serving `source`
+    struct Client {
+        stream: TcpStream,
+        server: thread::JoinHandle<Cpu>,
+    }
+
+    impl Client {
+        fn connect(source: &str) -> Self {
+            let mut cpu = Cpu::new();
+            cpu.load_program(&assemble(source).unwrap().machine_code, 0)
+                .unwrap();
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            let server = thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                serve(stream, cpu).unwrap()
+            });
+            let stream = TcpStream::connect(address).unwrap();
+            stream.set_nodelay(true).unwrap();
+            Self { stream, server }
+        }
+
+        /// Send a packet and return the reply, acknowledging both ways
+        fn send(&mut self, data: &str) -> String {
+            write_packet(&mut self.stream, data.as_bytes()).unwrap();
+            assert_eq!(
+                read_incoming(&mut self.stream).unwrap(),
+                Some(Incoming::Ack)
+            );
+            self.reply()
+        }
+
+        fn reply(&mut self) -> String {
+            match read_incoming(&mut self.stream).unwrap() {
+                Some(Incoming::Packet(data, true)) => {
+                    self.stream.write_all(b"+").unwrap();
+                    String::from_utf8(data).unwrap()
+                }
+                other => panic!("expected a packet, got {:?}", other),
+            }
+        }
+
+        fn finish(mut self) -> Cpu {
+            assert_eq!(self.send("D"), "OK");
+            self.server.join().unwrap()
+        }
+    }
+
+    const COUNTDOWN: &str =
+        "LDI 0x03\nPLO R3\nLDI 0x40\nPHI R4\nLOOP: DEC R3\nGLO R3\nSTR R4\nBNZ LOOP\nIDL";
+
+    #[test]
+    fn test_registers_memory_and_description() {
+        let mut client = Client::connect(COUNTDOWN);
+        assert!(
+            client
+                .send("qSupported:swbreak+")
+                .contains("qXfer:features:read+")
+        );
+        let xml = client.send("qXfer:features:read:target.xml:0,fff");
+        assert!(xml.starts_with("l<?xml"));
+        assert!(xml.contains("name=\"rf\" bitsize=\"16\""));
+        assert!(xml.contains("name=\"ie\" bitsize=\"8\""));
+        assert_eq!(
+            client.send("qXfer:features:read:target.xml:10,10").len(),
+            17
+        );
+
+        // 16 x 16-bit, 6 x 8-bit, then the PC
+        let registers = client.send("g");
+        assert_eq!(registers.len(), (16 * 2 + 6 + 2) * 2);
+        assert_eq!(client.send("P3=1234"), "OK");
+        assert_eq!(client.send("p3"), "1234");
+        assert_eq!(client.send("P10=ab"), "OK");
+        assert_eq!(client.send("p10"), "ab");
+        assert_eq!(client.send("p17"), "E01");
+
+        assert_eq!(client.send("m0,4"), "f803a3f8");
+        assert_eq!(client.send("M100,2:cafe"), "OK");
+        assert_eq!(client.send("m100,2"), "cafe");
+        assert_eq!(client.send("X102,1:}"), "OK");
+        assert_eq!(client.send("m102,1"), "7d");
+        assert_eq!(client.send("mffff,2"), "E01");
+        assert_eq!(client.send("vMustReplyEmpty"), "");
+
+        let cpu = client.finish();
+        assert_eq!((cpu.registers[3], cpu.d), (0x1234, 0xAB));
+    }
+
+    #[test]
+    fn test_breakpoints_watchpoints_and_stepping() {
+        let mut client = Client::connect(COUNTDOWN);
+        assert_eq!(client.send("s"), "S05");
+        assert_eq!(client.send("p10"), "03");
+        assert_eq!(client.send("p16"), "0002");
+
+        // LOOP is at 6
+        assert_eq!(client.send("Z0,6,1"), "OK");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p16"), "0006");
+        assert_eq!(client.send("z0,6,1"), "OK");
+
+        assert_eq!(client.send("Z2,4000,1"), "OK");
+        assert_eq!(client.send("c"), "T05watch:4000;");
+        assert_eq!(client.send("m4000,1"), "02");
+        assert_eq!(client.send("z2,4000,1"), "OK");
+        assert_eq!(client.send("Z4,4000,1"), "OK");
+        assert_eq!(client.send("c"), "T05awatch:4000;");
+        assert_eq!(client.send("z4,4000,1"), "OK");
+
+        assert_eq!(client.send("c"), "S05");
+        let cpu = client.finish();
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.registers[3], 0);
+    }
+
+    #[test]
+    fn test_interrupt_and_no_ack_mode() {
+        let mut client = Client::connect("LOOP: BR LOOP");
+        assert_eq!(client.send("QStartNoAckMode"), "OK");
+        write_packet(&mut client.stream, b"c").unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        client.stream.write_all(&[0x03]).unwrap();
+        assert_eq!(
+            read_incoming(&mut client.stream).unwrap(),
+            Some(Incoming::Packet(b"S02".to_vec(), true))
+        );
+        write_packet(&mut client.stream, b"k").unwrap();
+        client.server.join().unwrap();
+    }
+}
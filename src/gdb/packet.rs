@@ -0,0 +1,107 @@
+use std::io::{self, Read, Write};
+
+/// What arrived from the debugger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Incoming {
+    /// A packet's unescaped data, and whether its checksum matched
+    Packet(Vec<u8>, bool),
+    /// Ctrl-C: stop the running target
+    Interrupt,
+    /// `+`: the last reply arrived
+    Ack,
+    /// `-`: the last reply must be sent again
+    Nak,
+}
+
+/// Read the next packet, acknowledgement or interrupt; `None` at end of
+/// input
+pub(super) fn read_incoming(reader: &mut dyn Read) -> io::Result<Option<Incoming>> {
+    loop {
+        let Some(byte) = read_byte(reader)? else {
+            return Ok(None);
+        };
+        match byte {
+            b'$' => break,
+            0x03 => return Ok(Some(Incoming::Interrupt)),
+            b'+' => return Ok(Some(Incoming::Ack)),
+            b'-' => return Ok(Some(Incoming::Nak)),
+            _ => {}
+        }
+    }
+
+    let mut raw = Vec::new();
+    loop {
+        match read_byte(reader)? {
+            None => return Ok(None),
+            Some(b'#') => break,
+            Some(byte) => raw.push(byte),
+        }
+    }
+    let mut checksum = [0u8; 2];
+    reader.read_exact(&mut checksum)?;
+    let expected = std::str::from_utf8(&checksum)
+        .ok()
+        .and_then(|c| u8::from_str_radix(c, 16).ok());
+    let valid = expected == Some(sum(&raw));
+    Ok(Some(Incoming::Packet(unescape(&raw), valid)))
+}
+
+/// Frame `data` as `$data#checksum`, escaping the framing characters
+pub(super) fn write_packet(writer: &mut dyn Write, data: &[u8]) -> io::Result<()> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &byte in data {
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            escaped.extend([b'}', byte ^ 0x20]);
+        } else {
+            escaped.push(byte);
+        }
+    }
+    writer.write_all(b"$")?;
+    writer.write_all(&escaped)?;
+    write!(writer, "#{:02x}", sum(&escaped))?;
+    writer.flush()
+}
+
+/// Bytes as two lower-case hex digits each
+pub(super) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Pairs of hex digits as bytes
+pub(super) fn from_hex(text: &[u8]) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|p| u8::from_str_radix(p, 16).ok())
+        })
+        .collect()
+}
+
+fn read_byte(reader: &mut dyn Read) -> io::Result<Option<u8>> {
+    let mut byte = [0u8];
+    match reader.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Undo `}` escapes
+fn unescape(raw: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'}' => data.extend(bytes.next().map(|b| b ^ 0x20)),
+            _ => data.push(byte),
+        }
+    }
+    data
+}
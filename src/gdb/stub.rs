@@ -0,0 +1,283 @@
+use super::packet::{from_hex, to_hex};
+use super::target::{read_all, read_register, target_xml, write_all, write_register};
+use crate::cpu::{Cpu, CpuError, Instruction, Opcode};
+use std::collections::BTreeSet;
+
+/// Continue checks for an interrupt after this many instructions
+const INTERRUPT_POLL: u64 = 4096;
+
+/// What the session should do after a packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Action {
+    Reply(Vec<u8>),
+    Step,
+    Continue,
+    Detach,
+    Kill,
+}
+
+/// Whether an instruction reads or writes its data byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+/// Watchpoint kinds, as numbered by `Z2`, `Z3` and `Z4`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watchpoint {
+    kind: WatchKind,
+    address: u16,
+    length: u16,
+}
+
+impl Watchpoint {
+    /// The stop reply if this watchpoint fires on `access` of `address`
+    fn hit(&self, address: u16, access: Access) -> Option<Vec<u8>> {
+        let inside = address.wrapping_sub(self.address) < self.length;
+        let reason = match (self.kind, access) {
+            (WatchKind::Write, Access::Write) => "watch",
+            (WatchKind::Read, Access::Read) => "rwatch",
+            (WatchKind::Access, _) => "awatch",
+            _ => return None,
+        };
+        inside.then(|| format!("T05{}:{:x};", reason, address).into_bytes())
+    }
+}
+
+/// Debugger-visible state: the CPU and the breakpoints set on it
+pub(super) struct Stub {
+    pub cpu: Cpu,
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    /// Set by `QStartNoAckMode`: packets are no longer acknowledged
+    pub no_ack: bool,
+}
+
+impl Stub {
+    pub fn new(cpu: Cpu) -> Self {
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            no_ack: false,
+        }
+    }
+
+    /// Answer one packet; an empty reply means "not supported"
+    pub fn handle(&mut self, packet: &[u8]) -> Action {
+        let Some((&command, args)) = packet.split_first() else {
+            return Action::Reply(Vec::new());
+        };
+        let reply = match command {
+            b'?' => Some(b"S05".to_vec()),
+            b'g' => Some(to_hex(&read_all(&self.cpu)).into_bytes()),
+            b'G' => from_hex(args)
+                .and_then(|bytes| write_all(&mut self.cpu, &bytes))
+                .map(|_| b"OK".to_vec()),
+            b'p' => number(args)
+                .and_then(|n| read_register(&self.cpu, n as usize))
+                .map(|bytes| to_hex(&bytes).into_bytes()),
+            b'P' => split(args, b'=').and_then(|(n, value)| {
+                let bytes = from_hex(value)?;
+                write_register(&mut self.cpu, number(n)? as usize, &bytes)?;
+                Some(b"OK".to_vec())
+            }),
+            b'm' => self.read_memory(args),
+            b'M' => split(args, b':')
+                .and_then(|(range, data)| self.write_memory(range, &from_hex(data)?)),
+            b'X' => split(args, b':').and_then(|(range, data)| self.write_memory(range, data)),
+            b'Z' | b'z' => self.set_point(command == b'Z', args),
+            b's' | b'c' => {
+                if !args.is_empty() {
+                    match number(args).and_then(|a| u16::try_from(a).ok()) {
+                        Some(address) => self.cpu.set_pc(address),
+                        None => return Action::Reply(b"E01".to_vec()),
+                    }
+                }
+                return if command == b's' {
+                    Action::Step
+                } else {
+                    Action::Continue
+                };
+            }
+            b'D' => return Action::Detach,
+            b'k' => return Action::Kill,
+            b'H' => Some(b"OK".to_vec()),
+            b'q' => Some(self.query(args)),
+            b'Q' if args == b"StartNoAckMode" => {
+                self.no_ack = true;
+                Some(b"OK".to_vec())
+            }
+            b'v' if args == b"Kill" || args.starts_with(b"Kill;") => return Action::Kill,
+            _ => Some(Vec::new()),
+        };
+        Action::Reply(reply.unwrap_or_else(|| b"E01".to_vec()))
+    }
+
+    /// Execute one instruction, or run until a breakpoint, watchpoint, IDL
+    /// or `interrupted` returns true, giving the stop reply
+    pub fn resume(&mut self, step: bool, interrupted: &mut dyn FnMut() -> bool) -> Vec<u8> {
+        let mut executed = 0u64;
+        loop {
+            let access = data_access(&self.cpu);
+            match self.cpu.step() {
+                Ok(_) => {}
+                Err(CpuError::InvalidInstruction(_)) => return b"S04".to_vec(),
+                Err(CpuError::MemoryOutOfBounds(_)) => return b"S0b".to_vec(),
+                Err(_) => return b"S05".to_vec(),
+            }
+            if let Some((address, access)) = access
+                && let Some(reply) = self.watchpoints.iter().find_map(|w| w.hit(address, access))
+            {
+                return reply;
+            }
+            if step || self.cpu.is_halted() || self.breakpoints.contains(&self.cpu.get_pc()) {
+                return b"S05".to_vec();
+            }
+            executed += 1;
+            if executed.is_multiple_of(INTERRUPT_POLL) && interrupted() {
+                return b"S02".to_vec();
+            }
+        }
+    }
+
+    fn query(&self, args: &[u8]) -> Vec<u8> {
+        let text = String::from_utf8_lossy(args);
+        if text.starts_with("Supported") {
+            return b"PacketSize=1000;qXfer:features:read+;QStartNoAckMode+".to_vec();
+        }
+        if let Some(range) = text.strip_prefix("Xfer:features:read:target.xml:") {
+            let Some((offset, length)) = range
+                .split_once(',')
+                .and_then(|(o, l)| Some((number(o.as_bytes())?, number(l.as_bytes())?)))
+            else {
+                return b"E01".to_vec();
+            };
+            let xml = target_xml().into_bytes();
+            let start = (offset as usize).min(xml.len());
+            let end = start.saturating_add(length as usize).min(xml.len());
+            let marker = if end == xml.len() { b'l' } else { b'm' };
+            return [&[marker], &xml[start..end]].concat();
+        }
+        match text.as_ref() {
+            "Attached" => b"1".to_vec(),
+            "C" => b"QC1".to_vec(),
+            "fThreadInfo" => b"m1".to_vec(),
+            "sThreadInfo" => b"l".to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// `m ADDR,LEN`
+    fn read_memory(&self, args: &[u8]) -> Option<Vec<u8>> {
+        let (start, length) = range(args)?;
+        let bytes = self.cpu.memory.get(start..start + length)?;
+        Some(to_hex(bytes).into_bytes())
+    }
+
+    /// `M`/`X ADDR,LEN:DATA`
+    fn write_memory(&mut self, range_args: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+        let (start, length) = range(range_args)?;
+        if data.len() != length {
+            return None;
+        }
+        self.cpu
+            .memory
+            .get_mut(start..start + length)?
+            .copy_from_slice(data);
+        Some(b"OK".to_vec())
+    }
+
+    /// `Z TYPE,ADDR,KIND` and `z TYPE,ADDR,KIND`: types 0 and 1 are
+    /// breakpoints, 2-4 write, read and access watchpoints of KIND bytes
+    fn set_point(&mut self, insert: bool, args: &[u8]) -> Option<Vec<u8>> {
+        let mut fields = args.split(|&b| b == b',');
+        let kind = number(fields.next()?)?;
+        let address = u16::try_from(number(fields.next()?)?).ok()?;
+        let length = u16::try_from(number(fields.next()?)?).ok()?;
+        let watch = match kind {
+            0 | 1 => {
+                if insert {
+                    self.breakpoints.insert(address);
+                } else {
+                    self.breakpoints.remove(&address);
+                }
+                return Some(b"OK".to_vec());
+            }
+            2 => WatchKind::Write,
+            3 => WatchKind::Read,
+            4 => WatchKind::Access,
+            _ => return Some(Vec::new()),
+        };
+        let watchpoint = Watchpoint {
+            kind: watch,
+            address,
+            length: length.max(1),
+        };
+        if insert {
+            self.watchpoints.push(watchpoint);
+        } else {
+            self.watchpoints.retain(|w| *w != watchpoint);
+        }
+        Some(b"OK".to_vec())
+    }
+}
+
+/// The data byte the instruction at the PC will read or write
+///
+/// Instruction fetches and immediate operands are not data accesses.
+fn data_access(cpu: &Cpu) -> Option<(u16, Access)> {
+    let pc = cpu.get_pc();
+    let bytes: Vec<u8> = (0..3)
+        .map(|i| cpu.memory[pc.wrapping_add(i) as usize])
+        .collect();
+    let instruction = Instruction::decode(&bytes)?;
+    let via_n = cpu.registers[instruction.register as usize];
+    let via_x = cpu.registers[cpu.x as usize];
+    Some(match instruction.opcode {
+        Opcode::LDN | Opcode::LDA => (via_n, Access::Read),
+        Opcode::STR => (via_n, Access::Write),
+        Opcode::LDX
+        | Opcode::LDXA
+        | Opcode::OR
+        | Opcode::AND
+        | Opcode::XOR
+        | Opcode::ADD
+        | Opcode::SD
+        | Opcode::SM
+        | Opcode::ADC
+        | Opcode::SDB
+        | Opcode::SMB
+        | Opcode::OUT
+        | Opcode::RET
+        | Opcode::DIS => (via_x, Access::Read),
+        Opcode::STXD | Opcode::INP | Opcode::SAV => (via_x, Access::Write),
+        Opcode::MARK => (cpu.registers[2], Access::Write),
+        _ => return None,
+    })
+}
+
+/// A hex number
+fn number(text: &[u8]) -> Option<u32> {
+    u32::from_str_radix(std::str::from_utf8(text).ok()?, 16).ok()
+}
+
+/// `ADDR,LEN` as a memory range
+fn range(text: &[u8]) -> Option<(usize, usize)> {
+    let (start, length) = split(text, b',')?;
+    Some((number(start)? as usize, number(length)? as usize))
+}
+
+/// Split at the first `separator`
+fn split(text: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let at = text.iter().position(|&b| b == separator)?;
+    Some((&text[..at], &text[at + 1..]))
+}
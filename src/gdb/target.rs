@@ -0,0 +1,121 @@
+use crate::cpu::Cpu;
+
+/// Registers in GDB's numbering: R0-RF, D, DF, P, X, Q, IE and the
+/// program counter R(P), with their widths in bits
+const REGISTERS: [(&str, u32); 23] = [
+    ("r0", 16),
+    ("r1", 16),
+    ("r2", 16),
+    ("r3", 16),
+    ("r4", 16),
+    ("r5", 16),
+    ("r6", 16),
+    ("r7", 16),
+    ("r8", 16),
+    ("r9", 16),
+    ("ra", 16),
+    ("rb", 16),
+    ("rc", 16),
+    ("rd", 16),
+    ("re", 16),
+    ("rf", 16),
+    ("d", 8),
+    ("df", 8),
+    ("p", 8),
+    ("x", 8),
+    ("q", 8),
+    ("ie", 8),
+    ("pc", 16),
+];
+
+/// Index of the program counter in [`REGISTERS`]
+const PC: usize = 22;
+
+/// Target description served through `qXfer:features:read:target.xml`
+pub(super) fn target_xml() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n  <feature name=\"org.cosmac.rca1802\">\n",
+    );
+    for (number, (name, bits)) in REGISTERS.iter().enumerate() {
+        let kind = match (number, bits) {
+            (PC, _) => "code_ptr",
+            (_, 16) => "uint16",
+            _ => "uint8",
+        };
+        xml.push_str(&format!(
+            "    <reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"/>\n",
+            name, bits, kind, number
+        ));
+    }
+    xml.push_str("  </feature>\n</target>\n");
+    xml
+}
+
+/// Register `number` in target byte order (big-endian, as the 1802 keeps
+/// addresses in memory); `None` past the last register
+pub(super) fn read_register(cpu: &Cpu, number: usize) -> Option<Vec<u8>> {
+    let value = match number {
+        0..16 => cpu.registers[number],
+        16 => cpu.d as u16,
+        17 => cpu.df as u16,
+        18 => cpu.p as u16,
+        19 => cpu.x as u16,
+        20 => cpu.q as u16,
+        21 => cpu.ie as u16,
+        PC => cpu.get_pc(),
+        _ => return None,
+    };
+    Some(match REGISTERS[number].1 {
+        16 => value.to_be_bytes().to_vec(),
+        _ => vec![value as u8],
+    })
+}
+
+/// Set register `number` from bytes in target byte order; `None` if the
+/// number or the width is wrong
+pub(super) fn write_register(cpu: &mut Cpu, number: usize, bytes: &[u8]) -> Option<()> {
+    let value = match (REGISTERS.get(number)?.1, bytes) {
+        (16, &[high, low]) => u16::from_be_bytes([high, low]),
+        (8, &[byte]) => byte as u16,
+        _ => return None,
+    };
+    match number {
+        0..16 => cpu.registers[number] = value,
+        16 => cpu.d = value as u8,
+        17 => cpu.df = value & 1 != 0,
+        18 => cpu.p = value as u8 & 0x0F,
+        19 => cpu.x = value as u8 & 0x0F,
+        20 => cpu.q = value & 1 != 0,
+        21 => cpu.ie = value & 1 != 0,
+        _ => cpu.set_pc(value),
+    }
+    Some(())
+}
+
+/// Every register in order, as the `g` packet sends them
+pub(super) fn read_all(cpu: &Cpu) -> Vec<u8> {
+    (0..REGISTERS.len())
+        .flat_map(|n| read_register(cpu, n).unwrap_or_default())
+        .collect()
+}
+
+/// Set every register from a `G` packet's bytes
+pub(super) fn write_all(cpu: &mut Cpu, bytes: &[u8]) -> Option<()> {
+    let total: usize = REGISTERS.iter().map(|(_, bits)| *bits as usize / 8).sum();
+    if bytes.len() != total {
+        return None;
+    }
+    let old_pc = cpu.get_pc();
+    let mut offset = 0;
+    for (number, (_, bits)) in REGISTERS.iter().enumerate() {
+        let width = *bits as usize / 8;
+        let value = &bytes[offset..offset + width];
+        // The PC is also R(P): it wins only when the debugger changed it
+        if number != PC || value != old_pc.to_be_bytes() {
+            write_register(cpu, number, value)?;
+        }
+        offset += width;
+    }
+    Some(())
+}
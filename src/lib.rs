@@ -1,5 +1,21 @@
 pub mod assembler;
+pub mod challenge;
 pub mod cpu;
+pub mod disassembler;
+pub mod formats;
+pub mod linker;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gdb;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lsp;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod monitor;
 
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
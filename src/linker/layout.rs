@@ -0,0 +1,86 @@
+use super::LinkError;
+
+/// Addresses a section may occupy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// Name of the sections placed here
+    pub section: String,
+    pub start: u16,
+    /// Last usable address (inclusive)
+    pub end: u16,
+}
+
+/// Where each section goes in memory
+///
+/// The text form has one region per line: a section name, a start address
+/// and an optional last address (the top of memory if left out). Addresses
+/// are hexadecimal, with or without `0x` or `$`. `#` and `;` start comments.
+///
+/// ```text
+/// CODE  0x0000 0x7FFF
+/// DATA  0x8000
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryLayout {
+    pub regions: Vec<Region>,
+}
+
+impl MemoryLayout {
+    pub fn parse(text: &str) -> Result<Self, LinkError> {
+        let mut layout = Self::default();
+
+        for (i, raw) in text.lines().enumerate() {
+            let invalid = |message: String| LinkError::Layout {
+                line: i + 1,
+                message,
+            };
+            let content = raw.split(['#', ';']).next().unwrap_or("");
+            let fields: Vec<&str> = content.split_whitespace().collect();
+            let (section, start, end) = match fields[..] {
+                [] => continue,
+                [section, start] => (section, start, None),
+                [section, start, end] => (section, start, Some(end)),
+                _ => return Err(invalid("expected: SECTION start [end]".to_string())),
+            };
+
+            let section = section.to_uppercase();
+            if layout.region(&section).is_some() {
+                return Err(invalid(format!("{} is placed twice", section)));
+            }
+            let start =
+                parse_address(start).ok_or_else(|| invalid(format!("bad address {}", start)))?;
+            let end = match end {
+                Some(end) => {
+                    parse_address(end).ok_or_else(|| invalid(format!("bad address {}", end)))?
+                }
+                None => 0xFFFF,
+            };
+            if end < start {
+                return Err(invalid(format!("{} ends before it starts", section)));
+            }
+            layout.regions.push(Region {
+                section,
+                start,
+                end,
+            });
+        }
+
+        Ok(layout)
+    }
+
+    /// Region holding sections called `name`
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions
+            .iter()
+            .find(|r| r.section.eq_ignore_ascii_case(name))
+    }
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).ok()
+}
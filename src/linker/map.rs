@@ -0,0 +1,35 @@
+use super::LinkedImage;
+use std::fmt::Write;
+
+impl LinkedImage {
+    /// Text map of where every section and exported symbol ended up
+    pub fn map_file(&self) -> String {
+        let mut out = String::new();
+        let width = self
+            .placements
+            .iter()
+            .map(|p| p.section.len())
+            .max()
+            .unwrap_or(0)
+            .max(7);
+
+        let _ = writeln!(out, "SECTIONS");
+        let _ = writeln!(out, "  START  END    SIZE  {:<width$}  OBJECT", "SECTION");
+        for placement in &self.placements {
+            let end = placement.address.wrapping_add(placement.length - 1);
+            let _ = writeln!(
+                out,
+                "  {:04X}   {:04X}   {:04X}  {:<width$}  {}",
+                placement.address, end, placement.length, placement.section, placement.object
+            );
+        }
+
+        let _ = writeln!(out, "\nSYMBOLS");
+        let mut symbols: Vec<(&String, &u16)> = self.symbols.iter().collect();
+        symbols.sort_by_key(|&(name, &value)| (value, name));
+        for (name, value) in symbols {
+            let _ = writeln!(out, "  {:04X}  {}", value, name);
+        }
+        out
+    }
+}
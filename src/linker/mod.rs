@@ -0,0 +1,250 @@
+//! Linker for relocatable objects
+//!
+//! [`assemble_object`](crate::assembler::assemble_object) turns a source
+//! file into an [`ObjectFile`]: named sections of code and data, the
+//! symbols it exports and imports, and relocation records for every field
+//! holding an address. [`link`] places each section in the region a
+//! [`MemoryLayout`] gives it, resolves symbols across objects and patches
+//! the relocated fields, producing load segments and a map file.
+
+mod layout;
+mod map;
+mod object;
+
+pub use layout::{MemoryLayout, Region};
+pub use object::{ObjectFile, ObjectSymbol, Relocation, RelocationKind, RelocationTarget, Section};
+
+use crate::formats::{Segment, merge_segments};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use thiserror::Error;
+
+/// Errors reading objects and layouts or linking them
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("Layout line {line}: {message}")]
+    Layout { line: usize, message: String },
+
+    #[error("Invalid object file: {0}")]
+    InvalidObject(String),
+
+    #[error("Section {section} of {object} has no region in the memory layout")]
+    Unplaced { object: String, section: String },
+
+    #[error("Section {section} overflows its region ending at {end:#06X}")]
+    Overflow { section: String, end: u16 },
+
+    #[error("{first} and {second} overlap")]
+    Overlap { first: String, second: String },
+
+    #[error("Symbol {name} is exported by both {first} and {second}")]
+    DuplicateSymbol {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("Undefined symbol {name} (imported by {object})")]
+    UndefinedSymbol { name: String, object: String },
+
+    #[error("{object}: relocation at {offset:#06X} in {section} is invalid")]
+    InvalidRelocation {
+        object: String,
+        section: String,
+        offset: u16,
+    },
+
+    #[error("{object}: short branch at {address:#06X} to {target:#06X} leaves its page")]
+    BranchOffPage {
+        object: String,
+        address: u16,
+        target: u16,
+    },
+}
+
+/// Where the linker put one section of one object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    pub object: String,
+    pub section: String,
+    pub address: u16,
+    pub length: u16,
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} section {}", self.object, self.section)
+    }
+}
+
+/// A linked program
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkedImage {
+    /// Bytes to load, merged where sections touch
+    pub segments: Vec<Segment>,
+    /// Every non-empty section, in address order
+    pub placements: Vec<Placement>,
+    /// Address of every exported symbol
+    pub symbols: BTreeMap<String, u16>,
+}
+
+/// Link `objects` into one program laid out by `layout`
+///
+/// Sections are placed region by region in layout order. Within a region,
+/// sections of the same name follow each other in the order the objects
+/// are given.
+pub fn link(objects: &[ObjectFile], layout: &MemoryLayout) -> Result<LinkedImage, LinkError> {
+    // Base address of each (object, section)
+    let mut bases: HashMap<(usize, usize), u16> = HashMap::new();
+    let mut placements = Vec::new();
+
+    for object in objects {
+        let unplaced = object
+            .sections
+            .iter()
+            .find(|s| !s.data.is_empty() && layout.region(&s.name).is_none());
+        if let Some(section) = unplaced {
+            return Err(LinkError::Unplaced {
+                object: object.name.clone(),
+                section: section.name.clone(),
+            });
+        }
+    }
+
+    for region in &layout.regions {
+        let mut next = region.start as u32;
+        for (object_index, object) in objects.iter().enumerate() {
+            for (section_index, section) in object.sections.iter().enumerate() {
+                if !section.name.eq_ignore_ascii_case(&region.section) {
+                    continue;
+                }
+                if section.data.is_empty() {
+                    // Still gives symbols in it an address
+                    bases.insert((object_index, section_index), next as u16);
+                    continue;
+                }
+                let end = next + section.data.len() as u32 - 1;
+                if end > region.end as u32 {
+                    return Err(LinkError::Overflow {
+                        section: region.section.clone(),
+                        end: region.end,
+                    });
+                }
+                bases.insert((object_index, section_index), next as u16);
+                placements.push(Placement {
+                    object: object.name.clone(),
+                    section: section.name.clone(),
+                    address: next as u16,
+                    length: section.data.len() as u16,
+                });
+                next = end + 1;
+            }
+        }
+    }
+
+    let symbols = exported_symbols(objects, &bases)?;
+    let mut segments = Vec::new();
+
+    for (object_index, object) in objects.iter().enumerate() {
+        for name in &object.imports {
+            if !symbols.contains_key(name) {
+                return Err(LinkError::UndefinedSymbol {
+                    name: name.clone(),
+                    object: object.name.clone(),
+                });
+            }
+        }
+        for (section_index, section) in object.sections.iter().enumerate() {
+            let Some(&base) = bases.get(&(object_index, section_index)) else {
+                continue;
+            };
+            let mut data = section.data.clone();
+            for relocation in &section.relocations {
+                let target = match &relocation.target {
+                    RelocationTarget::Section(index) => bases.get(&(object_index, *index)).copied(),
+                    RelocationTarget::Symbol(name) => symbols.get(name).copied(),
+                };
+                let invalid = || LinkError::InvalidRelocation {
+                    object: object.name.clone(),
+                    section: section.name.clone(),
+                    offset: relocation.offset,
+                };
+                let value = target.ok_or_else(invalid)?.wrapping_add(relocation.addend);
+                let offset = relocation.offset as usize;
+                let field = data
+                    .get_mut(offset..offset + relocation.kind.width())
+                    .ok_or_else(invalid)?;
+                let address = base.wrapping_add(relocation.offset);
+                patch(field, relocation.kind, value, address).map_err(|target| {
+                    LinkError::BranchOffPage {
+                        object: object.name.clone(),
+                        address,
+                        target,
+                    }
+                })?;
+            }
+            segments.push(Segment::new(base, data));
+        }
+    }
+
+    placements.sort_by_key(|p| p.address);
+    for pair in placements.windows(2) {
+        if pair[0].address as u32 + pair[0].length as u32 > pair[1].address as u32 {
+            return Err(LinkError::Overlap {
+                first: pair[0].to_string(),
+                second: pair[1].to_string(),
+            });
+        }
+    }
+    Ok(LinkedImage {
+        segments: merge_segments(segments),
+        placements,
+        symbols,
+    })
+}
+
+/// Address of every `PUBLIC` symbol, once sections are placed
+fn exported_symbols(
+    objects: &[ObjectFile],
+    bases: &HashMap<(usize, usize), u16>,
+) -> Result<BTreeMap<String, u16>, LinkError> {
+    let mut symbols = BTreeMap::new();
+    let mut owners: HashMap<&str, &str> = HashMap::new();
+
+    for (object_index, object) in objects.iter().enumerate() {
+        for export in &object.exports {
+            if let Some(first) = owners.insert(&export.name, &object.name) {
+                return Err(LinkError::DuplicateSymbol {
+                    name: export.name.clone(),
+                    first: first.to_string(),
+                    second: object.name.clone(),
+                });
+            }
+            let base = bases
+                .get(&(object_index, export.section))
+                .copied()
+                .unwrap_or_default();
+            symbols.insert(export.name.clone(), base.wrapping_add(export.offset));
+        }
+    }
+
+    Ok(symbols)
+}
+
+/// Write `value` into a relocated field at `address`; a short branch to
+/// another page gives back its target
+fn patch(field: &mut [u8], kind: RelocationKind, value: u16, address: u16) -> Result<(), u16> {
+    match kind {
+        RelocationKind::Word => field.copy_from_slice(&value.to_be_bytes()),
+        RelocationKind::High => field[0] = (value >> 8) as u8,
+        RelocationKind::Low => field[0] = value as u8,
+        RelocationKind::ShortBranch => {
+            if value & 0xFF00 != address & 0xFF00 {
+                return Err(value);
+            }
+            field[0] = value as u8;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use super::LinkError;
+use serde::{Deserialize, Serialize};
+
+/// How a relocated address is written into a section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelocationKind {
+    /// Big-endian 16-bit address (long branches, `DW`, `CALL`)
+    Word,
+    /// High byte of the address (`HIGH(x)`)
+    High,
+    /// Low byte of the address (`LOW(x)`, `DB`)
+    Low,
+    /// Short branch operand: the low byte, with the target required to be
+    /// on the operand byte's page
+    ShortBranch,
+}
+
+impl RelocationKind {
+    /// Bytes the relocated field occupies
+    pub fn width(self) -> usize {
+        match self {
+            RelocationKind::Word => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// What a relocated field refers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelocationTarget {
+    /// Start of a section of the same object, by index
+    Section(usize),
+    /// A symbol exported by another object
+    Symbol(String),
+}
+
+/// A field in a section to patch once the target's address is known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relocation {
+    /// Offset of the field within its section
+    pub offset: u16,
+    pub kind: RelocationKind,
+    pub target: RelocationTarget,
+    /// Added to the target's address
+    pub addend: u16,
+}
+
+/// A named run of bytes placed as a unit by the linker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    pub name: String,
+    pub data: Vec<u8>,
+    pub relocations: Vec<Relocation>,
+}
+
+impl Section {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            data: Vec::new(),
+            relocations: Vec::new(),
+        }
+    }
+}
+
+/// A symbol an object makes available to others
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectSymbol {
+    pub name: String,
+    /// Index of the section the symbol is in
+    pub section: usize,
+    /// Offset within that section
+    pub offset: u16,
+}
+
+/// Relocatable output of assembling one source file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectFile {
+    /// Name used in link errors and the map file
+    pub name: String,
+    pub sections: Vec<Section>,
+    /// Symbols defined here and declared `PUBLIC`
+    pub exports: Vec<ObjectSymbol>,
+    /// Symbols declared `EXTERN`, to be found in other objects
+    pub imports: Vec<String>,
+}
+
+impl ObjectFile {
+    /// Serialize as JSON, the on-disk object format
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("object files always serialize")
+    }
+
+    /// Read an object written by [`ObjectFile::to_json`]
+    pub fn from_json(text: &str) -> Result<Self, LinkError> {
+        serde_json::from_str(text).map_err(|e| LinkError::InvalidObject(e.to_string()))
+    }
+}
@@ -0,0 +1,84 @@
+use crate::cpu::isa::{self, INSTRUCTIONS};
+
+/// What each pseudo-op and directive does, for hover text; instructions
+/// are described by the ISA table
+const DOCS: &[(&str, &str)] = &[
+    ("CALL", "SCRT call: SEP R4 followed by the target address"),
+    ("RETN", "SCRT return: SEP R5"),
+    (
+        "LOAD",
+        "Pseudo-op: load a 16-bit value into a register (6 bytes)",
+    ),
+    ("MOV", "Pseudo-op: copy one register to another (4 bytes)"),
+    ("ADD16", "Pseudo-op: add one register to another (12 bytes)"),
+    (
+        "PUSH",
+        "Pseudo-op: push a register onto the stack at R(X) (4 bytes)",
+    ),
+    (
+        "POP",
+        "Pseudo-op: pop a register from the stack at R(X) (5 bytes)",
+    ),
+    ("ORG", "Directive: set the location counter"),
+    ("PAGE", "Directive: move to the start of the next page"),
+    ("DB", "Directive: define bytes, characters and strings"),
+    ("DW", "Directive: define 16-bit big-endian words"),
+    ("INCLUDE", "Directive: assemble another file in place"),
+    ("SECTION", "Directive: switch section (relocatable objects)"),
+    (
+        "EXTERN",
+        "Directive: declare labels defined in other objects",
+    ),
+    ("PUBLIC", "Directive: export labels to other objects"),
+    (
+        "PROC",
+        "Directive: start a procedure with its own label scope",
+    ),
+    ("ENDP", "Directive: end a procedure"),
+    ("IF", "Directive: assemble the block if the condition holds"),
+    (
+        "IFDEF",
+        "Directive: assemble the block if the symbol is defined",
+    ),
+    (
+        "IFNDEF",
+        "Directive: assemble the block if the symbol is not defined",
+    ),
+    ("ELSE", "Directive: assemble the block if the IF did not"),
+    ("ENDIF", "Directive: end a conditional block"),
+    (
+        "DEFINE",
+        "Directive: define a symbol for conditional assembly",
+    ),
+];
+
+/// Every documented mnemonic and directive, for completion
+pub(super) fn mnemonics() -> impl Iterator<Item = (&'static str, &'static str)> {
+    INSTRUCTIONS
+        .iter()
+        .map(|i| (i.mnemonic, i.description))
+        .chain(DOCS.iter().copied())
+}
+
+/// Markdown hover text for a mnemonic, with its encoding and timing where
+/// it is a real instruction
+pub(super) fn hover(mnemonic: &str) -> Option<String> {
+    if let Some(info) = isa::lookup(mnemonic) {
+        let mut text = format!(
+            "**{}** - {}\n\n`{}` · {} bytes · {} machine cycles",
+            info.mnemonic,
+            info.description,
+            info.encoding(),
+            info.length,
+            info.cycles
+        );
+        if !info.flags.is_empty() {
+            let flags: Vec<String> = info.flags.iter().map(|f| format!("{:?}", f)).collect();
+            text.push_str(&format!(" · changes {}", flags.join(", ")));
+        }
+        return Some(text);
+    }
+    let mnemonic = mnemonic.to_uppercase();
+    let &(name, summary) = DOCS.iter().find(|(name, _)| *name == mnemonic)?;
+    Some(format!("**{}** - {}", name, summary))
+}
@@ -0,0 +1,183 @@
+use crate::assembler::{
+    AssembleOptions, Diagnostic, FileSystem, Severity, Span, Symbol, assemble_project,
+};
+use serde_json::{Value, json};
+use std::path::Path;
+
+/// An open source file and what the assembler last made of it
+pub(super) struct Document {
+    pub text: String,
+    /// File name the assembler reports for this document
+    name: String,
+    diagnostics: Vec<Diagnostic>,
+    /// Labels defined in this document, kept from the last clean assembly
+    /// while the text has errors
+    symbols: Vec<Symbol>,
+}
+
+impl Document {
+    pub fn new(uri: &str, text: String) -> Self {
+        let name = file_path(uri)
+            .file_name()
+            .map_or("input.asm".to_string(), |n| {
+                n.to_string_lossy().into_owned()
+            });
+        let mut document = Self {
+            text: String::new(),
+            name,
+            diagnostics: Vec::new(),
+            symbols: Vec::new(),
+        };
+        document.update(uri, text);
+        document
+    }
+
+    /// Replace the text and assemble it again, reading includes from the
+    /// document's directory
+    pub fn update(&mut self, uri: &str, text: String) {
+        let root = file_path(uri)
+            .parent()
+            .map_or_else(|| Path::new(".").to_path_buf(), Path::to_path_buf);
+        let options = AssembleOptions {
+            file_name: self.name.clone(),
+            ..Default::default()
+        };
+        match assemble_project(&text, &options, &FileSystem::new(root)) {
+            Ok(output) => {
+                self.diagnostics = output.diagnostics;
+                self.symbols = output
+                    .symbols
+                    .into_iter()
+                    .filter(|s| s.file == self.name && !s.name.starts_with('@'))
+                    .collect();
+            }
+            Err(error) if error.diagnostics().is_empty() => {
+                let end = text.lines().next().map_or(0, |l| l.chars().count());
+                self.diagnostics = vec![Diagnostic::error(
+                    &self.name,
+                    1,
+                    Span::new(0, end),
+                    error.to_string(),
+                )];
+            }
+            Err(error) => self.diagnostics = error.diagnostics().to_vec(),
+        }
+        self.text = text;
+    }
+
+    /// Diagnostics in this document, as LSP `Diagnostic`s
+    pub fn lsp_diagnostics(&self) -> Value {
+        let diagnostics: Vec<Value> = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.file == self.name)
+            .map(|d| {
+                let mut value = json!({
+                    "range": range(d.line, d.span.start, d.span.end),
+                    "severity": if d.severity == Severity::Error { 1 } else { 2 },
+                    "source": "rca1802",
+                    "message": d.message,
+                });
+                if let Some(code) = &d.code {
+                    value["code"] = json!(code);
+                }
+                value
+            })
+            .collect();
+        json!(diagnostics)
+    }
+
+    /// Labels defined in this document
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// The word under a 0-based position, with its span on the line
+    ///
+    /// Words are made of letters, digits, `_` and `.` so local labels are
+    /// taken whole.
+    pub fn word_at(&self, line: usize, character: usize) -> Option<(String, usize, usize)> {
+        let chars: Vec<char> = self.text.lines().nth(line)?.chars().collect();
+        let mut start = character.min(chars.len());
+        while start > 0 && is_word(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = start;
+        while end < chars.len() && is_word(chars[end]) {
+            end += 1;
+        }
+        (end > start).then(|| (chars[start..end].iter().collect(), start, end))
+    }
+
+    /// The label a word on 0-based `line` refers to
+    ///
+    /// Local labels are known to the assembler by their qualified names
+    /// (`MAIN.LOOP`), so a short name matches the closest qualified label
+    /// defined at or before the line.
+    pub fn resolve(&self, word: &str, line: usize) -> Option<&Symbol> {
+        let upper = word.to_uppercase();
+        if let Some(symbol) = self.symbols.iter().find(|s| s.name == upper) {
+            return Some(symbol);
+        }
+        let suffix = format!(".{}", upper.trim_start_matches('.'));
+        let candidates = self.symbols.iter().filter(|s| s.name.ends_with(&suffix));
+        candidates
+            .clone()
+            .filter(|s| s.defined <= line + 1)
+            .max_by_key(|s| s.defined)
+            .or_else(|| candidates.min_by_key(|s| s.defined))
+    }
+
+    /// Every place `symbol` is written in this document, definition first
+    pub fn occurrences(&self, symbol: &Symbol) -> Vec<Value> {
+        let last = symbol.name.rsplit('.').next().unwrap_or(&symbol.name);
+        let local = format!(".{}", last);
+        let names = [symbol.name.as_str(), local.as_str(), last];
+        let lines: Vec<&str> = self.text.lines().collect();
+
+        let mut found = Vec::new();
+        let uses = (symbol.references.iter())
+            .filter(|r| r.file == self.name)
+            .map(|r| r.line);
+        for number in std::iter::once(symbol.defined).chain(uses) {
+            let Some(text) = lines.get(number.wrapping_sub(1)) else {
+                continue;
+            };
+            let code = text.split(';').next().unwrap_or("");
+            let chars: Vec<char> = code.chars().collect();
+            let mut i = 0;
+            while i < chars.len() {
+                let Some((word, start, end)) =
+                    self.word_at(number - 1, i).filter(|_| is_word(chars[i]))
+                else {
+                    i += 1;
+                    continue;
+                };
+                if names.iter().any(|n| n.eq_ignore_ascii_case(&word)) {
+                    found.push(range(number, start, end));
+                }
+                i = end.max(i + 1);
+            }
+        }
+        found
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// LSP range on 1-based `line` covering columns `start..end`
+pub(super) fn range(line: usize, start: usize, end: usize) -> Value {
+    let line = line.saturating_sub(1);
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end },
+    })
+}
+
+/// Local path of a `file://` URI
+pub(super) fn file_path(uri: &str) -> std::path::PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    Path::new(&path.replace("%20", " ")).to_path_buf()
+}
@@ -0,0 +1,469 @@
+//! Language server for 1802 assembly
+//!
+//! Speaks the Language Server Protocol as JSON-RPC over stdio (see the
+//! `rca1802-lsp` binary). Every open document is assembled again on each
+//! change, giving:
+//!
+//! - diagnostics, including lint warnings with their IDs
+//! - hover documentation for mnemonics and directives, and label values
+//! - go to definition and find references for labels, local ones included
+//! - completion of mnemonics, registers and labels
+//! - the document's labels as its symbols
+//!
+//! Documents are kept in full; only whole-text changes are accepted.
+//! Includes are read from the document's directory on disk.
+
+mod docs;
+mod document;
+
+use document::{Document, range};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+/// LSP `CompletionItemKind`s and `SymbolKind`s used here
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_KEYWORD: u32 = 14;
+const COMPLETION_CONSTANT: u32 = 21;
+const SYMBOL_CONSTANT: u32 = 14;
+
+/// State of one language server session
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutting_down: bool,
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Has the client sent `exit`?
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handle one request or notification, returning the messages to send
+    /// back: a response for a request, and any notifications it causes
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutting_down = true;
+                Some(Value::Null)
+            }
+            "exit" => {
+                self.exited = true;
+                None
+            }
+            "textDocument/didOpen" => {
+                let uri = text(&params["textDocument"]["uri"]);
+                let source = text(&params["textDocument"]["text"]);
+                self.documents
+                    .insert(uri.clone(), Document::new(&uri, source));
+                return self.publish(&uri);
+            }
+            "textDocument/didChange" => {
+                let uri = text(&params["textDocument"]["uri"]);
+                let changes = params["contentChanges"].as_array();
+                let latest = changes.and_then(|c| c.last()).map(|c| text(&c["text"]));
+                if let (Some(document), Some(source)) = (self.documents.get_mut(&uri), latest) {
+                    document.update(&uri, source);
+                }
+                return self.publish(&uri);
+            }
+            "textDocument/didClose" => {
+                let uri = text(&params["textDocument"]["uri"]);
+                self.documents.remove(&uri);
+                return vec![notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )];
+            }
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/references" => Some(self.references(params)),
+            "textDocument/completion" => Some(self.completion(params)),
+            "textDocument/documentSymbol" => Some(self.document_symbols(params)),
+            _ => None,
+        };
+
+        let Some(id) = id else {
+            return Vec::new();
+        };
+        match result {
+            _ if self.shutting_down && method != "shutdown" => {
+                vec![error(id, INVALID_REQUEST, "Server is shutting down")]
+            }
+            Some(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None => vec![error(
+                id,
+                METHOD_NOT_FOUND,
+                &format!("Unknown method {}", method),
+            )],
+        }
+    }
+
+    fn publish(&self, uri: &str) -> Vec<Value> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        vec![notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": document.lsp_diagnostics() }),
+        )]
+    }
+
+    /// The document, and the 0-based line and word a position refers to
+    fn word_at(&self, params: &Value) -> Option<(&Document, usize, String)> {
+        let document = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        let (word, _, _) = document.word_at(line, character)?;
+        Some((document, line, word))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((document, line, word)) = self.word_at(params) else {
+            return Value::Null;
+        };
+        let contents = match document.resolve(&word, line) {
+            Some(symbol) => format!(
+                "**{}** = `{:#06X}`, defined on line {}",
+                symbol.name, symbol.value, symbol.defined
+            ),
+            None => match docs::hover(&word) {
+                Some(text) => text,
+                None => return Value::Null,
+            },
+        };
+        json!({ "contents": { "kind": "markdown", "value": contents } })
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let uri = &params["textDocument"]["uri"];
+        let Some((document, line, word)) = self.word_at(params) else {
+            return Value::Null;
+        };
+        let Some(symbol) = document.resolve(&word, line) else {
+            return Value::Null;
+        };
+        let target = document.occurrences(symbol).into_iter().next();
+        let target = target.unwrap_or_else(|| range(symbol.defined, 0, 0));
+        json!({ "uri": uri, "range": target })
+    }
+
+    fn references(&self, params: &Value) -> Value {
+        let uri = &params["textDocument"]["uri"];
+        let declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let Some((document, line, word)) = self.word_at(params) else {
+            return json!([]);
+        };
+        let Some(symbol) = document.resolve(&word, line) else {
+            return json!([]);
+        };
+        let locations: Vec<Value> = document
+            .occurrences(symbol)
+            .into_iter()
+            .filter(|r| declaration || r["start"]["line"] != json!(symbol.defined - 1))
+            .map(|r| json!({ "uri": uri, "range": r }))
+            .collect();
+        json!(locations)
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let mut items: Vec<Value> = docs::mnemonics()
+            .map(|(name, summary)| {
+                json!({ "label": name, "kind": COMPLETION_KEYWORD, "detail": summary })
+            })
+            .collect();
+        items.extend(
+            (0..16).map(|n| json!({ "label": format!("R{:X}", n), "kind": COMPLETION_VARIABLE })),
+        );
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        if let Some(document) = self.documents.get(uri) {
+            items.extend(document.symbols().iter().map(|s| {
+                json!({
+                    "label": s.name,
+                    "kind": COMPLETION_CONSTANT,
+                    "detail": format!("{:#06X}", s.value),
+                })
+            }));
+        }
+        json!(items)
+    }
+
+    fn document_symbols(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+        let Some(document) = self.documents.get(uri) else {
+            return json!([]);
+        };
+        let mut symbols: Vec<_> = document.symbols().iter().collect();
+        symbols.sort_by_key(|s| s.defined);
+        let symbols: Vec<Value> = symbols
+            .into_iter()
+            .map(|s| {
+                let line = document.text.lines().nth(s.defined - 1).unwrap_or("");
+                let whole = range(s.defined, 0, line.chars().count());
+                let name = document.occurrences(s).into_iter().next();
+                json!({
+                    "name": s.name,
+                    "detail": format!("{:#06X}", s.value),
+                    "kind": SYMBOL_CONSTANT,
+                    "range": whole,
+                    "selectionRange": name.unwrap_or_else(|| whole.clone()),
+                })
+            })
+            .collect();
+        json!(symbols)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "completionProvider": { "triggerCharacters": ["."] },
+            "documentSymbolProvider": true,
+        },
+        "serverInfo": { "name": "rca1802-lsp", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or("").to_string()
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Read one `Content-Length` framed message, or `None` at end of input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write one message with its `Content-Length` header
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Serve one client until it sends `exit` or closes the input
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///tmp/rca1802-lsp-test/main.asm";
+    const SOURCE: &str = "START:  LDI 0x10
+        PLO R3
+MAIN:   GLO R3
+.LOOP:  DEC R3
+        BNZ .LOOP
+        BR MAIN
+";
+
+    /// Run a scripted session and return every message the server sent
+    fn session(requests: &[Value]) -> Vec<Value> {
+        let mut input = Vec::new();
+        for request in requests {
+            write_message(&mut input, request).unwrap();
+        }
+        let mut output = Vec::new();
+        run(&input[..], &mut output).unwrap();
+
+        let mut replies = Vec::new();
+        let mut reader = &output[..];
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            replies.push(message);
+        }
+        replies
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn open(text: &str) -> Value {
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "asm1802", "version": 1, "text": text } }),
+        )
+    }
+
+    fn at(line: u32, character: u32) -> Value {
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+    }
+
+    fn result(replies: &[Value], id: u64) -> &Value {
+        &replies.iter().find(|r| r["id"] == json!(id)).unwrap()["result"]
+    }
+
+    #[test]
+    fn test_lifecycle_and_diagnostics() {
+        let replies = session(&[
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            open("LDI 0x10\nPHY R3\nBR NOWHERE"),
+            notification(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": "START: BR START\nGLO R1" }],
+                }),
+            ),
+            request(2, "shutdown", Value::Null),
+            notification("exit", Value::Null),
+            request(3, "initialize", json!({})),
+        ]);
+
+        assert_eq!(replies.len(), 4);
+        assert_eq!(result(&replies, 1)["capabilities"]["hoverProvider"], true);
+
+        let diagnostics = &replies[1]["params"]["diagnostics"];
+        let messages: Vec<&str> = diagnostics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["message"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["Invalid instruction: PHY", "Undefined label: NOWHERE"]
+        );
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(
+            diagnostics[0]["range"]["start"],
+            json!({ "line": 1, "character": 0 })
+        );
+
+        let warnings = &replies[2]["params"]["diagnostics"];
+        assert_eq!(warnings[0]["severity"], 2);
+        assert_eq!(warnings[0]["code"], "L004");
+        assert_eq!(result(&replies, 2), &Value::Null);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let replies = session(&[
+            open(SOURCE),
+            request(1, "textDocument/hover", at(1, 9)),
+            request(2, "textDocument/hover", at(5, 12)),
+            request(3, "textDocument/definition", at(4, 14)),
+            request(
+                4,
+                "textDocument/references",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 2, "character": 1 },
+                    "context": { "includeDeclaration": true },
+                }),
+            ),
+            request(5, "textDocument/completion", at(2, 0)),
+            request(
+                6,
+                "textDocument/documentSymbol",
+                json!({ "textDocument": { "uri": URI } }),
+            ),
+            request(7, "textDocument/formatting", json!({})),
+        ]);
+
+        let hover = result(&replies, 1)["contents"]["value"].as_str().unwrap();
+        assert!(hover.starts_with("**PLO** - Put low"));
+        assert!(hover.contains("`AN` · 1 bytes"));
+        let hover = result(&replies, 2)["contents"]["value"].as_str().unwrap();
+        assert_eq!(hover, "**MAIN** = `0x0003`, defined on line 3");
+
+        let definition = result(&replies, 3);
+        assert_eq!(definition["uri"], URI);
+        assert_eq!(
+            definition["range"]["start"],
+            json!({ "line": 3, "character": 0 })
+        );
+
+        let references = result(&replies, 4).as_array().unwrap();
+        let lines: Vec<u64> = references
+            .iter()
+            .map(|r| r["range"]["start"]["line"].as_u64().unwrap())
+            .collect();
+        assert_eq!(lines, vec![2, 5]);
+
+        let labels: Vec<&str> = result(&replies, 5)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["label"].as_str().unwrap())
+            .collect();
+        assert!(labels.contains(&"LDI") && labels.contains(&"RF"));
+        assert!(labels.contains(&"MAIN.LOOP"));
+
+        let symbols: Vec<&str> = result(&replies, 6)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(symbols, vec!["START", "MAIN", "MAIN.LOOP"]);
+
+        let unknown = replies.iter().find(|r| r["id"] == json!(7)).unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
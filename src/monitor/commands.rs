@@ -0,0 +1,482 @@
+use super::{Monitor, MonitorError, parse_byte, parse_hex};
+use crate::assembler::{AssembleOptions, assemble_with, is_pseudo};
+use crate::cpu::{Cpu, Instruction, isa};
+use std::fmt::Write;
+
+/// Bytes `m` shows when given no length
+const EXAMINE_LENGTH: u32 = 0x40;
+
+/// Instructions `u` shows when given no count
+const DISASSEMBLE_COUNT: u32 = 8;
+
+/// Bytes per `m` line
+const BYTES_PER_LINE: usize = 16;
+
+/// Data directive names
+const DATA_DIRECTIVES: &[&str] = &["DB", "DW", "BYTE", "WORD"];
+
+/// A register the monitor can show, set or check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    R(u8),
+    D,
+    DF,
+    P,
+    X,
+    Q,
+    IE,
+    /// R(P)
+    PC,
+}
+
+impl Register {
+    fn parse(text: &str) -> Option<Self> {
+        let upper = text.to_uppercase();
+        Some(match upper.as_str() {
+            "D" => Self::D,
+            "DF" => Self::DF,
+            "P" => Self::P,
+            "X" => Self::X,
+            "Q" => Self::Q,
+            "IE" => Self::IE,
+            "PC" => Self::PC,
+            _ => {
+                let digit = upper.strip_prefix('R').filter(|n| n.len() == 1)?;
+                Self::R(u8::from_str_radix(digit, 16).ok()?)
+            }
+        })
+    }
+
+    fn get(self, cpu: &Cpu) -> u16 {
+        match self {
+            Self::R(n) => cpu.registers[n as usize],
+            Self::D => cpu.d as u16,
+            Self::DF => cpu.df as u16,
+            Self::P => cpu.p as u16,
+            Self::X => cpu.x as u16,
+            Self::Q => cpu.q as u16,
+            Self::IE => cpu.ie as u16,
+            Self::PC => cpu.get_pc(),
+        }
+    }
+
+    /// Set the register, rejecting values wider than it
+    fn set(self, cpu: &mut Cpu, value: u16) -> Result<(), MonitorError> {
+        let max = match self {
+            Self::R(_) | Self::PC => 0xFFFF,
+            Self::D => 0xFF,
+            Self::P | Self::X => 0xF,
+            Self::DF | Self::Q | Self::IE => 1,
+        };
+        if value > max {
+            return Err(MonitorError::InvalidNumber(format!("{:X}", value)));
+        }
+        match self {
+            Self::R(n) => cpu.registers[n as usize] = value,
+            Self::D => cpu.d = value as u8,
+            Self::DF => cpu.df = value == 1,
+            Self::P => cpu.p = value as u8,
+            Self::X => cpu.x = value as u8,
+            Self::Q => cpu.q = value == 1,
+            Self::IE => cpu.ie = value == 1,
+            Self::PC => cpu.set_pc(value),
+        }
+        Ok(())
+    }
+
+    /// The value in as many hex digits as the register holds
+    fn show(self, cpu: &Cpu) -> String {
+        let value = self.get(cpu);
+        match self {
+            Self::R(_) | Self::PC => format!("{:04X}", value),
+            Self::D => format!("{:02X}", value),
+            _ => format!("{:X}", value),
+        }
+    }
+}
+
+/// D, the flags, P and X on one line, then R0-RF four to a line
+pub fn register_dump(cpu: &Cpu) -> String {
+    let mut out = format!(
+        "D={:02X} DF={} Q={} IE={} P={:X} X={:X}\n",
+        cpu.d, cpu.df as u8, cpu.q as u8, cpu.ie as u8, cpu.p, cpu.x
+    );
+    for (row, values) in cpu.registers.chunks(4).enumerate() {
+        let cells: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("R{:X}={:04X}", row * 4 + i, v))
+            .collect();
+        out.push_str(&cells.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+/// An executed instruction: its address, bytes and text, and D, DF, Q, P
+/// and X as it left them
+pub fn trace_line(cpu: &Cpu, pc: u16, instruction: &Instruction) -> String {
+    format!(
+        "{:04X}  {:<9} {:<16} D={:02X} DF={} Q={} P={:X} X={:X}",
+        pc,
+        hex_bytes(&instruction.encode()),
+        instruction.to_string(),
+        cpu.d,
+        cpu.df as u8,
+        cpu.q as u8,
+        cpu.p,
+        cpu.x
+    )
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    hex.join(" ")
+}
+
+impl Monitor {
+    /// `m [ADDR] [LEN]`: hex and ASCII dump
+    pub(super) fn examine(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let start = match args.first() {
+            Some(text) => self.address(text)?,
+            None => self.next_examine,
+        };
+        let length = match args.get(1) {
+            Some(text) => self.length(text)?,
+            None => EXAMINE_LENGTH.min(0x10000 - start as u32),
+        };
+        let bytes = self.range(start, length)?;
+        let mut out = String::new();
+        for (i, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            let address = start as usize + i * BYTES_PER_LINE;
+            let _ = writeln!(out, "{:04X}  {:<48} {}", address, hex_bytes(chunk), ascii);
+        }
+        self.next_examine = start.wrapping_add(length as u16);
+        Ok(out)
+    }
+
+    /// `d ADDR BYTE...`: store bytes from ADDR on
+    pub(super) fn deposit(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let [start, bytes @ ..] = args else {
+            return Err(MonitorError::Usage("d ADDR BYTE..."));
+        };
+        if bytes.is_empty() {
+            return Err(MonitorError::Usage("d ADDR BYTE..."));
+        }
+        let start = self.address(start)?;
+        let bytes = bytes
+            .iter()
+            .map(|b| parse_byte(b))
+            .collect::<Result<Vec<u8>, _>>()?;
+        self.range(start, bytes.len() as u32)?;
+        let at = start as usize;
+        self.cpu.memory[at..at + bytes.len()].copy_from_slice(&bytes);
+        Ok(String::new())
+    }
+
+    /// `r [REG VALUE]`: show every register or set one
+    pub(super) fn registers(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        match args {
+            [] => Ok(register_dump(&self.cpu)),
+            [name, value] => {
+                let register = Register::parse(name)
+                    .ok_or_else(|| MonitorError::UnknownRegister(name.to_string()))?;
+                let value = self.value(value)?;
+                register.set(&mut self.cpu, value)?;
+                Ok(String::new())
+            }
+            _ => Err(MonitorError::Usage("r [REG VALUE]")),
+        }
+    }
+
+    /// `a [ADDR] STATEMENT`: assemble one statement into memory
+    ///
+    /// A first word that is an instruction, data directive or
+    /// pseudo-instruction starts the statement, even where it would also
+    /// read as hex (`ADD`, `DEC`, `DB`, `ADD16`).
+    pub(super) fn assemble(&mut self, rest: &str) -> Result<String, MonitorError> {
+        let (first, after) = rest
+            .split_once(char::is_whitespace)
+            .map_or((rest, ""), |(f, a)| (f, a.trim()));
+        let upper = first.to_uppercase();
+        let mnemonic = isa::lookup(&upper).is_some()
+            || DATA_DIRECTIVES.contains(&upper.as_str())
+            || is_pseudo(&upper);
+        let statement = match self.address(first) {
+            Ok(address) if !mnemonic => {
+                self.next_assemble = address;
+                after
+            }
+            _ => rest,
+        };
+        if statement.is_empty() {
+            return Ok(String::new());
+        }
+
+        let source = format!("        ORG 0x{:04X}\n{}\n", self.next_assemble, statement);
+        let options = AssembleOptions {
+            file_name: "monitor".to_string(),
+            scrt: false,
+            ..Default::default()
+        };
+        let output = assemble_with(&source, &options).map_err(|error| {
+            let message = error
+                .diagnostics()
+                .iter()
+                .find(|d| d.is_error())
+                .map_or_else(|| error.to_string(), |d| d.message.clone());
+            MonitorError::Assembly(message)
+        })?;
+
+        let mut out = String::new();
+        for segment in output.segments() {
+            self.range(segment.address, segment.data.len() as u32)?;
+            let at = segment.address as usize;
+            self.cpu.memory[at..at + segment.data.len()].copy_from_slice(&segment.data);
+            let _ = writeln!(out, "{:04X}  {}", segment.address, hex_bytes(&segment.data));
+            self.next_assemble = segment.address.wrapping_add(segment.data.len() as u16);
+        }
+        Ok(out)
+    }
+
+    /// `u [ADDR] [COUNT]`: disassemble COUNT instructions in a straight line
+    pub(super) fn disassemble(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let mut address = match args.first() {
+            Some(text) => self.address(text)?,
+            None => self.next_disassemble,
+        };
+        let count = match args.get(1) {
+            Some(text) => self.length(text)?,
+            None => DISASSEMBLE_COUNT,
+        };
+        let mut out = String::new();
+        for _ in 0..count {
+            let bytes: Vec<u8> = (0..3)
+                .map(|i| self.cpu.memory[address.wrapping_add(i) as usize])
+                .collect();
+            let (length, text) = match Instruction::decode(&bytes) {
+                Some(instruction) => (instruction.opcode.length(), instruction.to_string()),
+                None => (1, format!("DB 0x{:02X}", bytes[0])),
+            };
+            let _ = writeln!(
+                out,
+                "{:04X}  {:<9} {}",
+                address,
+                hex_bytes(&bytes[..length as usize]),
+                text
+            );
+            address = address.wrapping_add(length as u16);
+        }
+        self.next_disassemble = address;
+        Ok(out)
+    }
+
+    /// `b [ADDR]`: set a breakpoint, or list them all
+    pub(super) fn set_breakpoint(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        match args {
+            [] => Ok(self
+                .breakpoints
+                .iter()
+                .map(|b| format!("{:04X}\n", b))
+                .collect()),
+            [address] => {
+                self.breakpoints.insert(self.address(address)?);
+                Ok(String::new())
+            }
+            _ => Err(MonitorError::Usage("b [ADDR]")),
+        }
+    }
+
+    /// `c [ADDR]`: clear one breakpoint, or all of them
+    pub(super) fn clear_breakpoint(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        match args {
+            [] => self.breakpoints.clear(),
+            [address] => {
+                let address = self.address(address)?;
+                self.breakpoints.remove(&address);
+            }
+            _ => return Err(MonitorError::Usage("c [ADDR]")),
+        }
+        Ok(String::new())
+    }
+
+    /// `s [COUNT]`: execute instructions, printing each
+    pub(super) fn step(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let count = match args.first() {
+            Some(text) => self.length(text)?,
+            None => 1,
+        };
+        let mut out = String::new();
+        for _ in 0..count {
+            let pc = self.cpu.get_pc();
+            let instruction = self.cpu.step()?;
+            let _ = writeln!(out, "{}", trace_line(&self.cpu, pc, &instruction));
+        }
+        self.next_disassemble = self.cpu.get_pc();
+        Ok(out)
+    }
+
+    /// `g [ADDR]`: run until a breakpoint, IDL or the cycle limit
+    ///
+    /// A breakpoint at the starting address does not stop the first
+    /// instruction, so `g` continues from a breakpoint.
+    pub(super) fn go(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        if let Some(text) = args.first() {
+            let address = self.address(text)?;
+            self.cpu.set_pc(address);
+            self.cpu.halted = false;
+        }
+        let end = self.cpu.cycles + self.max_cycles;
+        let stop = loop {
+            if self.cpu.cycles >= end {
+                break "Cycle limit reached";
+            }
+            self.cpu.step()?;
+            if self.cpu.is_halted() {
+                break "Halted";
+            }
+            if self.breakpoints.contains(&self.cpu.get_pc()) {
+                break "Breakpoint";
+            }
+        };
+        let pc = self.cpu.get_pc();
+        self.next_disassemble = pc;
+        Ok(format!(
+            "{} at {:04X}\n{}",
+            stop,
+            pc,
+            register_dump(&self.cpu)
+        ))
+    }
+
+    /// `f ADDR LEN BYTE`: fill a range with one byte
+    pub(super) fn fill(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let [start, length, byte] = args else {
+            return Err(MonitorError::Usage("f ADDR LEN BYTE"));
+        };
+        let (start, length) = (self.address(start)?, self.length(length)?);
+        let byte = parse_byte(byte)?;
+        self.range(start, length)?;
+        let at = start as usize;
+        self.cpu.memory[at..at + length as usize].fill(byte);
+        Ok(String::new())
+    }
+
+    /// `v FROM TO LEN`: copy a range, which may overlap its destination
+    pub(super) fn move_memory(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let [from, to, length] = args else {
+            return Err(MonitorError::Usage("v FROM TO LEN"));
+        };
+        let (from, to) = (self.address(from)?, self.address(to)?);
+        let length = self.length(length)?;
+        self.range(from, length)?;
+        self.range(to, length)?;
+        let from = from as usize;
+        self.cpu
+            .memory
+            .copy_within(from..from + length as usize, to as usize);
+        Ok(String::new())
+    }
+
+    /// `h ADDR LEN BYTE...`: list every address in the range where the
+    /// bytes occur
+    pub(super) fn hunt(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let [start, length, pattern @ ..] = args else {
+            return Err(MonitorError::Usage("h ADDR LEN BYTE..."));
+        };
+        if pattern.is_empty() {
+            return Err(MonitorError::Usage("h ADDR LEN BYTE..."));
+        }
+        let (start, length) = (self.address(start)?, self.length(length)?);
+        let pattern = pattern
+            .iter()
+            .map(|b| parse_byte(b))
+            .collect::<Result<Vec<u8>, _>>()?;
+        let found: String = self
+            .range(start, length)?
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| *window == pattern.as_slice())
+            .map(|(i, _)| format!("{:04X}\n", start as usize + i))
+            .collect();
+        Ok(if found.is_empty() {
+            "Not found\n".to_string()
+        } else {
+            found
+        })
+    }
+
+    /// `expect REG VALUE` or `expect ADDR BYTE...`: fail unless the machine
+    /// holds what the script expects
+    ///
+    /// Register names win over hex addresses: `expect d 5` checks D.
+    pub(super) fn expect(&mut self, args: &[&str]) -> Result<String, MonitorError> {
+        let [target, values @ ..] = args else {
+            return Err(MonitorError::Usage("expect REG VALUE | ADDR BYTE..."));
+        };
+        if let Some(register) = Register::parse(target) {
+            let [value] = values else {
+                return Err(MonitorError::Usage("expect REG VALUE"));
+            };
+            let expected = self.value(value)?;
+            if register.get(&self.cpu) != expected {
+                return Err(MonitorError::Expectation(format!(
+                    "{} = {}, expected {:X}",
+                    target.to_uppercase(),
+                    register.show(&self.cpu),
+                    expected
+                )));
+            }
+            return Ok(String::new());
+        }
+
+        let start = self.address(target)?;
+        let expected = values
+            .iter()
+            .map(|b| parse_byte(b))
+            .collect::<Result<Vec<u8>, _>>()?;
+        let actual = self.range(start, expected.len() as u32)?;
+        if actual != expected.as_slice() {
+            return Err(MonitorError::Expectation(format!(
+                "{:04X} = {}, expected {}",
+                start,
+                hex_bytes(actual),
+                hex_bytes(&expected)
+            )));
+        }
+        Ok(String::new())
+    }
+
+    /// Memory from `start` for `length` bytes, if it fits
+    fn range(&self, start: u16, length: u32) -> Result<&[u8], MonitorError> {
+        let end = start as usize + length as usize;
+        self.cpu
+            .memory
+            .get(start as usize..end)
+            .ok_or(MonitorError::OutOfRange { start, length })
+    }
+
+    /// A count or length in hex
+    fn length(&self, text: &str) -> Result<u32, MonitorError> {
+        parse_hex(text)
+            .filter(|&n| n <= 0x10000)
+            .ok_or_else(|| MonitorError::InvalidNumber(text.to_string()))
+    }
+
+    /// A 16-bit value in hex
+    fn value(&self, text: &str) -> Result<u16, MonitorError> {
+        parse_hex(text)
+            .and_then(|v| u16::try_from(v).ok())
+            .ok_or_else(|| MonitorError::InvalidNumber(text.to_string()))
+    }
+}
@@ -0,0 +1,323 @@
+//! Machine-language monitor
+//!
+//! A line-oriented command set in the style of IDIOT/4 and the Elf/OS
+//! monitor, driving a [`Cpu`]: examine and deposit memory, show and set
+//! registers, assemble and disassemble single lines, set breakpoints, step
+//! and run, and fill, move and search memory. Addresses and bytes are hex.
+//!
+//! The same commands are read from the terminal or from a script; a script
+//! produces a transcript with each command echoed, and `expect` lines make
+//! a script a regression test.
+
+mod commands;
+
+pub use commands::{register_dump, trace_line};
+
+use crate::cpu::{Cpu, CpuError};
+use std::collections::{BTreeSet, HashMap};
+use thiserror::Error;
+
+/// Machine cycles `g` runs before giving up
+pub const DEFAULT_MAX_CYCLES: u64 = 10_000_000;
+
+const HELP: &str = "\
+m [ADDR] [LEN]        examine memory
+d ADDR BYTE...        deposit bytes
+r [REG VALUE]         show registers, or set R0-RF, D, DF, P, X, Q, IE or PC
+a [ADDR] STATEMENT    assemble one line
+u [ADDR] [COUNT]      disassemble
+b [ADDR]              set a breakpoint, or list them
+c [ADDR]              clear a breakpoint, or all of them
+s [COUNT]             step instructions
+g [ADDR]              run until a breakpoint, IDL or the cycle limit
+f ADDR LEN BYTE       fill memory
+v FROM TO LEN         move memory
+h ADDR LEN BYTE...    hunt for a byte sequence
+expect REG VALUE      fail unless a register holds VALUE
+expect ADDR BYTE...   fail unless memory holds the bytes
+reset                 reset the CPU, keeping memory and breakpoints
+q                     quit";
+
+/// Errors from a monitor command
+#[derive(Debug, Error, Clone)]
+pub enum MonitorError {
+    #[error("Unknown command: {0} (? for help)")]
+    UnknownCommand(String),
+
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+
+    #[error("Invalid number: {0}")]
+    InvalidNumber(String),
+
+    #[error("Unknown register: {0}")]
+    UnknownRegister(String),
+
+    #[error("Range {start:#06X}+{length:#X} runs past the end of memory")]
+    OutOfRange { start: u16, length: u32 },
+
+    #[error("{0}")]
+    Assembly(String),
+
+    #[error("Expectation failed: {0}")]
+    Expectation(String),
+
+    #[error(transparent)]
+    Cpu(#[from] CpuError),
+}
+
+/// Monitor state around a CPU
+pub struct Monitor {
+    pub cpu: Cpu,
+    breakpoints: BTreeSet<u16>,
+    /// Labels usable in place of addresses
+    symbols: HashMap<String, u16>,
+    /// Where `m`, `u` and `a` continue when given no address
+    next_examine: u16,
+    next_disassemble: u16,
+    next_assemble: u16,
+    /// Machine cycles `g` runs before stopping
+    pub max_cycles: u64,
+    quit: bool,
+}
+
+impl Monitor {
+    pub fn new(cpu: Cpu) -> Self {
+        let pc = cpu.get_pc();
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            symbols: HashMap::new(),
+            next_examine: pc,
+            next_disassemble: pc,
+            next_assemble: pc,
+            max_cycles: DEFAULT_MAX_CYCLES,
+            quit: false,
+        }
+    }
+
+    /// Let `name` stand for `address` in commands
+    pub fn define(&mut self, name: &str, address: u16) {
+        self.symbols.insert(name.to_uppercase(), address);
+    }
+
+    /// Whether `q` has been entered
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Carry out one command line, returning what it prints
+    ///
+    /// Blank lines and lines starting with `;` do nothing.
+    pub fn execute(&mut self, line: &str) -> Result<String, MonitorError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            return Ok(String::new());
+        }
+        let (command, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(c, r)| (c, r.trim()));
+        let args: Vec<&str> = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|a| !a.is_empty())
+            .collect();
+        match command.to_lowercase().as_str() {
+            "m" => self.examine(&args),
+            "d" => self.deposit(&args),
+            "r" => self.registers(&args),
+            "a" => self.assemble(rest),
+            "u" => self.disassemble(&args),
+            "b" => self.set_breakpoint(&args),
+            "c" => self.clear_breakpoint(&args),
+            "s" => self.step(&args),
+            "g" => self.go(&args),
+            "f" => self.fill(&args),
+            "v" => self.move_memory(&args),
+            "h" => self.hunt(&args),
+            "expect" => self.expect(&args),
+            "reset" => {
+                let memory = std::mem::take(&mut self.cpu.memory);
+                self.cpu = Cpu::new();
+                self.cpu.memory = memory;
+                Ok(register_dump(&self.cpu))
+            }
+            "?" | "help" => Ok(format!("{}\n", HELP)),
+            "q" | "quit" => {
+                self.quit = true;
+                Ok(String::new())
+            }
+            _ => Err(MonitorError::UnknownCommand(command.to_string())),
+        }
+    }
+
+    /// Run every line of `script`, returning a transcript with each command
+    /// echoed after `> ` and the number of commands that failed
+    ///
+    /// Failures are shown as `error:` lines and do not stop the script.
+    pub fn run_script(&mut self, script: &str) -> (String, usize) {
+        let mut transcript = String::new();
+        let mut failures = 0;
+        for line in script.lines() {
+            if self.quit {
+                break;
+            }
+            let command = line.trim();
+            if command.is_empty() || command.starts_with(';') {
+                continue;
+            }
+            transcript.push_str(&format!("> {}\n", command));
+            match self.execute(command) {
+                Ok(output) => transcript.push_str(&output),
+                Err(error) => {
+                    failures += 1;
+                    transcript.push_str(&format!("error: {}\n", error));
+                }
+            }
+        }
+        (transcript, failures)
+    }
+
+    /// An address: hex, or a label
+    fn address(&self, text: &str) -> Result<u16, MonitorError> {
+        if let Some(&address) = self.symbols.get(&text.to_uppercase()) {
+            return Ok(address);
+        }
+        parse_hex(text)
+            .and_then(|v| u16::try_from(v).ok())
+            .ok_or_else(|| MonitorError::InvalidNumber(text.to_string()))
+    }
+}
+
+/// A hex number, with or without a `0x` or `$` prefix
+fn parse_hex(text: &str) -> Option<u32> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// A byte in hex
+fn parse_byte(text: &str) -> Result<u8, MonitorError> {
+    parse_hex(text)
+        .and_then(|v| u8::try_from(v).ok())
+        .ok_or_else(|| MonitorError::InvalidNumber(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> Monitor {
+        Monitor::new(Cpu::new())
+    }
+
+    #[test]
+    fn test_memory_commands() {
+        let mut monitor = monitor();
+        monitor.execute("d 100 48 49 21").unwrap();
+        assert_eq!(
+            monitor.execute("m 100 3").unwrap(),
+            "0100  48 49 21                                         HI!\n"
+        );
+        monitor.execute("f 200 4 AA").unwrap();
+        monitor.execute("v 100 202 2").unwrap();
+        assert_eq!(
+            &monitor.cpu.memory[0x200..0x205],
+            &[0xAA, 0xAA, 0x48, 0x49, 0]
+        );
+        assert_eq!(monitor.execute("h 0 FFFF 48 49").unwrap(), "0100\n0202\n");
+        assert!(matches!(
+            monitor.execute("f FFFF 2 0"),
+            Err(MonitorError::OutOfRange { start: 0xFFFF, .. })
+        ));
+    }
+
+    #[test]
+    fn test_assemble_and_disassemble() {
+        let mut monitor = monitor();
+        assert_eq!(monitor.execute("a 0 LDI 5").unwrap(), "0000  F8 05\n");
+        assert_eq!(monitor.execute("a DEC R3").unwrap(), "0002  23\n");
+        monitor.execute("a BR 0").unwrap();
+        assert_eq!(
+            monitor.execute("u 0 3").unwrap(),
+            "0000  F8 05     LDI 05\n0002  23        DEC R3\n0003  30 00     BR 00\n"
+        );
+        assert!(matches!(
+            monitor.execute("a LDI"),
+            Err(MonitorError::Assembly(_))
+        ));
+
+        // Directives and pseudo-instructions that read as hex
+        assert_eq!(
+            monitor.execute("a 10 DB 1").unwrap(),
+            "0010  01
+"
+        );
+        assert_eq!(
+            monitor.execute("a DW 0x1234").unwrap(),
+            "0011  12 34
+"
+        );
+        let added = monitor.execute("a ADD16 R7, R8").unwrap();
+        assert!(added.starts_with("0013  88 73 60 87"), "{}", added);
+        assert!(monitor.execute("a POP R7").unwrap().starts_with("001F  60"));
+    }
+
+    #[test]
+    fn test_breakpoints_and_registers() {
+        let mut monitor = monitor();
+        for line in [
+            "a 0 LDI 3",
+            "a PLO R3",
+            "a DEC R3",
+            "a GLO R3",
+            "a BNZ 3",
+            "a IDL",
+        ] {
+            monitor.execute(line).unwrap();
+        }
+        monitor.define("LOOP", 3);
+        monitor.execute("b loop").unwrap();
+        assert_eq!(monitor.execute("b").unwrap(), "0003\n");
+
+        let stop = monitor.execute("g").unwrap();
+        assert!(stop.starts_with("Breakpoint at 0003\n"), "{}", stop);
+        assert!(
+            monitor
+                .execute("s")
+                .unwrap()
+                .starts_with("0003  23        DEC R3")
+        );
+        monitor.execute("c").unwrap();
+        let stop = monitor.execute("g").unwrap();
+        assert!(stop.starts_with("Halted at 0008\n"), "{}", stop);
+
+        monitor.execute("expect r3 0").unwrap();
+        monitor.execute("r r3 1234").unwrap();
+        monitor.execute("r df 1").unwrap();
+        assert_eq!((monitor.cpu.registers[3], monitor.cpu.df), (0x1234, true));
+        assert!(matches!(
+            monitor.execute("expect d 1"),
+            Err(MonitorError::Expectation(_))
+        ));
+        assert!(matches!(
+            monitor.execute("r r16 0"),
+            Err(MonitorError::UnknownRegister(_))
+        ));
+    }
+
+    #[test]
+    fn test_script_transcript() {
+        let script =
+            "; load and run\na 0 LDI 2A\na IDL\ng\nexpect d 2A\nexpect 0 F8 2A\nbogus\nq\nm 0\n";
+        let mut monitor = monitor();
+        let (transcript, failures) = monitor.run_script(script);
+        assert_eq!(failures, 1);
+        assert!(transcript.starts_with("> a 0 LDI 2A\n0000  F8 2A\n> a IDL\n"));
+        assert!(transcript.contains("> bogus\nerror: Unknown command: bogus"));
+        assert!(transcript.ends_with("> q\n"), "{}", transcript);
+        assert!(monitor.is_quit());
+    }
+}
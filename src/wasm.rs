@@ -1,5 +1,5 @@
-use crate::assembler::assemble;
-use crate::cpu::{Cpu, Instruction, execute_instruction};
+use crate::assembler::{AssemblyError, SourceMap, assemble};
+use crate::cpu::{CallStack, Cpu};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
@@ -8,6 +8,8 @@ use wasm_bindgen::prelude::*;
 pub struct WasmCpu {
     cpu: Cpu,
     program_size: usize,
+    source_map: SourceMap,
+    call_stack: CallStack,
 }
 
 /// Register state for JavaScript
@@ -53,6 +55,8 @@ impl WasmCpu {
         Self {
             cpu: Cpu::new(),
             program_size: 0,
+            source_map: SourceMap::default(),
+            call_stack: CallStack::default(),
         }
     }
 
@@ -60,11 +64,16 @@ impl WasmCpu {
     pub fn reset(&mut self) {
         self.cpu.reset();
         self.program_size = 0;
+        self.source_map = SourceMap::default();
+        self.call_stack.clear();
     }
 
     /// Assemble source code and load into memory
+    ///
+    /// On failure the error is the list of diagnostics (file, line, span,
+    /// severity, message, suggestion) so the editor can underline them.
     pub fn assemble(&mut self, source: &str) -> Result<JsValue, JsValue> {
-        let output = assemble(source).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let output = assemble(source).map_err(|e| assembly_error_to_js(&e))?;
 
         self.program_size = output.machine_code.len();
         self.cpu
@@ -78,41 +87,59 @@ impl WasmCpu {
 
         // Clear halt flag
         self.cpu.halted = false;
+        self.source_map = output.source_map.clone();
+        self.call_stack.clear();
 
         // Return assembly output (disassembly)
         serde_wasm_bindgen::to_value(&output).map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
+    /// Source location (file, line, span) of the code at `address`, or
+    /// `null` if no statement emitted it
+    pub fn source_location(&self, address: u16) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.source_map.location(address))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Address a breakpoint on a source line should be placed at
+    pub fn breakpoint_address(&self, file: &str, line: usize) -> Option<u16> {
+        self.source_map.breakpoint_address(file, line)
+    }
+
+    /// Active SCRT and SEP-style calls, outermost first
+    pub fn call_stack(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self.call_stack.frames())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     /// Execute a single instruction
     pub fn step(&mut self) -> Result<JsValue, JsValue> {
         if self.cpu.halted {
             return Err(JsValue::from_str("CPU is halted"));
         }
 
-        let pc = self.cpu.get_pc();
-
-        // Decode instruction at current PC
-        let instr_bytes = (0..3)
-            .filter_map(|i| self.cpu.read_byte(pc.wrapping_add(i)).ok())
-            .collect::<Vec<_>>();
-
-        if instr_bytes.is_empty() {
-            return Err(JsValue::from_str("Failed to read instruction"));
-        }
-
-        let instruction = Instruction::decode(&instr_bytes)
-            .ok_or_else(|| JsValue::from_str("Failed to decode instruction"))?;
+        self.call_stack.observe(&self.cpu);
+        self.cpu
+            .step()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-        // Advance PC by instruction length BEFORE execution
-        // (simulates fetch cycle where PC is incremented as bytes are read)
-        let instruction_length = instruction.opcode.length() as u16;
-        self.cpu.set_pc(pc.wrapping_add(instruction_length));
+        // Return register state
+        self.get_state()
+    }
 
-        // Execute instruction (branches may overwrite PC)
-        execute_instruction(&mut self.cpu, &instruction)
+    /// Execute one instruction, running any subroutine it calls to completion
+    pub fn step_over(&mut self, max_cycles: u32) -> Result<JsValue, JsValue> {
+        self.call_stack
+            .step_over(&mut self.cpu, max_cycles as u64)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.get_state()
+    }
 
-        // Return register state
+    /// Run until the current subroutine returns
+    pub fn step_out(&mut self, max_cycles: u32) -> Result<JsValue, JsValue> {
+        self.call_stack
+            .step_out(&mut self.cpu, max_cycles as u64)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
         self.get_state()
     }
 
@@ -291,6 +318,15 @@ impl WasmCpu {
     }
 }
 
+/// Convert an assembly error into structured diagnostics for JavaScript
+fn assembly_error_to_js(error: &AssemblyError) -> JsValue {
+    if error.diagnostics().is_empty() {
+        return JsValue::from_str(&error.to_string());
+    }
+    serde_wasm_bindgen::to_value(error.diagnostics())
+        .unwrap_or_else(|e| JsValue::from_str(&e.to_string()))
+}
+
 impl Default for WasmCpu {
     fn default() -> Self {
         Self::new()